@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde_json::Value;
+use sqlx::{Any, Database, Encode, Pool, Row, Type, query::Query};
+
+use crate::{models::DbType, sql_utils::TableRef};
+
+/// Broad storage class of a column, used to decide how a JSON value is bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Integer,
+    Float,
+    Decimal,
+    Boolean,
+    Binary,
+    Json,
+    Text,
+    /// SQLite columns declared without a type accept any storage class
+    Untyped,
+}
+
+/// Declared type and derived kind of a single table column.
+#[derive(Debug, Clone)]
+pub struct ColumnType {
+    pub data_type: String,
+    pub kind: ColumnKind,
+    pub nullable: bool,
+}
+
+/// A JSON value converted to the Rust type it will be bound as.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+/// Maps a declared column type to the kind used for binding.
+pub fn classify(data_type: &str, db_type: &DbType) -> ColumnKind {
+    let t = data_type.to_lowercase();
+    match db_type {
+        DbType::Postgres => {
+            if t.ends_with("[]") {
+                ColumnKind::Text
+            } else if matches!(t.as_str(), "smallint" | "integer" | "bigint") {
+                ColumnKind::Integer
+            } else if matches!(t.as_str(), "real" | "double precision") {
+                ColumnKind::Float
+            } else if t.starts_with("numeric") || t == "money" {
+                ColumnKind::Decimal
+            } else if t == "boolean" {
+                ColumnKind::Boolean
+            } else if t == "bytea" {
+                ColumnKind::Binary
+            } else if t == "json" || t == "jsonb" {
+                ColumnKind::Json
+            } else {
+                ColumnKind::Text
+            }
+        }
+        DbType::Mysql => {
+            // COLUMN_TYPE is used here, so tinyint(1) can be told apart from other tinyints.
+            // The type's name is matched whole, so that e.g. point is not taken for an int.
+            let name = t.split(['(', ' ']).next().unwrap_or_default();
+            match name {
+                "tinyint" if t.starts_with("tinyint(1)") => ColumnKind::Boolean,
+                "bool" | "boolean" => ColumnKind::Boolean,
+                "tinyint" | "smallint" | "mediumint" | "int" | "integer" | "bigint" | "year"
+                | "bit" => ColumnKind::Integer,
+                "float" | "double" | "real" => ColumnKind::Float,
+                "decimal" | "dec" | "numeric" | "fixed" => ColumnKind::Decimal,
+                "tinyblob" | "blob" | "mediumblob" | "longblob" | "binary" | "varbinary" => {
+                    ColumnKind::Binary
+                }
+                "json" => ColumnKind::Json,
+                _ => ColumnKind::Text,
+            }
+        }
+        DbType::Sqlite => {
+            // Follows SQLite's type affinity rules (https://sqlite.org/datatype3.html)
+            if t.is_empty() {
+                ColumnKind::Untyped
+            } else if t.contains("bool") {
+                ColumnKind::Boolean
+            } else if t.contains("int") {
+                ColumnKind::Integer
+            } else if t.contains("char") || t.contains("clob") || t.contains("text") {
+                ColumnKind::Text
+            } else if t.contains("blob") {
+                ColumnKind::Binary
+            } else if t.contains("real") || t.contains("floa") || t.contains("doub") {
+                ColumnKind::Float
+            } else if t.contains("json") {
+                ColumnKind::Json
+            } else if t.contains("date") || t.contains("time") {
+                ColumnKind::Text
+            } else {
+                ColumnKind::Decimal
+            }
+        }
+    }
+}
+
+/// Looks up the declared type of every column of a table in a single catalog query.
+pub async fn fetch_column_types(
+    pool: &Pool<Any>,
+    db_type: &DbType,
+    table: &TableRef,
+) -> Result<HashMap<String, ColumnType>, String> {
+    fetch_schema_column_types(pool, db_type, &table.schema, Some(&table.name))
+        .await?
+        .into_values()
+        .next()
+        .ok_or_else(|| format!("Table '{}' not found", table))
+}
+
+/// Looks up the declared column types of every table and view in a schema, or of a single one,
+/// by table name
+pub async fn fetch_schema_column_types(
+    pool: &Pool<Any>,
+    db_type: &DbType,
+    schema: &str,
+    table: Option<&str>,
+) -> Result<HashMap<String, HashMap<String, ColumnType>>, String> {
+    let rows = match db_type {
+        DbType::Postgres => {
+            // format_type gives the full type (e.g. "character varying(255)"), which is also what we CAST to
+            let sql = "
+                SELECT c.relname::text as table_name, a.attname::text as column_name,
+                    format_type(a.atttypid, a.atttypmod) as data_type,
+                    CASE WHEN a.attnotnull THEN 0 ELSE 1 END::bigint as nullable
+                FROM pg_attribute a
+                JOIN pg_class c ON c.oid = a.attrelid
+                JOIN pg_namespace n ON n.oid = c.relnamespace
+                WHERE n.nspname = $1
+                    AND ($2::text IS NULL OR c.relname = $2)
+                    AND c.relkind IN ('r', 'p', 'v', 'm', 'f')
+                    AND a.attnum > 0
+                    AND NOT a.attisdropped
+            ";
+            sqlx::query(sql)
+                .bind(schema)
+                .bind(table)
+                .fetch_all(pool)
+                .await
+        }
+        DbType::Mysql => {
+            let sql = "
+                SELECT CAST(TABLE_NAME AS CHAR) as table_name,
+                    CAST(COLUMN_NAME AS CHAR) as column_name,
+                    CAST(COLUMN_TYPE AS CHAR) as data_type,
+                    CAST(IS_NULLABLE = 'YES' AS SIGNED) as nullable
+                FROM information_schema.columns
+                WHERE table_schema = ? AND (? IS NULL OR table_name = ?)
+            ";
+            sqlx::query(sql)
+                .bind(schema)
+                .bind(table)
+                .bind(table)
+                .fetch_all(pool)
+                .await
+        }
+        DbType::Sqlite => {
+            // an INTEGER PRIMARY KEY is a rowid alias and never NULL, though notnull reports 0
+            let sql = "
+                SELECT m.name as table_name, p.name as column_name, p.type as data_type,
+                    CASE WHEN p.\"notnull\" = 1 OR (p.pk > 0 AND upper(p.type) = 'INTEGER') THEN 0 ELSE 1 END as nullable
+                FROM sqlite_schema m
+                JOIN pragma_table_info(m.name) p
+                WHERE m.type IN ('table', 'view')
+                    AND (?1 IS NULL OR m.name = ?1 COLLATE NOCASE)
+            ";
+            sqlx::query(sql).bind(table).fetch_all(pool).await
+        }
+    }
+    .map_err(|e| e.to_string())?;
+
+    let mut tables: HashMap<String, HashMap<String, ColumnType>> = HashMap::new();
+    for row in rows {
+        let table_name: String = row.try_get("table_name").unwrap_or_default();
+        let name: String = row.try_get("column_name").unwrap_or_default();
+        let data_type: String = row.try_get("data_type").unwrap_or_default();
+        let nullable: i64 = row.try_get("nullable").unwrap_or(1);
+        let kind = classify(&data_type, db_type);
+        tables.entry(table_name).or_default().insert(
+            name,
+            ColumnType {
+                data_type,
+                kind,
+                nullable: nullable != 0,
+            },
+        );
+    }
+    Ok(tables)
+}
+
+/// Returns the primary key columns of a table in key order, empty if it has none
+pub async fn get_primary_key_columns(
+    pool: &Pool<Any>,
+    db_type: &DbType,
+    table: &TableRef,
+) -> Result<Vec<String>, String> {
+    let rows = match db_type {
+        DbType::Postgres => {
+            let sql = "
+                SELECT ku.column_name::text as column_name
+                FROM information_schema.key_column_usage ku
+                JOIN information_schema.table_constraints tc
+                    ON ku.constraint_name = tc.constraint_name
+                    AND ku.table_schema = tc.table_schema
+                    AND ku.table_name = tc.table_name
+                WHERE tc.constraint_type = 'PRIMARY KEY'
+                    AND tc.table_schema = $1
+                    AND tc.table_name = $2
+                ORDER BY ku.ordinal_position
+            ";
+            sqlx::query(sql)
+                .bind(&table.schema)
+                .bind(&table.name)
+                .fetch_all(pool)
+                .await
+        }
+        DbType::Mysql => {
+            let sql = "
+                SELECT CAST(COLUMN_NAME AS CHAR) as column_name
+                FROM information_schema.KEY_COLUMN_USAGE
+                WHERE TABLE_SCHEMA = ?
+                    AND TABLE_NAME = ?
+                    AND CONSTRAINT_NAME = 'PRIMARY'
+                ORDER BY ORDINAL_POSITION
+            ";
+            sqlx::query(sql)
+                .bind(&table.schema)
+                .bind(&table.name)
+                .fetch_all(pool)
+                .await
+        }
+        DbType::Sqlite => {
+            // pk holds the column's 1-based position within the key
+            let sql =
+                "SELECT name as column_name FROM pragma_table_info(?) WHERE pk > 0 ORDER BY pk";
+            sqlx::query(sql).bind(&table.name).fetch_all(pool).await
+        }
+    }
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| row.try_get("column_name").unwrap_or_default())
+        .collect())
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim().to_lowercase().as_str() {
+        "true" | "t" | "1" | "yes" | "y" => Some(true),
+        "false" | "f" | "0" | "no" | "n" => Some(false),
+        _ => None,
+    }
+}
+
+/// Converts a JSON value into the bind value expected by a column of the given kind.
+pub fn to_bind_value(
+    value: &Value,
+    kind: ColumnKind,
+    db_type: &DbType,
+) -> Result<BindValue, String> {
+    // Booleans are stored as integers everywhere except Postgres
+    let from_bool = |b: bool| match db_type {
+        DbType::Postgres => BindValue::Bool(b),
+        DbType::Mysql | DbType::Sqlite => BindValue::Int(b as i64),
+    };
+
+    let bind = match (kind, value) {
+        (_, Value::Null) => BindValue::Null,
+
+        (ColumnKind::Integer, Value::Number(n)) => match n.as_i64() {
+            Some(i) => BindValue::Int(i),
+            // unsigned values above i64::MAX, e.g. MySQL BIGINT UNSIGNED
+            None if n.is_u64() => BindValue::Text(n.to_string()),
+            None => return Err(format!("expected an integer, got {}", n)),
+        },
+        // flags kept in integer columns take booleans as 0 and 1, e.g. on a MySQL tinyint
+        // reported without its (1)
+        (ColumnKind::Integer, Value::String(s)) => match s.trim().parse::<i64>() {
+            Ok(i) => BindValue::Int(i),
+            Err(_) => match s.trim().to_lowercase().as_str() {
+                "true" => BindValue::Int(1),
+                "false" => BindValue::Int(0),
+                _ => return Err(format!("expected an integer, got '{}'", s)),
+            },
+        },
+        (ColumnKind::Integer, Value::Bool(b)) => BindValue::Int(*b as i64),
+
+        (ColumnKind::Float, Value::Number(n)) => BindValue::Float(n.as_f64().unwrap_or_default()),
+        (ColumnKind::Float, Value::String(s)) => match s.trim().parse::<f64>() {
+            Ok(f) => BindValue::Float(f),
+            Err(_) => return Err(format!("expected a number, got '{}'", s)),
+        },
+
+        // decimals travel as text so no precision is lost on the way to the database
+        (ColumnKind::Decimal, Value::Number(n)) => BindValue::Text(n.to_string()),
+        (ColumnKind::Decimal, Value::String(s)) => {
+            if s.trim().parse::<f64>().is_err() {
+                return Err(format!("expected a number, got '{}'", s));
+            }
+            BindValue::Text(s.trim().to_string())
+        }
+
+        (ColumnKind::Boolean, Value::Bool(b)) => from_bool(*b),
+        (ColumnKind::Boolean, Value::Number(n)) => match n.as_i64() {
+            Some(0) => from_bool(false),
+            Some(1) => from_bool(true),
+            _ => return Err(format!("expected a boolean, got {}", n)),
+        },
+        (ColumnKind::Boolean, Value::String(s)) => match parse_bool(s) {
+            Some(b) => from_bool(b),
+            None => return Err(format!("expected a boolean, got '{}'", s)),
+        },
+
+        // binary values are read back as base64, so they are written the same way
+        (ColumnKind::Binary, Value::String(s)) => match BASE64.decode(s.trim()) {
+            Ok(bytes) => BindValue::Bytes(bytes),
+            Err(_) => return Err("expected base64-encoded bytes".to_string()),
+        },
+        (ColumnKind::Binary, Value::Array(items)) => {
+            let bytes: Option<Vec<u8>> = items
+                .iter()
+                .map(|v| v.as_u64().and_then(|n| u8::try_from(n).ok()))
+                .collect();
+            match bytes {
+                Some(b) => BindValue::Bytes(b),
+                None => return Err("expected an array of bytes".to_string()),
+            }
+        }
+
+        // strings are assumed to already hold JSON text, as typed in the grid
+        (ColumnKind::Json, Value::String(s)) => BindValue::Text(s.clone()),
+        (ColumnKind::Json, v) => BindValue::Text(v.to_string()),
+
+        (ColumnKind::Untyped, Value::Bool(b)) => from_bool(*b),
+        (ColumnKind::Untyped, Value::Number(n)) => match n.as_i64() {
+            Some(i) => BindValue::Int(i),
+            None => BindValue::Float(n.as_f64().unwrap_or_default()),
+        },
+
+        (_, Value::String(s)) => BindValue::Text(s.clone()),
+        (ColumnKind::Text | ColumnKind::Untyped, v) => BindValue::Text(v.to_string()),
+
+        (_, v) => return Err(format!("unsupported value {}", v)),
+    };
+
+    Ok(bind)
+}
+
+/// Returns the placeholder for the `index`-th (1-based) bound parameter.
+/// Postgres placeholders are cast to the column type, since text parameters are not implicitly coerced there.
+pub fn placeholder(db_type: &DbType, index: usize, column: &ColumnType) -> String {
+    match db_type {
+        DbType::Postgres => format!("CAST(${} AS {})", index, column.data_type),
+        DbType::Mysql | DbType::Sqlite => "?".to_string(),
+    }
+}
+
+/// Binds a converted value onto a query, for the Any pool as well as the native pools.
+pub fn bind_value<'q, DB>(
+    query: Query<'q, DB, <DB as Database>::Arguments<'q>>,
+    value: BindValue,
+) -> Query<'q, DB, <DB as Database>::Arguments<'q>>
+where
+    DB: Database,
+    bool: Encode<'q, DB> + Type<DB>,
+    i64: Encode<'q, DB> + Type<DB>,
+    f64: Encode<'q, DB> + Type<DB>,
+    String: Encode<'q, DB> + Type<DB>,
+    Vec<u8>: Encode<'q, DB> + Type<DB>,
+    Option<String>: Encode<'q, DB> + Type<DB>,
+{
+    match value {
+        BindValue::Null => query.bind(None::<String>),
+        BindValue::Bool(b) => query.bind(b),
+        BindValue::Int(i) => query.bind(i),
+        BindValue::Float(f) => query.bind(f),
+        BindValue::Text(s) => query.bind(s),
+        BindValue::Bytes(b) => query.bind(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("integer", &DbType::Postgres), ColumnKind::Integer);
+        assert_eq!(
+            classify("numeric(10,2)", &DbType::Postgres),
+            ColumnKind::Decimal
+        );
+        assert_eq!(classify("jsonb", &DbType::Postgres), ColumnKind::Json);
+        assert_eq!(
+            classify("timestamp without time zone", &DbType::Postgres),
+            ColumnKind::Text
+        );
+
+        assert_eq!(classify("tinyint(1)", &DbType::Mysql), ColumnKind::Boolean);
+        assert_eq!(
+            classify("bigint unsigned", &DbType::Mysql),
+            ColumnKind::Integer
+        );
+        assert_eq!(
+            classify("varbinary(16)", &DbType::Mysql),
+            ColumnKind::Binary
+        );
+        assert_eq!(classify("tinyint", &DbType::Mysql), ColumnKind::Integer);
+        assert_eq!(classify("point", &DbType::Mysql), ColumnKind::Text);
+        assert_eq!(
+            classify("multilinestring", &DbType::Mysql),
+            ColumnKind::Text
+        );
+
+        assert_eq!(classify("VARCHAR(20)", &DbType::Sqlite), ColumnKind::Text);
+        assert_eq!(classify("BOOLEAN", &DbType::Sqlite), ColumnKind::Boolean);
+        assert_eq!(classify("", &DbType::Sqlite), ColumnKind::Untyped);
+    }
+
+    #[test]
+    fn test_to_bind_value() {
+        let pg = DbType::Postgres;
+        let mysql = DbType::Mysql;
+
+        assert_eq!(
+            to_bind_value(&json!("42"), ColumnKind::Integer, &pg),
+            Ok(BindValue::Int(42))
+        );
+        assert!(to_bind_value(&json!("abc"), ColumnKind::Integer, &pg).is_err());
+        assert_eq!(
+            to_bind_value(&json!("true"), ColumnKind::Integer, &mysql),
+            Ok(BindValue::Int(1))
+        );
+        assert!(to_bind_value(&json!(1.5), ColumnKind::Integer, &pg).is_err());
+
+        assert_eq!(
+            to_bind_value(&json!(true), ColumnKind::Boolean, &pg),
+            Ok(BindValue::Bool(true))
+        );
+        assert_eq!(
+            to_bind_value(&json!("false"), ColumnKind::Boolean, &mysql),
+            Ok(BindValue::Int(0))
+        );
+
+        assert_eq!(
+            to_bind_value(&json!(12.50), ColumnKind::Decimal, &pg),
+            Ok(BindValue::Text("12.5".to_string()))
+        );
+        assert_eq!(
+            to_bind_value(&json!({"a": 1}), ColumnKind::Json, &pg),
+            Ok(BindValue::Text("{\"a\":1}".to_string()))
+        );
+        assert_eq!(
+            to_bind_value(&json!("O'Reilly\\"), ColumnKind::Text, &mysql),
+            Ok(BindValue::Text("O'Reilly\\".to_string()))
+        );
+        assert_eq!(
+            to_bind_value(&Value::Null, ColumnKind::Integer, &mysql),
+            Ok(BindValue::Null)
+        );
+        assert_eq!(
+            to_bind_value(&json!("aGk="), ColumnKind::Binary, &pg),
+            Ok(BindValue::Bytes(b"hi".to_vec()))
+        );
+        assert!(to_bind_value(&json!("not base64!"), ColumnKind::Binary, &pg).is_err());
+    }
+}
@@ -0,0 +1,451 @@
+use std::borrow::Cow;
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use futures_util::{StreamExt, stream::BoxStream};
+use serde_json::{Map, Value};
+use sqlx::{
+    Column, Executor, Row, TypeInfo, ValueRef,
+    mysql::{MySqlRow, types::MySqlTime},
+    postgres::{PgColumn, PgRow, types::PgInterval},
+    sqlite::SqliteRow,
+    types::{
+        BigDecimal, JsonValue, Uuid,
+        chrono::{DateTime, NaiveDate, NaiveDateTime, Utc},
+    },
+};
+
+use crate::{
+    binding::{BindValue, bind_value},
+    models::{DbType, NativePool, ResultColumn},
+    sql_utils::quote_identifier,
+};
+
+/// Postgres types decode_pg_value reads from their binary values. Columns of any other type are
+/// selected as text, since their binary values can't be read here.
+const PG_DECODED_TYPES: &[&str] = &[
+    "BOOL",
+    "INT2",
+    "INT4",
+    "INT8",
+    "FLOAT4",
+    "FLOAT8",
+    "NUMERIC",
+    "JSON",
+    "JSONB",
+    "UUID",
+    "INTERVAL",
+    "BYTEA",
+    "TEXT",
+    "VARCHAR",
+    "CHAR",
+    "NAME",
+    "TEXT[]",
+    "VARCHAR[]",
+    "CHAR[]",
+    "NAME[]",
+    "INT2[]",
+    "INT4[]",
+    "INT8[]",
+    "FLOAT4[]",
+    "FLOAT8[]",
+    "BOOL[]",
+    "UUID[]",
+    "JSON[]",
+    "JSONB[]",
+];
+
+/// Postgres date and time types, selected as their ISO 8601 JSON strings. chrono can't hold
+/// 'infinity' or years past 262143, which Postgres allows.
+const PG_ISO_TYPES: &[&str] = &["DATE", "TIME", "TIMESTAMP", "TIMESTAMPTZ"];
+
+/// Rows decoded into JSON objects, along with the columns of the result set
+pub struct DecodedRows {
+    pub columns: Vec<ResultColumn>,
+    pub rows: Vec<Value>,
+}
+
+fn timestamp(dt: NaiveDateTime) -> Value {
+    Value::from(dt.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+}
+
+/// NaN and the infinities have no JSON number, so they are kept as Postgres spells them
+fn float(f: f64) -> Value {
+    if f.is_nan() {
+        Value::from("NaN")
+    } else if f.is_infinite() {
+        Value::from(if f > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        Value::from(f)
+    }
+}
+
+fn floats<F: Into<f64>>(v: Vec<F>) -> Value {
+    Value::Array(v.into_iter().map(|f| float(f.into())).collect())
+}
+
+fn bytes(b: Vec<u8>) -> Value {
+    Value::from(BASE64.encode(b))
+}
+
+fn interval(i: PgInterval) -> Value {
+    let secs = i.microseconds / 1_000_000;
+    let micros = (i.microseconds % 1_000_000).abs();
+    let mut s = format!(
+        "{} mons {} days {:02}:{:02}:{:02}",
+        i.months,
+        i.days,
+        secs / 3600,
+        (secs % 3600 / 60).abs(),
+        (secs % 60).abs()
+    );
+    if micros > 0 {
+        s.push_str(&format!(".{:06}", micros));
+    }
+    Value::from(s)
+}
+
+/// Decodes a single Postgres column into JSON based on its type
+pub fn decode_pg_value(row: &PgRow, i: usize) -> Value {
+    match row.try_get_raw(i) {
+        Ok(raw) if !raw.is_null() => {}
+        _ => return Value::Null,
+    }
+
+    let decoded = match row.column(i).type_info().name() {
+        "BOOL" => row.try_get::<bool, _>(i).ok().map(Value::from),
+        "INT2" => row.try_get::<i16, _>(i).ok().map(Value::from),
+        "INT4" => row.try_get::<i32, _>(i).ok().map(Value::from),
+        "INT8" => row.try_get::<i64, _>(i).ok().map(Value::from),
+        "FLOAT4" => row.try_get::<f32, _>(i).ok().map(|f| float(f.into())),
+        "FLOAT8" => row.try_get::<f64, _>(i).ok().map(float),
+        // kept as a string so no precision is lost
+        "NUMERIC" => row
+            .try_get::<BigDecimal, _>(i)
+            .ok()
+            .map(|d| Value::from(d.to_string())),
+        "JSON" | "JSONB" => row.try_get::<JsonValue, _>(i).ok(),
+        "UUID" => row
+            .try_get::<Uuid, _>(i)
+            .ok()
+            .map(|u| Value::from(u.to_string())),
+        "INTERVAL" => row.try_get::<PgInterval, _>(i).ok().map(interval),
+        "BYTEA" => row.try_get::<Vec<u8>, _>(i).ok().map(bytes),
+        "TEXT[]" | "VARCHAR[]" | "CHAR[]" | "NAME[]" => {
+            row.try_get::<Vec<String>, _>(i).ok().map(Value::from)
+        }
+        "INT2[]" => row.try_get::<Vec<i16>, _>(i).ok().map(Value::from),
+        "INT4[]" => row.try_get::<Vec<i32>, _>(i).ok().map(Value::from),
+        "INT8[]" => row.try_get::<Vec<i64>, _>(i).ok().map(Value::from),
+        "FLOAT4[]" => row.try_get::<Vec<f32>, _>(i).ok().map(floats),
+        "FLOAT8[]" => row.try_get::<Vec<f64>, _>(i).ok().map(floats),
+        "BOOL[]" => row.try_get::<Vec<bool>, _>(i).ok().map(Value::from),
+        "UUID[]" => row
+            .try_get::<Vec<Uuid>, _>(i)
+            .ok()
+            .map(|v| Value::from(v.iter().map(|u| u.to_string()).collect::<Vec<_>>())),
+        "JSON[]" | "JSONB[]" => row.try_get::<Vec<JsonValue>, _>(i).ok().map(Value::from),
+        _ => None,
+    };
+
+    // text columns, and the ones selected as text because they have no decoder above
+    decoded
+        .or_else(|| row.try_get_unchecked::<String, _>(i).ok().map(Value::from))
+        .unwrap_or(Value::Null)
+}
+
+/// Decodes a single MySQL column into JSON based on its type
+pub fn decode_mysql_value(row: &MySqlRow, i: usize) -> Value {
+    match row.try_get_raw(i) {
+        Ok(raw) if !raw.is_null() => {}
+        _ => return Value::Null,
+    }
+
+    let decoded = match row.column(i).type_info().name() {
+        "BOOLEAN" => row.try_get::<bool, _>(i).ok().map(Value::from),
+        "TINYINT" => row.try_get::<i8, _>(i).ok().map(Value::from),
+        "SMALLINT" => row.try_get::<i16, _>(i).ok().map(Value::from),
+        "MEDIUMINT" | "INT" => row.try_get::<i32, _>(i).ok().map(Value::from),
+        "BIGINT" => row.try_get::<i64, _>(i).ok().map(Value::from),
+        "TINYINT UNSIGNED" => row.try_get::<u8, _>(i).ok().map(Value::from),
+        "SMALLINT UNSIGNED" => row.try_get::<u16, _>(i).ok().map(Value::from),
+        "MEDIUMINT UNSIGNED" | "INT UNSIGNED" => row.try_get::<u32, _>(i).ok().map(Value::from),
+        "BIGINT UNSIGNED" | "YEAR" | "BIT" => row.try_get::<u64, _>(i).ok().map(Value::from),
+        "FLOAT" => row.try_get::<f32, _>(i).ok().map(|f| float(f.into())),
+        "DOUBLE" => row.try_get::<f64, _>(i).ok().map(float),
+        "DECIMAL" => row
+            .try_get::<BigDecimal, _>(i)
+            .ok()
+            .map(|d| Value::from(d.to_string())),
+        "JSON" => row.try_get::<JsonValue, _>(i).ok(),
+        "DATE" => row
+            .try_get::<NaiveDate, _>(i)
+            .ok()
+            .map(|d| Value::from(d.to_string())),
+        // MySQL TIME is a duration and may be negative or exceed 24 hours
+        "TIME" => row
+            .try_get::<MySqlTime, _>(i)
+            .ok()
+            .map(|t| Value::from(t.to_string())),
+        "DATETIME" => row.try_get::<NaiveDateTime, _>(i).ok().map(timestamp),
+        "TIMESTAMP" => row
+            .try_get::<DateTime<Utc>, _>(i)
+            .ok()
+            .map(|t| Value::from(t.to_rfc3339())),
+        "BINARY" | "VARBINARY" | "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" => {
+            row.try_get::<Vec<u8>, _>(i).ok().map(bytes)
+        }
+        _ => None,
+    };
+
+    decoded
+        .or_else(|| row.try_get_unchecked::<String, _>(i).ok().map(Value::from))
+        .or_else(|| row.try_get_unchecked::<Vec<u8>, _>(i).ok().map(bytes))
+        .unwrap_or(Value::Null)
+}
+
+/// Decodes a single SQLite column into JSON based on the value's storage class
+pub fn decode_sqlite_value(row: &SqliteRow, i: usize) -> Value {
+    let storage = match row.try_get_raw(i) {
+        Ok(raw) if !raw.is_null() => raw.type_info().name().to_string(),
+        _ => return Value::Null,
+    };
+    let declared = row.column(i).type_info().name();
+
+    let decoded = match storage.as_str() {
+        "INTEGER" if declared == "BOOLEAN" => row
+            .try_get_unchecked::<i64, _>(i)
+            .ok()
+            .map(|n| Value::from(n != 0)),
+        "INTEGER" => row.try_get_unchecked::<i64, _>(i).ok().map(Value::from),
+        "REAL" => row.try_get_unchecked::<f64, _>(i).ok().map(float),
+        "BLOB" => row.try_get_unchecked::<Vec<u8>, _>(i).ok().map(bytes),
+        _ => row.try_get_unchecked::<String, _>(i).ok().map(Value::from),
+    };
+
+    decoded.unwrap_or(Value::Null)
+}
+
+fn to_object<R: Row>(row: &R, decode: fn(&R, usize) -> Value) -> Value {
+    let mut obj = Map::new();
+    for (i, col) in row.columns().iter().enumerate() {
+        obj.insert(col.name().to_string(), decode(row, i));
+    }
+    Value::Object(obj)
+}
+
+fn to_result_columns<C: Column>(columns: &[C]) -> Vec<ResultColumn> {
+    columns
+        .iter()
+        .map(|c| ResultColumn {
+            name: c.name().to_string(),
+            data_type: c.type_info().name().to_string(),
+        })
+        .collect()
+}
+
+/// Wraps a Postgres query so that columns of types without a decoder come back as text, keeping
+/// the names, order and declared types of the result's columns. None when no column needs it.
+fn pg_text_query(sql: &str, columns: &[PgColumn]) -> Option<String> {
+    let decoded = |c: &PgColumn| PG_DECODED_TYPES.contains(&c.type_info().name());
+    if columns.iter().all(decoded) {
+        return None;
+    }
+    // the subquery's columns are renamed by position, since names may repeat or be missing
+    let aliases: Vec<String> = (1..=columns.len()).map(|i| format!("c{}", i)).collect();
+    let select: Vec<String> = columns
+        .iter()
+        .zip(&aliases)
+        .map(|(c, alias)| {
+            let value = if decoded(c) {
+                format!("q.{}", alias)
+            } else if PG_ISO_TYPES.contains(&c.type_info().name()) {
+                format!("to_json(q.{}) #>> '{{}}'", alias)
+            } else {
+                format!("q.{}::text", alias)
+            };
+            format!(
+                "{} AS {}",
+                value,
+                quote_identifier(c.name(), &DbType::Postgres)
+            )
+        })
+        .collect();
+    Some(format!(
+        "SELECT {} FROM ({}) AS q({})",
+        select.join(", "),
+        sql.trim_end().trim_end_matches(';'),
+        aliases.join(", ")
+    ))
+}
+
+/// The query to stream rows with: on Postgres, columns of types without a decoder are selected
+/// as text, other databases run the query as is
+pub async fn decodable_sql<'a>(
+    pool: &NativePool,
+    sql: &'a str,
+) -> Result<Cow<'a, str>, sqlx::Error> {
+    match pool {
+        NativePool::Postgres(pool) => {
+            let described = pool.describe(sql).await?;
+            Ok(pg_text_query(sql, described.columns()).map_or(Cow::Borrowed(sql), Cow::Owned))
+        }
+        _ => Ok(Cow::Borrowed(sql)),
+    }
+}
+
+/// Runs a query on the native pool and decodes every row into typed JSON.
+/// When no rows come back, the columns are taken from the prepared statement instead.
+/// Postgres columns are always described first, so the ones selected as text keep their type.
+pub async fn fetch_rows(
+    pool: &NativePool,
+    sql: &str,
+    params: Vec<BindValue>,
+) -> Result<DecodedRows, sqlx::Error> {
+    match pool {
+        NativePool::Postgres(pool) => {
+            let described = pool.describe(sql).await?;
+            let text_query = pg_text_query(sql, described.columns());
+            let mut query = sqlx::query(text_query.as_deref().unwrap_or(sql));
+            for param in params {
+                query = bind_value(query, param);
+            }
+            let rows = query.fetch_all(pool).await?;
+            Ok(DecodedRows {
+                columns: to_result_columns(described.columns()),
+                rows: rows.iter().map(|r| to_object(r, decode_pg_value)).collect(),
+            })
+        }
+        NativePool::Mysql(pool) => {
+            let mut query = sqlx::query(sql);
+            for param in params {
+                query = bind_value(query, param);
+            }
+            let rows = query.fetch_all(pool).await?;
+            let columns = match rows.first() {
+                Some(row) => to_result_columns(row.columns()),
+                None => to_result_columns(pool.describe(sql).await?.columns()),
+            };
+            Ok(DecodedRows {
+                columns,
+                rows: rows
+                    .iter()
+                    .map(|r| to_object(r, decode_mysql_value))
+                    .collect(),
+            })
+        }
+        NativePool::Sqlite(pool) => {
+            let mut query = sqlx::query(sql);
+            for param in params {
+                query = bind_value(query, param);
+            }
+            let rows = query.fetch_all(pool).await?;
+            let columns = match rows.first() {
+                Some(row) => to_result_columns(row.columns()),
+                None => to_result_columns(pool.describe(sql).await?.columns()),
+            };
+            Ok(DecodedRows {
+                columns,
+                rows: rows
+                    .iter()
+                    .map(|r| to_object(r, decode_sqlite_value))
+                    .collect(),
+            })
+        }
+    }
+}
+
+/// Streams a query's rows from the native pool, decoding each into typed JSON as it is read.
+/// Rows are only pulled from the database as fast as the stream is consumed. The query should
+/// come from decodable_sql, so that every Postgres column can be decoded.
+pub fn fetch_rows_stream<'a>(
+    pool: &'a NativePool,
+    sql: &'a str,
+) -> BoxStream<'a, Result<Value, sqlx::Error>> {
+    match pool {
+        NativePool::Postgres(pool) => sqlx::query(sql)
+            .fetch(pool)
+            .map(|row| row.map(|r| to_object(&r, decode_pg_value)))
+            .boxed(),
+        NativePool::Mysql(pool) => sqlx::query(sql)
+            .fetch(pool)
+            .map(|row| row.map(|r| to_object(&r, decode_mysql_value)))
+            .boxed(),
+        NativePool::Sqlite(pool) => sqlx::query(sql)
+            .fetch(pool)
+            .map(|row| row.map(|r| to_object(&r, decode_sqlite_value)))
+            .boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval() {
+        let i = PgInterval {
+            months: 14,
+            days: 3,
+            microseconds: 3_723_000_500,
+        };
+        assert_eq!(interval(i), Value::from("14 mons 3 days 01:02:03.000500"));
+    }
+
+    #[test]
+    fn test_float() {
+        assert_eq!(float(2.5), Value::from(2.5));
+        assert_eq!(float(f64::NAN), Value::from("NaN"));
+        assert_eq!(float(f64::INFINITY), Value::from("Infinity"));
+        assert_eq!(
+            floats(vec![1.0f32, f32::NEG_INFINITY]),
+            serde_json::json!([1.0, "-Infinity"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rows_sqlite() {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query(
+            "CREATE TABLE t (id INTEGER, flag BOOLEAN, price REAL, name TEXT, data BLOB, created DATETIME)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO t VALUES (1, 1, 2.5, 'a', x'6869', '2024-01-02 03:04:05'), (2, 0, NULL, 'b', NULL, NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let native = NativePool::Sqlite(pool);
+        let result = fetch_rows(
+            &native,
+            "SELECT * FROM t WHERE id = ?",
+            vec![BindValue::Int(1)],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(
+            result.rows[0],
+            serde_json::json!({
+                "id": 1,
+                "flag": true,
+                "price": 2.5,
+                "name": "a",
+                "data": "aGk=",
+                "created": "2024-01-02 03:04:05"
+            })
+        );
+        assert_eq!(result.columns[1].data_type, "BOOLEAN");
+
+        // columns are still reported for an empty result
+        let empty = fetch_rows(&native, "SELECT id, name FROM t WHERE id > 10", vec![])
+            .await
+            .unwrap();
+        assert!(empty.rows.is_empty());
+        assert_eq!(empty.columns.len(), 2);
+    }
+}
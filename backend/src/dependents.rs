@@ -0,0 +1,268 @@
+use sqlx::{AnyPool, Row};
+
+use crate::{
+    binding::get_primary_key_columns,
+    lexer::mentions,
+    models::{DbType, DependentObject},
+    routes::schema::fetch_foreign_keys,
+    sql_utils::{TableRef, quote_identifier},
+};
+
+/// What dropping a table, or one of its columns, affects: the foreign keys referencing it, each
+/// with the rows that refer to it, and the views reading it. A dropped column also takes the
+/// table's own foreign keys on it.
+pub async fn fetch_dependents(
+    pool: &AnyPool,
+    db_type: &DbType,
+    table: &TableRef,
+    column: Option<&str>,
+) -> Result<Vec<DependentObject>, String> {
+    // SQLite names are case-insensitive
+    let same = |a: &str, b: &str| match db_type {
+        DbType::Sqlite => a.eq_ignore_ascii_case(b),
+        _ => a == b,
+    };
+    let mut dependents = Vec::new();
+
+    // a SQLite key without columns refers to the primary key
+    let primary_key = get_primary_key_columns(pool, db_type, table).await?;
+    for child in referencing_tables(pool, db_type, table).await? {
+        let is_self = child.schema == table.schema && same(&child.name, &table.name);
+        // a table's references to itself go with it
+        if is_self && column.is_none() {
+            continue;
+        }
+        for fk in fetch_foreign_keys(pool, db_type, &child).await? {
+            if fk.foreign_schema != table.schema || !same(&fk.foreign_table, &table.name) {
+                continue;
+            }
+            let referenced = if fk.foreign_columns.is_empty() {
+                &primary_key
+            } else {
+                &fk.foreign_columns
+            };
+            if let Some(column) = column
+                && !referenced.iter().any(|c| same(c, column))
+            {
+                continue;
+            }
+            let rows = count_referencing_rows(pool, db_type, &child, &fk.columns).await?;
+            dependents.push(DependentObject {
+                object_type: "FOREIGN KEY".to_string(),
+                schema: child.schema.clone(),
+                name: fk.constraint_name,
+                table: Some(child.name.clone()),
+                on_delete: Some(fk.on_delete),
+                rows: Some(rows),
+            });
+        }
+    }
+
+    if let Some(column) = column {
+        for fk in fetch_foreign_keys(pool, db_type, table).await? {
+            let listed = dependents
+                .iter()
+                .any(|d| d.table.as_deref() == Some(&table.name) && d.name == fk.constraint_name);
+            if !listed && fk.columns.iter().any(|c| same(c, column)) {
+                dependents.push(DependentObject {
+                    object_type: "FOREIGN KEY".to_string(),
+                    schema: table.schema.clone(),
+                    name: fk.constraint_name,
+                    table: Some(table.name.clone()),
+                    on_delete: Some(fk.on_delete),
+                    rows: None,
+                });
+            }
+        }
+    }
+
+    for (schema, name) in dependent_views(pool, db_type, table, column).await? {
+        dependents.push(DependentObject {
+            object_type: "VIEW".to_string(),
+            schema,
+            name,
+            table: None,
+            on_delete: None,
+            rows: None,
+        });
+    }
+    Ok(dependents)
+}
+
+/// The rows a drop discards: all rows of a table, or those holding a value in a column
+pub async fn count_dropped_rows(
+    pool: &AnyPool,
+    db_type: &DbType,
+    table: &TableRef,
+    column: Option<&str>,
+) -> Result<i64, String> {
+    let counted = match column {
+        Some(column) => quote_identifier(column, db_type),
+        None => "*".to_string(),
+    };
+    let sql = format!("SELECT COUNT({}) FROM {}", counted, table.quoted(db_type));
+    sqlx::query_scalar(&sql)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tables with a foreign key referencing the table, possibly including itself
+async fn referencing_tables(
+    pool: &AnyPool,
+    db_type: &DbType,
+    table: &TableRef,
+) -> Result<Vec<TableRef>, String> {
+    let rows = match db_type {
+        DbType::Postgres => {
+            sqlx::query(
+                "SELECT DISTINCT cn.nspname::text AS schema_name, ct.relname::text AS name
+                 FROM pg_constraint c
+                 JOIN pg_class ct ON ct.oid = c.conrelid
+                 JOIN pg_namespace cn ON cn.oid = ct.relnamespace
+                 JOIN pg_class ft ON ft.oid = c.confrelid
+                 JOIN pg_namespace fns ON fns.oid = ft.relnamespace
+                 WHERE c.contype = 'f' AND fns.nspname = $1 AND ft.relname = $2",
+            )
+            .bind(&table.schema)
+            .bind(&table.name)
+            .fetch_all(pool)
+            .await
+        }
+        DbType::Mysql => {
+            let sql = format!(
+                "SELECT DISTINCT CAST(TABLE_SCHEMA AS CHAR) as schema_name, CAST(TABLE_NAME AS CHAR) as name
+                 FROM INFORMATION_SCHEMA.KEY_COLUMN_USAGE
+                 WHERE REFERENCED_TABLE_SCHEMA = '{}' AND REFERENCED_TABLE_NAME = '{}'",
+                table.schema.replace("'", "''"),
+                table.name.replace("'", "''")
+            );
+            sqlx::query(&sql).fetch_all(pool).await
+        }
+        DbType::Sqlite => {
+            sqlx::query(
+                "SELECT DISTINCT 'main' AS schema_name, m.name AS name
+                 FROM sqlite_schema m
+                 JOIN pragma_foreign_key_list(m.name) f
+                 WHERE m.type = 'table' AND f.\"table\" = ? COLLATE NOCASE",
+            )
+            .bind(&table.name)
+            .fetch_all(pool)
+            .await
+        }
+    }
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let schema: String = row.try_get("schema_name").unwrap_or_default();
+            let name: String = row.try_get("name").unwrap_or_default();
+            TableRef::new(&schema, &name, db_type)
+        })
+        .collect())
+}
+
+/// Rows of a table whose foreign key columns all hold a value, and so refer to another row
+async fn count_referencing_rows(
+    pool: &AnyPool,
+    db_type: &DbType,
+    table: &TableRef,
+    columns: &[String],
+) -> Result<i64, String> {
+    let conditions: Vec<String> = columns
+        .iter()
+        .map(|c| format!("{} IS NOT NULL", quote_identifier(c, db_type)))
+        .collect();
+    let sql = format!(
+        "SELECT COUNT(*) FROM {} WHERE {}",
+        table.quoted(db_type),
+        conditions.join(" AND ")
+    );
+    sqlx::query_scalar(&sql)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Schemas and names of the views reading a table, or one of its columns. MySQL only records
+/// which tables a view uses, and SQLite nothing, so there views mentioning the names are listed.
+async fn dependent_views(
+    pool: &AnyPool,
+    db_type: &DbType,
+    table: &TableRef,
+    column: Option<&str>,
+) -> Result<Vec<(String, String)>, String> {
+    let rows = match db_type {
+        DbType::Postgres => {
+            // a view depends on each column it reads, and on the table through those
+            let column_filter = if column.is_some() {
+                "AND a.attname = $3"
+            } else {
+                ""
+            };
+            let sql = format!(
+                "SELECT DISTINCT vn.nspname::text AS schema_name, v.relname::text AS name
+                 FROM pg_depend d
+                 JOIN pg_rewrite r ON r.oid = d.objid
+                 JOIN pg_class v ON v.oid = r.ev_class
+                 JOIN pg_namespace vn ON vn.oid = v.relnamespace
+                 JOIN pg_class t ON t.oid = d.refobjid
+                 JOIN pg_namespace tn ON tn.oid = t.relnamespace
+                 LEFT JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = d.refobjsubid
+                 WHERE d.classid = 'pg_rewrite'::regclass
+                   AND tn.nspname = $1 AND t.relname = $2 AND v.oid <> t.oid {}
+                 ORDER BY 1, 2",
+                column_filter
+            );
+            let mut query = sqlx::query(&sql).bind(&table.schema).bind(&table.name);
+            if let Some(column) = column {
+                query = query.bind(column);
+            }
+            query.fetch_all(pool).await
+        }
+        DbType::Mysql => {
+            let sql = format!(
+                "SELECT CAST(v.TABLE_SCHEMA AS CHAR) as schema_name, CAST(v.TABLE_NAME AS CHAR) as name,
+                        CAST(v.VIEW_DEFINITION AS CHAR) as definition
+                 FROM information_schema.VIEW_TABLE_USAGE u
+                 JOIN information_schema.VIEWS v
+                   ON v.TABLE_SCHEMA = u.VIEW_SCHEMA AND v.TABLE_NAME = u.VIEW_NAME
+                 WHERE u.TABLE_SCHEMA = '{}' AND u.TABLE_NAME = '{}'
+                 ORDER BY 1, 2",
+                table.schema.replace("'", "''"),
+                table.name.replace("'", "''")
+            );
+            sqlx::query(&sql).fetch_all(pool).await
+        }
+        DbType::Sqlite => {
+            sqlx::query(
+                "SELECT 'main' AS schema_name, name, sql AS definition
+                 FROM sqlite_schema WHERE type = 'view' ORDER BY name",
+            )
+            .fetch_all(pool)
+            .await
+        }
+    }
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|row| {
+            let definition: Option<String> = match db_type {
+                DbType::Postgres => None,
+                _ => Some(row.try_get("definition").unwrap_or_default()),
+            };
+            definition.is_none_or(|sql| {
+                (matches!(db_type, DbType::Mysql) || mentions(&sql, &table.name))
+                    && column.is_none_or(|c| mentions(&sql, c))
+            })
+        })
+        .map(|row| {
+            (
+                row.try_get("schema_name").unwrap_or_default(),
+                row.try_get("name").unwrap_or_default(),
+            )
+        })
+        .collect())
+}
@@ -0,0 +1,585 @@
+use std::collections::HashMap;
+
+use sqlx::AnyPool;
+
+use crate::{
+    binding::{ColumnType, fetch_schema_column_types},
+    dump::{TableSchema, default_sql},
+    models::{
+        ColumnInfo, DbType, DiffKind, ForeignKeyInfo, IndexInfo, ObjectDiff, SchemaDiff,
+        SchemaSnapshot, TableDiff, TableSnapshot,
+    },
+    rebuild::{TableChange, plan_rebuild},
+    snapshot::load_snapshot,
+    sql_utils::{TableRef, quote_identifier},
+};
+
+/// A schema as the diff sees it: its snapshot, and the declared type of every column, which the
+/// snapshot abbreviates (e.g. "character varying" for "character varying(255)")
+pub struct SchemaModel {
+    pub snapshot: SchemaSnapshot,
+    pub column_types: HashMap<String, HashMap<String, ColumnType>>,
+    pub db_type: DbType,
+}
+
+impl SchemaModel {
+    pub async fn load(pool: &AnyPool, db_type: &DbType, schema: &str) -> Result<Self, String> {
+        Ok(Self {
+            snapshot: load_snapshot(pool, db_type, schema).await?,
+            column_types: fetch_schema_column_types(pool, db_type, schema, None).await?,
+            db_type: db_type.clone(),
+        })
+    }
+
+    /// Base tables, which are all the diff compares
+    fn tables(&self) -> impl Iterator<Item = &TableSnapshot> {
+        self.snapshot
+            .tables
+            .iter()
+            .filter(|t| t.table_type == "TABLE")
+    }
+
+    fn table(&self, name: &str) -> Option<&TableSnapshot> {
+        self.tables().find(|t| t.name == name)
+    }
+
+    fn declared_type<'a>(&'a self, table: &str, column: &'a ColumnInfo) -> &'a str {
+        self.column_types
+            .get(table)
+            .and_then(|types| types.get(&column.name))
+            .map(|t| t.data_type.as_str())
+            .unwrap_or(&column.data_type)
+    }
+
+    /// A table of this schema as the target would create it, its foreign keys referring to
+    /// tables of the target schema
+    fn table_schema(&self, table: &TableSnapshot, target: &SchemaModel) -> TableSchema {
+        let target_schema = &target.snapshot.schema;
+        let foreign_keys = table
+            .foreign_keys
+            .iter()
+            .cloned()
+            .map(|mut fk| {
+                if fk.foreign_schema == self.snapshot.schema {
+                    fk.foreign_schema = target_schema.clone();
+                }
+                fk
+            })
+            .collect();
+        TableSchema {
+            table: TableRef::new(target_schema, &table.name, &target.db_type),
+            columns: table.columns.clone(),
+            column_types: self
+                .column_types
+                .get(&table.name)
+                .cloned()
+                .unwrap_or_default(),
+            primary_key: table.primary_key.clone(),
+            indexes: table.indexes.clone(),
+            foreign_keys,
+        }
+    }
+}
+
+/// Compares the base tables of a target schema with the source's, both of the same kind of
+/// database. Names are matched exactly, types regardless of case and index definitions
+/// regardless of whitespace.
+pub fn diff_schemas(source: &SchemaModel, target: &SchemaModel) -> SchemaDiff {
+    let secondary = |t: &TableSnapshot| -> Vec<IndexInfo> {
+        t.indexes
+            .iter()
+            .filter(|i| !i.is_primary)
+            .cloned()
+            .collect()
+    };
+    let table_diff = |name: &str, kind: DiffKind| TableDiff {
+        name: name.to_string(),
+        kind,
+        columns: Vec::new(),
+        indexes: Vec::new(),
+        foreign_keys: Vec::new(),
+    };
+
+    let mut tables = Vec::new();
+    for table in source.tables() {
+        let Some(existing) = target.table(&table.name) else {
+            tables.push(table_diff(&table.name, DiffKind::Added));
+            continue;
+        };
+        let columns = diff_objects(
+            &table.columns,
+            &existing.columns,
+            |c| &c.name,
+            |a, b| {
+                source
+                    .declared_type(&table.name, a)
+                    .eq_ignore_ascii_case(target.declared_type(&existing.name, b))
+                    && a.nullable == b.nullable
+                    && a.is_primary_key == b.is_primary_key
+                    && a.auto_increment == b.auto_increment
+                    && a.default_value == b.default_value
+            },
+        );
+        let indexes = diff_objects(
+            &secondary(table),
+            &secondary(existing),
+            |i| &i.name,
+            |a, b| {
+                a.is_unique == b.is_unique
+                    && a.column_names == b.column_names
+                    && a.definition.as_deref().map(normalize_whitespace)
+                        == b.definition.as_deref().map(normalize_whitespace)
+            },
+        );
+        let foreign_keys = diff_objects(
+            &table.foreign_keys,
+            &existing.foreign_keys,
+            |f| &f.constraint_name,
+            |a, b| {
+                // references within each schema correspond
+                let same_schema = a.foreign_schema == b.foreign_schema
+                    || (a.foreign_schema == source.snapshot.schema
+                        && b.foreign_schema == target.snapshot.schema);
+                same_schema
+                    && a.foreign_table == b.foreign_table
+                    && a.columns == b.columns
+                    && a.foreign_columns == b.foreign_columns
+                    && a.on_update.eq_ignore_ascii_case(&b.on_update)
+                    && a.on_delete.eq_ignore_ascii_case(&b.on_delete)
+                    && a.deferrable == b.deferrable
+                    && a.initially_deferred == b.initially_deferred
+            },
+        );
+        if !columns.is_empty() || !indexes.is_empty() || !foreign_keys.is_empty() {
+            tables.push(TableDiff {
+                columns,
+                indexes,
+                foreign_keys,
+                ..table_diff(&table.name, DiffKind::Changed)
+            });
+        }
+    }
+    for table in target.tables() {
+        if source.table(&table.name).is_none() {
+            tables.push(table_diff(&table.name, DiffKind::Removed));
+        }
+    }
+
+    SchemaDiff {
+        source_schema: source.snapshot.schema.clone(),
+        target_schema: target.snapshot.schema.clone(),
+        tables,
+    }
+}
+
+/// Collapses runs of whitespace, which SQLite keeps in an index definition as it was written
+fn normalize_whitespace(sql: &str) -> String {
+    sql.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn diff_objects<T: Clone>(
+    source: &[T],
+    target: &[T],
+    name: impl Fn(&T) -> &String,
+    same: impl Fn(&T, &T) -> bool,
+) -> Vec<ObjectDiff<T>> {
+    let mut diffs = Vec::new();
+    for object in source {
+        match target.iter().find(|t| name(t) == name(object)) {
+            None => diffs.push(ObjectDiff {
+                name: name(object).clone(),
+                kind: DiffKind::Added,
+                source: Some(object.clone()),
+                target: None,
+            }),
+            Some(existing) if !same(object, existing) => diffs.push(ObjectDiff {
+                name: name(object).clone(),
+                kind: DiffKind::Changed,
+                source: Some(object.clone()),
+                target: Some(existing.clone()),
+            }),
+            Some(_) => {}
+        }
+    }
+    for object in target {
+        if !source.iter().any(|s| name(s) == name(object)) {
+            diffs.push(ObjectDiff {
+                name: name(object).clone(),
+                kind: DiffKind::Removed,
+                source: None,
+                target: Some(object.clone()),
+            });
+        }
+    }
+    diffs
+}
+
+/// The statements turning the target schema, read through its pool, into the source's, in the
+/// target's dialect. Keys and indexes go first and come back last, so that they never refer to
+/// a column or table in the making. SQLite can't alter a column or a key in place, nor add a
+/// NOT NULL column without a default, so such tables are rebuilt instead, once everything
+/// else is in place.
+pub async fn migration(
+    diff: &SchemaDiff,
+    source: &SchemaModel,
+    target: &SchemaModel,
+    pool: &AnyPool,
+) -> Result<Vec<String>, String> {
+    let db_type = &target.db_type;
+    let mut drops = Vec::new();
+    let mut changes = Vec::new();
+    let mut creates = Vec::new();
+    let mut rebuilds = Vec::new();
+
+    for table_diff in &diff.tables {
+        let (table, existing) = match (
+            source.table(&table_diff.name),
+            target.table(&table_diff.name),
+        ) {
+            (Some(table), Some(existing)) => (table, existing),
+            (Some(table), None) => {
+                let schema = source.table_schema(table, target);
+                changes.push(schema.create_table(db_type));
+                creates.extend(schema.create_indexes(db_type));
+                creates.extend(schema.add_foreign_keys(db_type));
+                continue;
+            }
+            (None, Some(existing)) => {
+                let table = TableRef::new(&target.snapshot.schema, &existing.name, db_type);
+                if !matches!(db_type, DbType::Sqlite) {
+                    for fk in &existing.foreign_keys {
+                        drops.push(drop_foreign_key(&table, fk, db_type));
+                    }
+                }
+                changes.push(format!("DROP TABLE {};\n", table.quoted(db_type)));
+                continue;
+            }
+            (None, None) => continue,
+        };
+        let schema = source.table_schema(table, target);
+        let quoted = schema.table.quoted(db_type);
+        let primary_key_changed = table.primary_key != existing.primary_key;
+
+        // indexes behind SQLite UNIQUE constraints only go with their table
+        let rebuilt = matches!(db_type, DbType::Sqlite)
+            && (primary_key_changed
+                || !table_diff.foreign_keys.is_empty()
+                || table_diff
+                    .columns
+                    .iter()
+                    .any(|c| match (&c.source, &c.target) {
+                        (Some(_), Some(_)) => true,
+                        (Some(col), None) => !col.nullable && col.default_value.is_none(),
+                        (None, _) => false,
+                    })
+                || table_diff
+                    .indexes
+                    .iter()
+                    .any(|i| i.name.starts_with("sqlite_autoindex_")));
+        if rebuilt {
+            let indexes = schema
+                .secondary_indexes()
+                .map(|idx| (idx.name.clone(), schema.create_index(idx, db_type)))
+                .collect();
+            let change = TableChange::Redefine {
+                table_sql: schema.create_table(db_type),
+                indexes,
+            };
+            let plan = plan_rebuild(pool, &existing.name, change).await?;
+            rebuilds.extend(
+                plan.statements()
+                    .into_iter()
+                    .map(|sql| format!("{};\n", sql)),
+            );
+            continue;
+        }
+
+        for index in &table_diff.indexes {
+            if let Some(old) = &index.target {
+                drops.push(drop_index(&schema.table, old, db_type));
+            }
+            if let Some(new) = &index.source {
+                creates.push(schema.create_index(new, db_type));
+            }
+        }
+        for fk in &table_diff.foreign_keys {
+            if let Some(old) = &fk.target {
+                drops.push(drop_foreign_key(&schema.table, old, db_type));
+            }
+            // the schema's copy refers to the target schema
+            if fk.source.is_some()
+                && let Some(new) = schema
+                    .foreign_keys
+                    .iter()
+                    .find(|f| f.constraint_name == fk.name)
+            {
+                creates.push(schema.add_foreign_key(new, db_type));
+            }
+        }
+
+        if primary_key_changed && !existing.primary_key.is_empty() {
+            changes.push(match db_type {
+                DbType::Mysql => format!("ALTER TABLE {} DROP PRIMARY KEY;\n", quoted),
+                _ => {
+                    // the primary key constraint is named after its index
+                    let name = existing
+                        .indexes
+                        .iter()
+                        .find(|i| i.is_primary)
+                        .map(|i| i.name.clone())
+                        .unwrap_or_else(|| format!("{}_pkey", existing.name));
+                    format!(
+                        "ALTER TABLE {} DROP CONSTRAINT {};\n",
+                        quoted,
+                        quote_identifier(&name, db_type)
+                    )
+                }
+            });
+        }
+        for column in &table_diff.columns {
+            match (&column.source, &column.target) {
+                (Some(col), Some(old)) => {
+                    let old_type = target.declared_type(&existing.name, old);
+                    changes.extend(alter_column(&schema, col, old, old_type, db_type));
+                }
+                (Some(col), None) => changes.push(format!(
+                    "ALTER TABLE {} ADD COLUMN {};\n",
+                    quoted,
+                    schema.column_definition(col, db_type)
+                )),
+                (None, _) => changes.push(format!(
+                    "ALTER TABLE {} DROP COLUMN {};\n",
+                    quoted,
+                    quote_identifier(&column.name, db_type)
+                )),
+            }
+        }
+        if primary_key_changed && !table.primary_key.is_empty() {
+            changes.push(format!(
+                "ALTER TABLE {} ADD PRIMARY KEY ({});\n",
+                quoted,
+                schema.quote_all(&table.primary_key, db_type)
+            ));
+        }
+    }
+
+    let mut statements: Vec<String> = drops.into_iter().chain(changes).chain(creates).collect();
+    // SQLite checks foreign keys on every DROP TABLE; the rebuilds suspend and check them
+    // themselves
+    if matches!(db_type, DbType::Sqlite) && !statements.is_empty() {
+        statements.insert(0, "PRAGMA foreign_keys = OFF;\n".to_string());
+        statements.push("PRAGMA foreign_keys = ON;\n".to_string());
+    }
+    statements.extend(rebuilds);
+    Ok(statements)
+}
+
+fn drop_foreign_key(table: &TableRef, fk: &ForeignKeyInfo, db_type: &DbType) -> String {
+    format!(
+        "ALTER TABLE {} DROP {} {};\n",
+        table.quoted(db_type),
+        match db_type {
+            DbType::Mysql => "FOREIGN KEY",
+            _ => "CONSTRAINT",
+        },
+        quote_identifier(&fk.constraint_name, db_type)
+    )
+}
+
+fn drop_index(table: &TableRef, index: &IndexInfo, db_type: &DbType) -> String {
+    match db_type {
+        DbType::Postgres => format!(
+            "DROP INDEX {};\n",
+            TableRef::new(&table.schema, &index.name, db_type).quoted(db_type)
+        ),
+        DbType::Mysql => format!(
+            "DROP INDEX {} ON {};\n",
+            quote_identifier(&index.name, db_type),
+            table.quoted(db_type)
+        ),
+        DbType::Sqlite => format!("DROP INDEX {};\n", quote_identifier(&index.name, db_type)),
+    }
+}
+
+/// Statements redefining a column as the source declares it, leaving the primary key to the
+/// table. MySQL restates the column whole, PostgreSQL alters each property that differs.
+fn alter_column(
+    schema: &TableSchema,
+    col: &ColumnInfo,
+    old: &ColumnInfo,
+    old_type: &str,
+    db_type: &DbType,
+) -> Vec<String> {
+    let quoted = schema.table.quoted(db_type);
+    let data_type = schema
+        .column_types
+        .get(&col.name)
+        .map(|t| t.data_type.as_str())
+        .unwrap_or(&col.data_type);
+    let type_changed = !data_type.eq_ignore_ascii_case(old_type);
+    let default_changed = col.default_value != old.default_value;
+
+    if matches!(db_type, DbType::Mysql) {
+        let changed = type_changed
+            || default_changed
+            || col.nullable != old.nullable
+            || col.auto_increment != old.auto_increment;
+        return changed
+            .then(|| {
+                format!(
+                    "ALTER TABLE {} MODIFY COLUMN {};\n",
+                    quoted,
+                    schema.column_definition(col, db_type)
+                )
+            })
+            .into_iter()
+            .collect();
+    }
+
+    let name = quote_identifier(&col.name, db_type);
+    let alter =
+        |action: String| format!("ALTER TABLE {} ALTER COLUMN {} {};\n", quoted, name, action);
+    let mut statements = Vec::new();
+    if type_changed {
+        statements.push(alter(format!(
+            "TYPE {} USING {}::{}",
+            data_type, name, data_type
+        )));
+    }
+    if col.nullable != old.nullable {
+        let action = if col.nullable {
+            "DROP NOT NULL"
+        } else {
+            "SET NOT NULL"
+        };
+        statements.push(alter(action.to_string()));
+    }
+    if default_changed {
+        statements.push(alter(match &col.default_value {
+            Some(default) => format!("SET DEFAULT {}", default_sql(default, db_type)),
+            None => "DROP DEFAULT".to_string(),
+        }));
+    }
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::any::AnyPoolOptions;
+
+    use super::*;
+
+    async fn database(setup: &str) -> AnyPool {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::raw_sql(setup).execute(&pool).await.unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_migration_applies_diff() {
+        let source = database(
+            "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email VARCHAR(255) NOT NULL, name TEXT);
+            CREATE UNIQUE INDEX users_email ON users (email);
+            CREATE TABLE posts (
+                id INTEGER PRIMARY KEY,
+                author INTEGER CONSTRAINT fk_author REFERENCES users (id) ON DELETE CASCADE,
+                title TEXT NOT NULL
+            );
+            CREATE INDEX posts_title ON posts (title) WHERE title <> '';
+            CREATE TABLE tags (post INTEGER, tag TEXT, PRIMARY KEY (post, tag));
+            CREATE TABLE settings (key TEXT, value TEXT NOT NULL);
+            CREATE TABLE post_log (title TEXT);
+            ",
+        )
+        .await;
+        let target = database(
+            "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email VARCHAR(100), legacy TEXT);
+            CREATE INDEX users_legacy ON users (legacy);
+            CREATE TABLE posts (id INTEGER PRIMARY KEY, author INTEGER, title TEXT NOT NULL);
+            CREATE INDEX posts_title ON posts (title);
+            CREATE TABLE audit (at TEXT);
+            CREATE TABLE settings (key TEXT);
+            CREATE TABLE post_log (title TEXT);
+            CREATE TRIGGER posts_log AFTER INSERT ON posts BEGIN
+                INSERT INTO post_log VALUES (new.title);
+            END;
+            INSERT INTO users VALUES (1, 'a@example.com', 'x');
+            INSERT INTO posts VALUES (1, 1, 'hello');
+            ",
+        )
+        .await;
+
+        let db_type = DbType::Sqlite;
+        let source = SchemaModel::load(&source, &db_type, "main").await.unwrap();
+        let before = SchemaModel::load(&target, &db_type, "main").await.unwrap();
+        let diff = diff_schemas(&source, &before);
+
+        let kinds: Vec<(&str, DiffKind)> = diff
+            .tables
+            .iter()
+            .map(|t| (t.name.as_str(), t.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("posts", DiffKind::Changed),
+                ("settings", DiffKind::Changed),
+                ("tags", DiffKind::Added),
+                ("users", DiffKind::Changed),
+                ("audit", DiffKind::Removed),
+            ]
+        );
+        let users = &diff.tables[3];
+        let columns: Vec<(&str, DiffKind)> = users
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("email", DiffKind::Changed),
+                ("name", DiffKind::Added),
+                ("legacy", DiffKind::Removed),
+            ]
+        );
+        assert_eq!(users.indexes.len(), 2);
+        assert_eq!(diff.tables[0].foreign_keys[0].name, "fk_author");
+        // the partial index differs only by its predicate
+        assert_eq!(diff.tables[0].indexes[0].name, "posts_title");
+        assert_eq!(diff.tables[0].indexes[0].kind, DiffKind::Changed);
+
+        let statements = migration(&diff, &source, &before, &target).await.unwrap();
+        assert_eq!(statements[0], "PRAGMA foreign_keys = OFF;\n");
+        assert!(statements.contains(&"DROP TABLE \"audit\";\n".to_string()));
+        sqlx::raw_sql(&statements.concat())
+            .execute(&target)
+            .await
+            .unwrap();
+
+        let after = SchemaModel::load(&target, &db_type, "main").await.unwrap();
+        assert!(diff_schemas(&source, &after).tables.is_empty());
+        let title: String = sqlx::query_scalar("SELECT title FROM posts WHERE author = 1")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_eq!(title, "hello");
+        // the rebuilt table keeps its trigger
+        sqlx::query("INSERT INTO posts (title) VALUES ('again')")
+            .execute(&target)
+            .await
+            .unwrap();
+        let logged: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM post_log")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_eq!(logged, 2);
+    }
+}
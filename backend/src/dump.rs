@@ -0,0 +1,644 @@
+use std::collections::HashMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde_json::Value;
+
+use crate::{
+    binding::{ColumnKind, ColumnType, fetch_column_types, get_primary_key_columns},
+    models::{ColumnInfo, DbType, ForeignKeyInfo, IndexInfo, Session},
+    routes::schema::{fetch_columns, fetch_foreign_keys, fetch_indexes},
+    sql_utils::{TableRef, escape_string_literal, quote_identifier},
+};
+
+/// Rows written per INSERT statement
+pub const INSERT_BATCH_SIZE: usize = 100;
+
+/// Everything needed to recreate one table and its data
+pub struct TableSchema {
+    pub table: TableRef,
+    pub columns: Vec<ColumnInfo>,
+    /// Full declared types (e.g. "character varying(255)"), which `ColumnInfo` abbreviates
+    pub column_types: HashMap<String, ColumnType>,
+    /// Primary key columns in key order
+    pub primary_key: Vec<String>,
+    pub indexes: Vec<IndexInfo>,
+    pub foreign_keys: Vec<ForeignKeyInfo>,
+}
+
+impl TableSchema {
+    /// Gathers the columns, keys and indexes of a table through the schema introspection queries
+    pub async fn load(session: &Session, table: &TableRef) -> Result<Self, String> {
+        let (pool, db_type) = (&session.pool, &session.db_type);
+        Ok(Self {
+            table: table.clone(),
+            columns: fetch_columns(pool, db_type, table).await?,
+            column_types: fetch_column_types(pool, db_type, table).await?,
+            primary_key: get_primary_key_columns(pool, db_type, table).await?,
+            indexes: fetch_indexes(pool, db_type, table).await?,
+            foreign_keys: fetch_foreign_keys(pool, db_type, table).await?,
+        })
+    }
+
+    /// The CREATE TABLE statement, with the primary key and, on SQLite, the foreign keys inline
+    pub fn create_table(&self, db_type: &DbType) -> String {
+        let mut defs: Vec<String> = self
+            .columns
+            .iter()
+            .map(|col| format!("    {}", self.column_definition(col, db_type)))
+            .collect();
+
+        // a SQLite rowid alias only auto-increments when declared as a column constraint
+        let rowid_alias = matches!(db_type, DbType::Sqlite)
+            && self
+                .columns
+                .iter()
+                .any(|c| c.auto_increment && c.is_primary_key);
+        if !self.primary_key.is_empty() && !rowid_alias {
+            defs.push(format!(
+                "    PRIMARY KEY ({})",
+                self.quote_all(&self.primary_key, db_type)
+            ));
+        }
+
+        // SQLite cannot add constraints to an existing table
+        if matches!(db_type, DbType::Sqlite) {
+            for fk in &self.foreign_keys {
+                // keys declared without a name are listed as fk_{id}
+                let generated = fk
+                    .constraint_name
+                    .strip_prefix("fk_")
+                    .is_some_and(|id| id.parse::<u32>().is_ok());
+                let name = if generated {
+                    String::new()
+                } else {
+                    format!(
+                        "CONSTRAINT {} ",
+                        quote_identifier(&fk.constraint_name, db_type)
+                    )
+                };
+                defs.push(format!("    {}{}", name, fk_clause(fk, db_type)));
+            }
+        }
+
+        format!(
+            "CREATE TABLE {} (\n{}\n);\n",
+            self.table.quoted(db_type),
+            defs.join(",\n")
+        )
+    }
+
+    /// A column as declared in CREATE TABLE or ADD COLUMN: name, type, generation, NOT NULL and
+    /// default
+    pub fn column_definition(&self, col: &ColumnInfo, db_type: &DbType) -> String {
+        let declared = self
+            .column_types
+            .get(&col.name)
+            .map(|t| t.data_type.as_str())
+            .unwrap_or(&col.data_type);
+        let default = col
+            .default_value
+            .as_deref()
+            .filter(|d| !d.eq_ignore_ascii_case("NULL"));
+
+        let mut def = quote_identifier(&col.name, db_type);
+        let mut default_clause = default.map(|d| format!("DEFAULT {}", default_sql(d, db_type)));
+        match db_type {
+            // a SERIAL column creates its own sequence, which a nextval() default would refer to
+            DbType::Postgres
+                if col.auto_increment && default.is_some_and(|d| d.starts_with("nextval(")) =>
+            {
+                let serial = match declared {
+                    "smallint" => "smallserial",
+                    "bigint" => "bigserial",
+                    _ => "serial",
+                };
+                def.push_str(&format!(" {}", serial));
+                default_clause = None;
+            }
+            DbType::Postgres if col.auto_increment => {
+                let generation = if col.identity_always {
+                    "ALWAYS"
+                } else {
+                    "BY DEFAULT"
+                };
+                def.push_str(&format!(
+                    " {} GENERATED {} AS IDENTITY",
+                    declared, generation
+                ));
+            }
+            DbType::Mysql if col.auto_increment => {
+                def.push_str(&format!(" {} AUTO_INCREMENT", declared));
+            }
+            DbType::Sqlite if col.auto_increment && col.is_primary_key => {
+                def.push_str(&format!(" {} PRIMARY KEY", declared));
+            }
+            _ if !declared.is_empty() => def.push_str(&format!(" {}", declared)),
+            _ => {}
+        }
+        // computed columns cannot have a default
+        if let Some(generated) = &col.generated {
+            def.push_str(&format!(" {}", generated));
+            default_clause = None;
+        }
+
+        if !col.nullable {
+            def.push_str(" NOT NULL");
+        }
+        if let Some(clause) = default_clause {
+            def.push(' ');
+            def.push_str(&clause);
+        }
+        def
+    }
+
+    /// CREATE INDEX statements for every index other than the primary key
+    pub fn create_indexes(&self, db_type: &DbType) -> Vec<String> {
+        self.secondary_indexes()
+            .map(|idx| self.create_index(idx, db_type))
+            .collect()
+    }
+
+    /// The indexes other than the primary key that CREATE INDEX can recreate
+    pub fn secondary_indexes(&self) -> impl Iterator<Item = &IndexInfo> {
+        self.indexes.iter().filter(|idx| {
+            !idx.is_primary
+                && (idx.definition.is_some() || idx.column_names.iter().any(|c| !c.is_empty()))
+        })
+    }
+
+    /// The CREATE INDEX statement of one of the table's indexes, keeping its method, ordering,
+    /// expressions and predicate when the database reports its definition
+    pub fn create_index(&self, idx: &IndexInfo, db_type: &DbType) -> String {
+        // SQLite reserves the sqlite_ prefix for the indexes behind UNIQUE constraints
+        let name = if idx.name.starts_with("sqlite_") {
+            format!("{}_{}_key", self.table.name, idx.column_names.join("_"))
+        } else {
+            idx.name.clone()
+        };
+        let definition = match &idx.definition {
+            Some(definition) => definition.clone(),
+            None => {
+                let columns: Vec<String> = idx
+                    .column_names
+                    .iter()
+                    .filter(|c| !c.is_empty())
+                    .cloned()
+                    .collect();
+                format!("({})", self.quote_all(&columns, db_type))
+            }
+        };
+        let kind = match &idx.kind {
+            Some(kind) => format!("{} ", kind),
+            None if idx.is_unique => "UNIQUE ".to_string(),
+            None => String::new(),
+        };
+        format!(
+            "CREATE {}INDEX {} ON {} {};\n",
+            kind,
+            quote_identifier(&name, db_type),
+            self.table.quoted(db_type),
+            definition
+        )
+    }
+
+    /// ALTER TABLE statements adding the foreign keys, meant to run once every table is loaded.
+    /// Empty on SQLite, where they are part of CREATE TABLE.
+    pub fn add_foreign_keys(&self, db_type: &DbType) -> Vec<String> {
+        if matches!(db_type, DbType::Sqlite) {
+            return Vec::new();
+        }
+        self.foreign_keys
+            .iter()
+            .map(|fk| self.add_foreign_key(fk, db_type))
+            .collect()
+    }
+
+    /// The ALTER TABLE statement adding one foreign key, which SQLite has no equivalent for
+    pub fn add_foreign_key(&self, fk: &ForeignKeyInfo, db_type: &DbType) -> String {
+        format!(
+            "ALTER TABLE {} ADD CONSTRAINT {} {};\n",
+            self.table.quoted(db_type),
+            quote_identifier(&fk.constraint_name, db_type),
+            fk_clause(fk, db_type)
+        )
+    }
+
+    /// Statements moving the sequences of serial and identity columns past the loaded rows,
+    /// since the INSERTs give their values explicitly. Empty on MySQL and SQLite, where
+    /// inserted values advance the counters.
+    pub fn reset_sequences(&self, db_type: &DbType) -> Vec<String> {
+        if !matches!(db_type, DbType::Postgres) {
+            return Vec::new();
+        }
+        let table = self.table.quoted(db_type);
+        self.columns
+            .iter()
+            .filter(|col| col.auto_increment)
+            .map(|col| {
+                let quoted = quote_identifier(&col.name, db_type);
+                format!(
+                    "SELECT setval(pg_get_serial_sequence({}, {}), COALESCE(MAX({}), 1), MAX({}) IS NOT NULL) FROM {};\n",
+                    escape_string_literal(&table, db_type),
+                    escape_string_literal(&col.name, db_type),
+                    quoted,
+                    quoted,
+                    table
+                )
+            })
+            .collect()
+    }
+
+    /// A multi-row INSERT for a batch of decoded rows, leaving out generated columns, which the
+    /// database computes again
+    pub fn insert(&self, rows: &[Value], db_type: &DbType) -> String {
+        let columns: Vec<&ColumnInfo> = self
+            .columns
+            .iter()
+            .filter(|c| c.generated.is_none())
+            .collect();
+        let names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+        let tuples: Vec<String> = rows
+            .iter()
+            .map(|row| {
+                let values: Vec<String> = columns
+                    .iter()
+                    .map(|c| sql_literal(&row[&c.name], self.column_types.get(&c.name), db_type))
+                    .collect();
+                format!("({})", values.join(", "))
+            })
+            .collect();
+
+        // GENERATED ALWAYS identity columns refuse the dumped values otherwise
+        let overriding = if columns.iter().any(|c| c.identity_always) {
+            "OVERRIDING SYSTEM VALUE "
+        } else {
+            ""
+        };
+        format!(
+            "INSERT INTO {} ({}) {}VALUES\n{};\n",
+            self.table.quoted(db_type),
+            self.quote_all(&names, db_type),
+            overriding,
+            tuples.join(",\n")
+        )
+    }
+
+    pub fn quote_all(&self, names: &[String], db_type: &DbType) -> String {
+        names
+            .iter()
+            .map(|n| quote_identifier(n, db_type))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn fk_clause(fk: &ForeignKeyInfo, db_type: &DbType) -> String {
+    let quote = |cols: &[String]| {
+        cols.iter()
+            .map(|c| quote_identifier(c, db_type))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut clause = format!(
+        "FOREIGN KEY ({}) REFERENCES {}",
+        quote(&fk.columns),
+        TableRef::new(&fk.foreign_schema, &fk.foreign_table, db_type).quoted(db_type)
+    );
+    // a SQLite key without columns refers to the primary key
+    if !fk.foreign_columns.is_empty() {
+        clause.push_str(&format!(" ({})", quote(&fk.foreign_columns)));
+    }
+    // NO ACTION is the default everywhere
+    for (event, action) in [("DELETE", &fk.on_delete), ("UPDATE", &fk.on_update)] {
+        if !action.is_empty() && !action.eq_ignore_ascii_case("NO ACTION") {
+            clause.push_str(&format!(" ON {} {}", event, action));
+        }
+    }
+    if fk.deferrable {
+        clause.push_str(" DEFERRABLE");
+        if fk.initially_deferred {
+            clause.push_str(" INITIALLY DEFERRED");
+        }
+    }
+    clause
+}
+
+/// Renders a catalog default as SQL. Postgres and SQLite report the default expression itself,
+/// while MySQL reports literal defaults unquoted.
+pub fn default_sql(default: &str, db_type: &DbType) -> String {
+    match db_type {
+        DbType::Mysql => {
+            let is_expression = default.starts_with('(')
+                || default.to_uppercase().starts_with("CURRENT_TIMESTAMP")
+                || default.parse::<f64>().is_ok();
+            if is_expression {
+                default.to_string()
+            } else {
+                escape_string_literal(default, db_type)
+            }
+        }
+        DbType::Postgres | DbType::Sqlite => default.to_string(),
+    }
+}
+
+/// Renders an array as a Postgres array literal, e.g. {1,"a b",NULL}
+fn pg_array(items: &[Value]) -> String {
+    let elements: Vec<String> = items
+        .iter()
+        .map(|item| match item {
+            Value::Null => "NULL".to_string(),
+            Value::Array(inner) => pg_array(inner),
+            Value::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+            other => format!(
+                "\"{}\"",
+                other.to_string().replace('\\', "\\\\").replace('"', "\\\"")
+            ),
+        })
+        .collect();
+    format!("{{{}}}", elements.join(","))
+}
+
+/// Renders a decoded value as a SQL literal for a column of the given type
+pub fn sql_literal(value: &Value, column: Option<&ColumnType>, db_type: &DbType) -> String {
+    let kind = column.map(|c| c.kind).unwrap_or(ColumnKind::Text);
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(b) => match db_type {
+            DbType::Postgres => if *b { "TRUE" } else { "FALSE" }.to_string(),
+            DbType::Mysql | DbType::Sqlite => (*b as i64).to_string(),
+        },
+        Value::Number(n) => n.to_string(),
+        // binary values are decoded as base64
+        Value::String(s) if kind == ColumnKind::Binary => match BASE64.decode(s) {
+            Ok(bytes) => {
+                let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                match db_type {
+                    DbType::Postgres => format!("'\\x{}'", hex),
+                    DbType::Mysql | DbType::Sqlite => format!("X'{}'", hex),
+                }
+            }
+            Err(_) => escape_string_literal(s, db_type),
+        },
+        Value::String(s) => escape_string_literal(s, db_type),
+        Value::Array(items) if matches!(db_type, DbType::Postgres) && kind != ColumnKind::Json => {
+            escape_string_literal(&pg_array(items), db_type)
+        }
+        other => escape_string_literal(&other.to_string(), db_type),
+    }
+}
+
+/// Opening lines of a dump, setting up the session to load it
+pub fn dump_header(database: &str, db_type: &DbType) -> String {
+    let (dialect, settings) = match db_type {
+        DbType::Postgres => (
+            "PostgreSQL",
+            "SET client_encoding = 'UTF8';\nSET standard_conforming_strings = on;\n",
+        ),
+        DbType::Mysql => ("MySQL", "SET NAMES utf8mb4;\n"),
+        DbType::Sqlite => ("SQLite", "PRAGMA foreign_keys = OFF;\n"),
+    };
+    format!(
+        "-- DockAdmin SQL dump\n-- Database: {}\n-- Dialect: {}\n\n{}",
+        database.replace('\n', " "),
+        dialect,
+        settings
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::binding::classify;
+
+    fn column(name: &str, data_type: &str, nullable: bool, pk: bool, auto: bool) -> ColumnInfo {
+        ColumnInfo {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable,
+            is_primary_key: pk,
+            auto_increment: auto,
+            default_value: None,
+            generated: None,
+            identity_always: false,
+        }
+    }
+
+    fn schema(db_type: &DbType, columns: Vec<ColumnInfo>, primary_key: &[&str]) -> TableSchema {
+        let column_types = columns
+            .iter()
+            .map(|c| {
+                (
+                    c.name.clone(),
+                    ColumnType {
+                        data_type: c.data_type.clone(),
+                        kind: classify(&c.data_type, db_type),
+                        nullable: c.nullable,
+                    },
+                )
+            })
+            .collect();
+        TableSchema {
+            table: TableRef::new("public", "t", db_type),
+            columns,
+            column_types,
+            primary_key: primary_key.iter().map(|c| c.to_string()).collect(),
+            indexes: Vec::new(),
+            foreign_keys: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_create_table_postgres() {
+        let db_type = DbType::Postgres;
+        let mut id = column("id", "integer", false, true, true);
+        id.default_value = Some("nextval('t_id_seq'::regclass)".to_string());
+        let mut status = column("status", "character varying(20)", true, false, false);
+        status.default_value = Some("'new'::character varying".to_string());
+        let mut table = schema(&db_type, vec![id, status], &["id"]);
+        table.foreign_keys = vec![ForeignKeyInfo {
+            constraint_name: "t_fk".to_string(),
+            columns: vec!["status".to_string(), "id".to_string()],
+            foreign_schema: "public".to_string(),
+            foreign_table: "statuses".to_string(),
+            foreign_columns: vec!["code".to_string(), "owner".to_string()],
+            on_update: "CASCADE".to_string(),
+            on_delete: "NO ACTION".to_string(),
+            deferrable: true,
+            initially_deferred: true,
+        }];
+
+        assert_eq!(
+            table.create_table(&db_type),
+            "CREATE TABLE \"public\".\"t\" (\n    \"id\" serial NOT NULL,\n    \
+             \"status\" character varying(20) DEFAULT 'new'::character varying,\n    \
+             PRIMARY KEY (\"id\")\n);\n"
+        );
+        assert_eq!(
+            table.add_foreign_keys(&db_type),
+            vec![
+                "ALTER TABLE \"public\".\"t\" ADD CONSTRAINT \"t_fk\" FOREIGN KEY (\"status\", \"id\") REFERENCES \"public\".\"statuses\" (\"code\", \"owner\") ON UPDATE CASCADE DEFERRABLE INITIALLY DEFERRED;\n"
+            ]
+        );
+        assert_eq!(
+            table.reset_sequences(&db_type),
+            vec![
+                "SELECT setval(pg_get_serial_sequence('\"public\".\"t\"', 'id'), COALESCE(MAX(\"id\"), 1), MAX(\"id\") IS NOT NULL) FROM \"public\".\"t\";\n"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_table_sqlite() {
+        let db_type = DbType::Sqlite;
+        let mut table = schema(
+            &db_type,
+            vec![
+                column("id", "INTEGER", false, true, true),
+                column("owner", "INTEGER", true, false, false),
+            ],
+            &["id"],
+        );
+        table.foreign_keys = vec![ForeignKeyInfo {
+            constraint_name: "fk_0".to_string(),
+            columns: vec!["owner".to_string()],
+            foreign_schema: "main".to_string(),
+            foreign_table: "users".to_string(),
+            foreign_columns: Vec::new(),
+            on_update: "NO ACTION".to_string(),
+            on_delete: "SET NULL".to_string(),
+            deferrable: false,
+            initially_deferred: false,
+        }];
+        table.indexes = vec![
+            IndexInfo {
+                name: "sqlite_autoindex_t_1".to_string(),
+                column_names: vec!["owner".to_string()],
+                is_unique: true,
+                is_primary: false,
+                definition: None,
+                kind: None,
+            },
+            IndexInfo {
+                name: "t_recent".to_string(),
+                column_names: Vec::new(),
+                is_unique: false,
+                is_primary: false,
+                definition: Some("(abs(\"owner\") DESC) WHERE \"owner\" > 0".to_string()),
+                kind: None,
+            },
+        ];
+
+        assert_eq!(
+            table.create_table(&db_type),
+            "CREATE TABLE \"t\" (\n    \"id\" INTEGER PRIMARY KEY NOT NULL,\n    \"owner\" INTEGER,\n    \
+             FOREIGN KEY (\"owner\") REFERENCES \"users\" ON DELETE SET NULL\n);\n"
+        );
+        assert_eq!(
+            table.create_indexes(&db_type),
+            vec![
+                "CREATE UNIQUE INDEX \"t_owner_key\" ON \"t\" (\"owner\");\n",
+                "CREATE INDEX \"t_recent\" ON \"t\" (abs(\"owner\") DESC) WHERE \"owner\" > 0;\n"
+            ]
+        );
+        assert!(table.add_foreign_keys(&db_type).is_empty());
+        assert!(table.reset_sequences(&db_type).is_empty());
+    }
+
+    #[test]
+    fn test_generated_columns() {
+        let db_type = DbType::Postgres;
+        let mut id = column("id", "bigint", false, true, true);
+        id.identity_always = true;
+        let mut total = column("total", "numeric", true, false, false);
+        total.generated = Some("GENERATED ALWAYS AS ((price * 2)) STORED".to_string());
+        let price = column("price", "numeric", true, false, false);
+        let table = schema(&db_type, vec![id, price, total], &["id"]);
+
+        assert_eq!(
+            table.create_table(&db_type),
+            "CREATE TABLE \"public\".\"t\" (\n    \"id\" bigint GENERATED ALWAYS AS IDENTITY NOT NULL,\n    \
+             \"price\" numeric,\n    \"total\" numeric GENERATED ALWAYS AS ((price * 2)) STORED,\n    \
+             PRIMARY KEY (\"id\")\n);\n"
+        );
+        assert_eq!(
+            table.insert(&[json!({"id": 1, "price": 3, "total": 6})], &db_type),
+            "INSERT INTO \"public\".\"t\" (\"id\", \"price\") OVERRIDING SYSTEM VALUE VALUES\n(1, 3);\n"
+        );
+    }
+
+    #[test]
+    fn test_create_index_mysql() {
+        let db_type = DbType::Mysql;
+        let mut table = schema(
+            &db_type,
+            vec![column("body", "text", true, false, false)],
+            &[],
+        );
+        table.indexes = vec![
+            IndexInfo {
+                name: "body_search".to_string(),
+                column_names: vec!["body".to_string()],
+                is_unique: false,
+                is_primary: false,
+                definition: Some("(`body`)".to_string()),
+                kind: Some("FULLTEXT".to_string()),
+            },
+            IndexInfo {
+                name: "body_prefix".to_string(),
+                column_names: Vec::new(),
+                is_unique: true,
+                is_primary: false,
+                definition: Some("(`body`(10) DESC, (lower(`body`)))".to_string()),
+                kind: None,
+            },
+        ];
+        assert_eq!(
+            table.create_indexes(&db_type),
+            vec![
+                "CREATE FULLTEXT INDEX `body_search` ON `t` (`body`);\n",
+                "CREATE UNIQUE INDEX `body_prefix` ON `t` (`body`(10) DESC, (lower(`body`)));\n"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_literals() {
+        let db_type = DbType::Mysql;
+        let table = schema(
+            &db_type,
+            vec![
+                column("id", "int(11)", false, true, false),
+                column("name", "varchar(20)", true, false, false),
+                column("data", "blob", true, false, false),
+                column("meta", "json", true, false, false),
+            ],
+            &["id"],
+        );
+        let rows = vec![
+            json!({"id": 1, "name": "O'Reilly\\", "data": "aGk=", "meta": {"a": 1}}),
+            json!({"id": 2, "name": null, "data": null, "meta": null}),
+        ];
+        assert_eq!(
+            table.insert(&rows, &db_type),
+            "INSERT INTO `t` (`id`, `name`, `data`, `meta`) VALUES\n\
+             (1, 'O''Reilly\\\\', X'6869', '{\"a\":1}'),\n\
+             (2, NULL, NULL, NULL);\n"
+        );
+
+        let pg_text_array = ColumnType {
+            data_type: "text[]".to_string(),
+            kind: classify("text[]", &DbType::Postgres),
+            nullable: true,
+        };
+        assert_eq!(
+            sql_literal(
+                &json!(["a \"b\"", null]),
+                Some(&pg_text_array),
+                &DbType::Postgres
+            ),
+            "'{\"a \\\"b\\\"\",NULL}'"
+        );
+    }
+}
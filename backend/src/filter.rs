@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{
+    binding::{BindValue, ColumnType, placeholder, to_bind_value},
+    models::{DbType, FilterCondition, FilterNode, FilterOp},
+    sql_utils::quote_identifier,
+};
+
+/// Maximum nesting of and/or groups, so a crafted filter can't blow the stack
+const MAX_DEPTH: usize = 16;
+
+/// Compiles a filter tree into a WHERE expression, pushing its bound values onto `params`.
+/// Every column is checked against `columns` and every value converted to that column's type.
+pub fn compile_filter(
+    node: &FilterNode,
+    columns: &HashMap<String, ColumnType>,
+    db_type: &DbType,
+    params: &mut Vec<BindValue>,
+) -> Result<String, String> {
+    compile_node(node, columns, db_type, params, 0)
+}
+
+fn compile_node(
+    node: &FilterNode,
+    columns: &HashMap<String, ColumnType>,
+    db_type: &DbType,
+    params: &mut Vec<BindValue>,
+    depth: usize,
+) -> Result<String, String> {
+    if depth > MAX_DEPTH {
+        return Err("Filter is nested too deeply".to_string());
+    }
+
+    let (nodes, joiner, empty) = match node {
+        FilterNode::Condition(cond) => return compile_condition(cond, columns, db_type, params),
+        FilterNode::And { and } => (and, " AND ", "1 = 1"),
+        FilterNode::Or { or } => (or, " OR ", "1 = 0"),
+    };
+
+    if nodes.is_empty() {
+        return Ok(empty.to_string());
+    }
+
+    let parts = nodes
+        .iter()
+        .map(|n| compile_node(n, columns, db_type, params, depth + 1))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(format!("({})", parts.join(joiner)))
+}
+
+fn compile_condition(
+    cond: &FilterCondition,
+    columns: &HashMap<String, ColumnType>,
+    db_type: &DbType,
+    params: &mut Vec<BindValue>,
+) -> Result<String, String> {
+    let column = columns
+        .get(&cond.column)
+        .ok_or_else(|| format!("Unknown column in filter: {}", cond.column))?;
+    let col_quoted = quote_identifier(&cond.column, db_type);
+
+    // converts a value to the column's type and returns its placeholder
+    let mut bind = |value: &Value| -> Result<String, String> {
+        if value.is_null() {
+            return Err(format!(
+                "Filter on '{}' needs a value, use is_null to match NULL",
+                cond.column
+            ));
+        }
+        let bound = to_bind_value(value, column.kind, db_type)
+            .map_err(|e| format!("Invalid filter value for column '{}': {}", cond.column, e))?;
+        params.push(bound);
+        Ok(placeholder(db_type, params.len(), column))
+    };
+
+    let sql = match cond.op {
+        FilterOp::Eq => format!("{} = {}", col_quoted, bind(&cond.value)?),
+        FilterOp::Neq => format!("{} <> {}", col_quoted, bind(&cond.value)?),
+        FilterOp::Gt => format!("{} > {}", col_quoted, bind(&cond.value)?),
+        FilterOp::Gte => format!("{} >= {}", col_quoted, bind(&cond.value)?),
+        FilterOp::Lt => format!("{} < {}", col_quoted, bind(&cond.value)?),
+        FilterOp::Lte => format!("{} <= {}", col_quoted, bind(&cond.value)?),
+        FilterOp::Between => match cond.value.as_array().map(|v| v.as_slice()) {
+            Some([low, high]) => {
+                format!("{} BETWEEN {} AND {}", col_quoted, bind(low)?, bind(high)?)
+            }
+            _ => {
+                return Err(format!(
+                    "Filter 'between' on '{}' needs a [low, high] array",
+                    cond.column
+                ));
+            }
+        },
+        FilterOp::In => {
+            let items = cond.value.as_array().ok_or_else(|| {
+                format!("Filter 'in' on '{}' needs an array of values", cond.column)
+            })?;
+            if items.is_empty() {
+                // nothing can be IN an empty list
+                "1 = 0".to_string()
+            } else {
+                let placeholders = items.iter().map(&mut bind).collect::<Result<Vec<_>, _>>()?;
+                format!("{} IN ({})", col_quoted, placeholders.join(", "))
+            }
+        }
+        FilterOp::Like | FilterOp::Ilike => {
+            let pattern = cond
+                .value
+                .as_str()
+                .ok_or_else(|| format!("Filter pattern for '{}' must be a string", cond.column))?;
+            params.push(BindValue::Text(pattern.to_string()));
+            let index = params.len();
+            // patterns are always text, so the column is cast rather than the placeholder
+            match db_type {
+                DbType::Postgres => {
+                    let op = if matches!(cond.op, FilterOp::Ilike) {
+                        "ILIKE"
+                    } else {
+                        "LIKE"
+                    };
+                    format!("CAST({} AS TEXT) {} ${}", col_quoted, op, index)
+                }
+                DbType::Mysql | DbType::Sqlite => {
+                    if matches!(cond.op, FilterOp::Ilike) {
+                        format!("LOWER({}) LIKE LOWER(?)", col_quoted)
+                    } else {
+                        format!("{} LIKE ?", col_quoted)
+                    }
+                }
+            }
+        }
+        FilterOp::IsNull => format!("{} IS NULL", col_quoted),
+        FilterOp::IsNotNull => format!("{} IS NOT NULL", col_quoted),
+    };
+
+    Ok(sql)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binding::{ColumnKind, classify};
+
+    fn columns(db_type: &DbType, defs: &[(&str, &str)]) -> HashMap<String, ColumnType> {
+        defs.iter()
+            .map(|(name, data_type)| {
+                (
+                    name.to_string(),
+                    ColumnType {
+                        data_type: data_type.to_string(),
+                        kind: classify(data_type, db_type),
+                        nullable: true,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn parse(json: &str) -> FilterNode {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_compile_filter_postgres() {
+        let db_type = DbType::Postgres;
+        let cols = columns(&db_type, &[("age", "integer"), ("name", "text")]);
+        let filter = parse(
+            r#"{"and": [
+                {"column": "age", "op": "between", "value": [18, "65"]},
+                {"or": [
+                    {"column": "name", "op": "ilike", "value": "%jo%"},
+                    {"column": "name", "op": "is_null"}
+                ]}
+            ]}"#,
+        );
+
+        let mut params = Vec::new();
+        let sql = compile_filter(&filter, &cols, &db_type, &mut params).unwrap();
+        assert_eq!(
+            sql,
+            "(\"age\" BETWEEN CAST($1 AS integer) AND CAST($2 AS integer) AND \
+             (CAST(\"name\" AS TEXT) ILIKE $3 OR \"name\" IS NULL))"
+        );
+        assert_eq!(
+            params,
+            vec![
+                BindValue::Int(18),
+                BindValue::Int(65),
+                BindValue::Text("%jo%".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_filter_in_list() {
+        let db_type = DbType::Mysql;
+        let cols = columns(&db_type, &[("id", "int(11)")]);
+        assert_eq!(cols["id"].kind, ColumnKind::Integer);
+
+        let mut params = Vec::new();
+        let filter = parse(r#"{"column": "id", "op": "in", "value": [1, 2, 3]}"#);
+        let sql = compile_filter(&filter, &cols, &db_type, &mut params).unwrap();
+        assert_eq!(sql, "`id` IN (?, ?, ?)");
+        assert_eq!(params.len(), 3);
+
+        let mut params = Vec::new();
+        let filter = parse(r#"{"column": "id", "op": "in", "value": []}"#);
+        let sql = compile_filter(&filter, &cols, &db_type, &mut params).unwrap();
+        assert_eq!(sql, "1 = 0");
+    }
+
+    #[test]
+    fn test_compile_filter_rejects_bad_input() {
+        let db_type = DbType::Sqlite;
+        let cols = columns(&db_type, &[("age", "INTEGER")]);
+        let mut params = Vec::new();
+
+        let unknown = parse(r#"{"column": "age; DROP TABLE x", "op": "eq", "value": 1}"#);
+        assert!(compile_filter(&unknown, &cols, &db_type, &mut params).is_err());
+
+        let bad_value = parse(r#"{"column": "age", "op": "eq", "value": "abc"}"#);
+        assert!(compile_filter(&bad_value, &cols, &db_type, &mut params).is_err());
+
+        let missing = parse(r#"{"column": "age", "op": "gt"}"#);
+        assert!(compile_filter(&missing, &cols, &db_type, &mut params).is_err());
+    }
+}
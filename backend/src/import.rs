@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{Map, Value};
+
+use crate::binding::ColumnType;
+
+/// Longest single record accepted from an upload
+const MAX_RECORD_BYTES: usize = 1024 * 1024;
+
+/// A parsed record, or the reason it could not be read
+pub type Record<T> = Result<T, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CsvState {
+    FieldStart,
+    Unquoted,
+    Quoted,
+    /// A quote inside a quoted field, which either closes it or escapes the next quote
+    QuoteInQuoted,
+}
+
+/// Splits a CSV byte stream into records of fields (RFC 4180, CRLF or LF line endings).
+/// Chunks may end anywhere, including inside a quoted field or a multi-byte character.
+pub struct CsvReader {
+    state: CsvState,
+    field: Vec<u8>,
+    fields: Vec<Vec<u8>>,
+    record_len: usize,
+}
+
+impl CsvReader {
+    pub fn new() -> Self {
+        Self {
+            state: CsvState::FieldStart,
+            field: Vec::new(),
+            fields: Vec::new(),
+            record_len: 0,
+        }
+    }
+
+    /// Feeds the next chunk of the stream, appending every record it completes to `records`.
+    /// Errors are fatal, since the reader cannot find the next record boundary after them.
+    pub fn feed(
+        &mut self,
+        chunk: &[u8],
+        records: &mut Vec<Record<Vec<String>>>,
+    ) -> Result<(), String> {
+        for &b in chunk {
+            self.record_len += 1;
+            if self.record_len > MAX_RECORD_BYTES {
+                return Err(format!(
+                    "Record exceeds the {} byte limit",
+                    MAX_RECORD_BYTES
+                ));
+            }
+
+            match (self.state, b) {
+                (CsvState::Quoted, b'"') => self.state = CsvState::QuoteInQuoted,
+                (CsvState::Quoted, _) => self.field.push(b),
+                (CsvState::QuoteInQuoted, b'"') => {
+                    self.field.push(b'"');
+                    self.state = CsvState::Quoted;
+                }
+                (CsvState::FieldStart, b'"') => self.state = CsvState::Quoted,
+                (_, b',') => self.end_field(),
+                (_, b'\n') => self.end_record(records),
+                // CR of a CRLF line ending
+                (_, b'\r') => {}
+                (_, _) => {
+                    self.field.push(b);
+                    self.state = CsvState::Unquoted;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the last record when the stream does not end with a newline
+    pub fn finish(&mut self, records: &mut Vec<Record<Vec<String>>>) -> Result<(), String> {
+        if self.state == CsvState::Quoted {
+            return Err("Unterminated quoted field at end of input".to_string());
+        }
+        self.end_record(records);
+        Ok(())
+    }
+
+    fn end_field(&mut self) {
+        self.fields.push(std::mem::take(&mut self.field));
+        self.state = CsvState::FieldStart;
+    }
+
+    fn end_record(&mut self, records: &mut Vec<Record<Vec<String>>>) {
+        let blank = self.fields.is_empty() && self.state == CsvState::FieldStart;
+        if !blank {
+            self.end_field();
+            let fields = std::mem::take(&mut self.fields)
+                .into_iter()
+                .map(|f| String::from_utf8(f).map_err(|_| "Record is not valid UTF-8".to_string()))
+                .collect();
+            records.push(fields);
+        }
+        self.state = CsvState::FieldStart;
+        self.record_len = 0;
+    }
+}
+
+/// Splits an NDJSON byte stream into lines, skipping blank ones
+pub struct LineReader {
+    line: Vec<u8>,
+}
+
+impl LineReader {
+    pub fn new() -> Self {
+        Self { line: Vec::new() }
+    }
+
+    /// Feeds the next chunk of the stream, appending every line it completes to `lines`
+    pub fn feed(&mut self, chunk: &[u8], lines: &mut Vec<Vec<u8>>) -> Result<(), String> {
+        for &b in chunk {
+            if b == b'\n' {
+                self.end_line(lines);
+                continue;
+            }
+            self.line.push(b);
+            if self.line.len() > MAX_RECORD_BYTES {
+                return Err(format!("Line exceeds the {} byte limit", MAX_RECORD_BYTES));
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the last line when the stream does not end with a newline
+    pub fn finish(&mut self, lines: &mut Vec<Vec<u8>>) {
+        self.end_line(lines);
+    }
+
+    fn end_line(&mut self, lines: &mut Vec<Vec<u8>>) {
+        let line = std::mem::take(&mut self.line);
+        if !line.iter().all(u8::is_ascii_whitespace) {
+            lines.push(line);
+        }
+    }
+}
+
+/// Finds the table column an uploaded field name refers to, by exact name first and then
+/// ignoring case, as long as that is unambiguous
+fn resolve_column<'a>(name: &str, columns: &'a HashMap<String, ColumnType>) -> Option<&'a String> {
+    if let Some((col, _)) = columns.get_key_value(name) {
+        return Some(col);
+    }
+    let mut matches = columns.keys().filter(|col| col.eq_ignore_ascii_case(name));
+    match (matches.next(), matches.next()) {
+        (Some(col), None) => Some(col),
+        _ => None,
+    }
+}
+
+/// Maps the fields of a CSV header to table columns
+pub struct CsvMapping {
+    /// Column name and nullability for each field position
+    columns: Vec<(String, bool)>,
+}
+
+impl CsvMapping {
+    pub fn new(header: &[String], columns: &HashMap<String, ColumnType>) -> Result<Self, String> {
+        let mut mapped = Vec::new();
+        let mut seen = HashSet::new();
+        let mut unknown = Vec::new();
+        for field in header {
+            let field = field.trim();
+            if field.is_empty() {
+                return Err("Header contains an empty column name".to_string());
+            }
+            let Some(col) = resolve_column(field, columns) else {
+                unknown.push(field.to_string());
+                continue;
+            };
+            if !seen.insert(col) {
+                return Err(format!(
+                    "Column '{}' appears more than once in the header",
+                    col
+                ));
+            }
+            mapped.push((col.clone(), columns[col].nullable));
+        }
+
+        if !unknown.is_empty() {
+            return Err(format!("Unknown columns in header: {}", unknown.join(", ")));
+        }
+        Ok(Self { columns: mapped })
+    }
+
+    /// Builds the insert values for one record. An empty field is NULL in a nullable column
+    /// and is left out otherwise, so the column default applies.
+    pub fn values(&self, record: Vec<String>) -> Result<Map<String, Value>, String> {
+        if record.len() != self.columns.len() {
+            return Err(format!(
+                "Expected {} fields, found {}",
+                self.columns.len(),
+                record.len()
+            ));
+        }
+
+        let mut values = Map::new();
+        for ((col, nullable), field) in self.columns.iter().zip(record) {
+            if field.is_empty() {
+                if *nullable {
+                    values.insert(col.clone(), Value::Null);
+                }
+                continue;
+            }
+            values.insert(col.clone(), Value::String(field));
+        }
+        Ok(values)
+    }
+}
+
+/// Parses one NDJSON line into insert values, keyed by the table's column names
+pub fn json_line_values(
+    line: &[u8],
+    columns: &HashMap<String, ColumnType>,
+) -> Result<Map<String, Value>, String> {
+    let object = match serde_json::from_slice::<Value>(line) {
+        Ok(Value::Object(object)) => object,
+        Ok(_) => return Err("Line is not a JSON object".to_string()),
+        Err(e) => return Err(format!("Invalid JSON: {}", e)),
+    };
+
+    let mut values = Map::new();
+    for (key, value) in object {
+        let col =
+            resolve_column(&key, columns).ok_or_else(|| format!("Unknown column: {}", key))?;
+        if values.insert(col.clone(), value).is_some() {
+            return Err(format!("Column '{}' is given more than once", col));
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{binding::classify, models::DbType};
+
+    fn columns(defs: &[(&str, &str, bool)]) -> HashMap<String, ColumnType> {
+        defs.iter()
+            .map(|(name, data_type, nullable)| {
+                (
+                    name.to_string(),
+                    ColumnType {
+                        data_type: data_type.to_string(),
+                        kind: classify(data_type, &DbType::Sqlite),
+                        nullable: *nullable,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn read_csv(chunks: &[&[u8]]) -> Result<Vec<Record<Vec<String>>>, String> {
+        let mut reader = CsvReader::new();
+        let mut records = Vec::new();
+        for chunk in chunks {
+            reader.feed(chunk, &mut records)?;
+        }
+        reader.finish(&mut records)?;
+        Ok(records)
+    }
+
+    #[test]
+    fn test_csv_reader_across_chunks() {
+        let input = "id,name\r\n1,\"Smith, \"\"J\"\"\"\r\n\n2,\"multi\nline\"\n3,Zoë";
+        let expected = vec![
+            Ok(vec!["id".to_string(), "name".to_string()]),
+            Ok(vec!["1".to_string(), "Smith, \"J\"".to_string()]),
+            Ok(vec!["2".to_string(), "multi\nline".to_string()]),
+            Ok(vec!["3".to_string(), "Zoë".to_string()]),
+        ];
+
+        assert_eq!(read_csv(&[input.as_bytes()]).unwrap(), expected);
+        // the same input split at every possible position
+        for i in 0..input.len() {
+            let (a, b) = input.as_bytes().split_at(i);
+            assert_eq!(read_csv(&[a, b]).unwrap(), expected);
+        }
+
+        assert_eq!(
+            read_csv(&[b"a,,\n"]).unwrap(),
+            vec![Ok(vec!["a".to_string(), String::new(), String::new()])]
+        );
+        assert!(read_csv(&[b"1,\"open"]).is_err());
+    }
+
+    #[test]
+    fn test_line_reader() {
+        let mut reader = LineReader::new();
+        let mut lines = Vec::new();
+        reader.feed(b"{\"a\":1}\n\n  \n{\"a\"", &mut lines).unwrap();
+        reader.feed(b":2}", &mut lines).unwrap();
+        reader.finish(&mut lines);
+        assert_eq!(lines, vec![b"{\"a\":1}".to_vec(), b"{\"a\":2}".to_vec()]);
+    }
+
+    #[test]
+    fn test_csv_mapping() {
+        let cols = columns(&[
+            ("id", "INTEGER", false),
+            ("Name", "TEXT", true),
+            ("status", "TEXT", false),
+        ]);
+        let header = vec!["id".to_string(), "name".to_string(), " status ".to_string()];
+        let mapping = CsvMapping::new(&header, &cols).unwrap();
+
+        let values = mapping
+            .values(vec!["1".to_string(), String::new(), String::new()])
+            .unwrap();
+        assert_eq!(Value::Object(values), json!({"id": "1", "Name": null}));
+        assert!(mapping.values(vec!["1".to_string()]).is_err());
+
+        assert!(CsvMapping::new(&["id".to_string(), "missing".to_string()], &cols).is_err());
+        assert!(CsvMapping::new(&["id".to_string(), "ID".to_string()], &cols).is_err());
+    }
+
+    #[test]
+    fn test_json_line_values() {
+        let cols = columns(&[("id", "INTEGER", false), ("name", "TEXT", true)]);
+        let values = json_line_values(br#"{"ID": 1, "name": null}"#, &cols).unwrap();
+        assert_eq!(Value::Object(values), json!({"id": 1, "name": null}));
+
+        assert!(json_line_values(br#"{"other": 1}"#, &cols).is_err());
+        assert!(json_line_values(b"[1, 2]", &cols).is_err());
+        assert!(json_line_values(b"{not json", &cols).is_err());
+    }
+}
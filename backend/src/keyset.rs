@@ -0,0 +1,371 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    binding::{BindValue, ColumnKind, ColumnType, placeholder, to_bind_value},
+    models::DbType,
+    sql_utils::quote_identifier,
+};
+
+/// Which side of the cursor's boundary row a page is read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CursorDirection {
+    #[serde(rename = "n")]
+    Next,
+    #[serde(rename = "p")]
+    Prev,
+}
+
+/// Contents of an opaque cursor token
+#[derive(Serialize, Deserialize)]
+struct CursorToken {
+    /// key columns the cursor was issued for
+    k: Vec<String>,
+    /// whether the sort was descending
+    desc: bool,
+    d: CursorDirection,
+    /// key values of the boundary row
+    v: Vec<Value>,
+}
+
+/// Columns that together give every row a unique position, used for keyset pagination.
+/// NULLs in nullable key columns sort after all values in ascending order and before them in
+/// descending order, on every database.
+pub struct Keyset {
+    columns: Vec<(String, ColumnType)>,
+    descending: bool,
+}
+
+impl Keyset {
+    /// `columns` must end with a unique key (usually the primary key) so the order is total
+    pub fn new(columns: Vec<(String, ColumnType)>, descending: bool) -> Self {
+        Self {
+            columns,
+            descending,
+        }
+    }
+
+    /// Whether a column's values can be compared, so that it can be part of a keyset. JSON and
+    /// geometric values have no ordering, or none that a cursor could seek on.
+    pub fn orderable(column: &ColumnType, db_type: &DbType) -> bool {
+        if column.kind == ColumnKind::Json {
+            return false;
+        }
+        let t = column.data_type.to_lowercase();
+        let name = t
+            .split(['(', ' '])
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches("[]");
+        match db_type {
+            DbType::Postgres => !matches!(
+                name,
+                "json"
+                    | "jsonb"
+                    | "xml"
+                    | "point"
+                    | "line"
+                    | "lseg"
+                    | "box"
+                    | "path"
+                    | "polygon"
+                    | "circle"
+            ),
+            DbType::Mysql => !matches!(
+                name,
+                "json"
+                    | "geometry"
+                    | "point"
+                    | "linestring"
+                    | "polygon"
+                    | "multipoint"
+                    | "multilinestring"
+                    | "multipolygon"
+                    | "geometrycollection"
+                    | "geomcollection"
+            ),
+            DbType::Sqlite => true,
+        }
+    }
+
+    /// ORDER BY clause for reading in sort order (`forward`) or against it
+    pub fn order_clause(&self, db_type: &DbType, forward: bool) -> String {
+        let dir = if forward != self.descending {
+            "ASC"
+        } else {
+            "DESC"
+        };
+
+        let keys: Vec<String> = self
+            .columns
+            .iter()
+            .flat_map(|(name, column)| {
+                let quoted = quote_identifier(name, db_type);
+                let mut keys = Vec::new();
+                if column.nullable {
+                    keys.push(format!("({} IS NULL) {}", quoted, dir));
+                }
+                keys.push(format!("{} {}", quoted, dir));
+                keys
+            })
+            .collect();
+        format!("ORDER BY {}", keys.join(", "))
+    }
+
+    /// WHERE expression matching the rows strictly past the boundary `values`,
+    /// reading in sort order (`forward`) or against it. Bound values are pushed onto `params`.
+    pub fn seek_condition(
+        &self,
+        values: &[Value],
+        forward: bool,
+        db_type: &DbType,
+        params: &mut Vec<BindValue>,
+    ) -> Result<String, String> {
+        let ascending = forward != self.descending;
+        let mut terms = Vec::new();
+        // conditions pinning every earlier key column to its boundary value
+        let mut equal_prefix: Vec<String> = Vec::new();
+
+        for (i, ((name, column), value)) in self.columns.iter().zip(values).enumerate() {
+            let quoted = quote_identifier(name, db_type);
+            let mut bind = || -> Result<String, String> {
+                let bound = to_bind_value(value, column.kind, db_type)
+                    .map_err(|e| format!("Invalid cursor value for column '{}': {}", name, e))?;
+                params.push(bound);
+                Ok(placeholder(db_type, params.len(), column))
+            };
+
+            let past = match (value.is_null(), ascending) {
+                // NULLs sort last, so nothing comes after them
+                (true, true) => None,
+                (true, false) => Some(format!("{} IS NOT NULL", quoted)),
+                (false, true) if column.nullable => {
+                    Some(format!("({} > {} OR {} IS NULL)", quoted, bind()?, quoted))
+                }
+                (false, true) => Some(format!("{} > {}", quoted, bind()?)),
+                (false, false) => Some(format!("{} < {}", quoted, bind()?)),
+            };
+            if let Some(past) = past {
+                let mut parts = equal_prefix.clone();
+                parts.push(past);
+                terms.push(format!("({})", parts.join(" AND ")));
+            }
+
+            if i + 1 == self.columns.len() {
+                break;
+            }
+            let equal = if value.is_null() {
+                format!("{} IS NULL", quoted)
+            } else {
+                format!("{} = {}", quoted, bind()?)
+            };
+            equal_prefix.push(equal);
+        }
+
+        if terms.is_empty() {
+            return Ok("1 = 0".to_string());
+        }
+        Ok(format!("({})", terms.join(" OR ")))
+    }
+
+    /// Encodes a cursor pointing at `row`, a decoded result row holding every key column
+    pub fn encode_cursor(&self, row: &Value, direction: CursorDirection) -> String {
+        let token = CursorToken {
+            k: self.columns.iter().map(|(name, _)| name.clone()).collect(),
+            desc: self.descending,
+            d: direction,
+            v: self
+                .columns
+                .iter()
+                .map(|(name, _)| row.get(name).cloned().unwrap_or(Value::Null))
+                .collect(),
+        };
+        let json = serde_json::to_vec(&token).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Decodes a cursor token, checking it was issued for this keyset
+    pub fn decode_cursor(&self, cursor: &str) -> Result<(CursorDirection, Vec<Value>), String> {
+        let token: CursorToken = URL_SAFE_NO_PAD
+            .decode(cursor.trim())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| "Invalid cursor".to_string())?;
+
+        let same_keys = token.k.len() == self.columns.len()
+            && token
+                .k
+                .iter()
+                .zip(&self.columns)
+                .all(|(k, (name, _))| k == name);
+        if !same_keys || token.desc != self.descending || token.v.len() != self.columns.len() {
+            return Err("Cursor does not match the current sort order".to_string());
+        }
+
+        Ok((token.d, token.v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{binding::classify, decode::fetch_rows, models::NativePool};
+
+    fn keyset(db_type: &DbType, defs: &[(&str, &str, bool)], descending: bool) -> Keyset {
+        let columns = defs
+            .iter()
+            .map(|(name, data_type, nullable)| {
+                (
+                    name.to_string(),
+                    ColumnType {
+                        data_type: data_type.to_string(),
+                        kind: classify(data_type, db_type),
+                        nullable: *nullable,
+                    },
+                )
+            })
+            .collect();
+        Keyset::new(columns, descending)
+    }
+
+    #[test]
+    fn test_orderable() {
+        let orderable = |data_type: &str, db_type: &DbType| {
+            let column = ColumnType {
+                data_type: data_type.to_string(),
+                kind: classify(data_type, db_type),
+                nullable: true,
+            };
+            Keyset::orderable(&column, db_type)
+        };
+        assert!(orderable("timestamp with time zone", &DbType::Postgres));
+        assert!(!orderable("jsonb", &DbType::Postgres));
+        assert!(!orderable("point[]", &DbType::Postgres));
+        assert!(orderable("varchar(20)", &DbType::Mysql));
+        assert!(!orderable("json", &DbType::Mysql));
+        assert!(!orderable("multipolygon", &DbType::Mysql));
+    }
+
+    #[test]
+    fn test_seek_condition() {
+        let db_type = DbType::Postgres;
+        let keys = keyset(
+            &db_type,
+            &[("score", "integer", true), ("id", "integer", false)],
+            false,
+        );
+        assert_eq!(
+            keys.order_clause(&db_type, true),
+            "ORDER BY (\"score\" IS NULL) ASC, \"score\" ASC, \"id\" ASC"
+        );
+
+        let mut params = Vec::new();
+        let sql = keys
+            .seek_condition(&[json!(5), json!(10)], true, &db_type, &mut params)
+            .unwrap();
+        assert_eq!(
+            sql,
+            "(((\"score\" > CAST($1 AS integer) OR \"score\" IS NULL)) OR \
+             (\"score\" = CAST($2 AS integer) AND \"id\" > CAST($3 AS integer)))"
+        );
+        assert_eq!(params.len(), 3);
+
+        // reading backwards from a NULL boundary
+        let mut params = Vec::new();
+        let sql = keys
+            .seek_condition(&[Value::Null, json!(10)], false, &db_type, &mut params)
+            .unwrap();
+        assert_eq!(
+            sql,
+            "((\"score\" IS NOT NULL) OR (\"score\" IS NULL AND \"id\" < CAST($1 AS integer)))"
+        );
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let db_type = DbType::Mysql;
+        let keys = keyset(&db_type, &[("id", "int(11)", false)], true);
+        let cursor = keys.encode_cursor(&json!({"id": 42, "name": "x"}), CursorDirection::Prev);
+
+        let (direction, values) = keys.decode_cursor(&cursor).unwrap();
+        assert_eq!(direction, CursorDirection::Prev);
+        assert_eq!(values, vec![json!(42)]);
+
+        let ascending = keyset(&db_type, &[("id", "int(11)", false)], false);
+        assert!(ascending.decode_cursor(&cursor).is_err());
+        assert!(keys.decode_cursor("not a cursor").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pages_through_nulls_sqlite() {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query("CREATE TABLE t (id INTEGER PRIMARY KEY, score INTEGER)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO t VALUES (1, 3), (2, NULL), (3, 1), (4, 3), (5, NULL), (6, 2)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let native = NativePool::Sqlite(pool);
+
+        let db_type = DbType::Sqlite;
+        let keys = keyset(
+            &db_type,
+            &[("score", "INTEGER", true), ("id", "INTEGER", false)],
+            false,
+        );
+
+        // read forward two rows at a time until the table is exhausted
+        let mut seen = Vec::new();
+        let mut boundary: Option<Value> = None;
+        loop {
+            let mut params = Vec::new();
+            let condition = match &boundary {
+                Some(row) => {
+                    let (_, values) = keys
+                        .decode_cursor(&keys.encode_cursor(row, CursorDirection::Next))
+                        .unwrap();
+                    let seek = keys
+                        .seek_condition(&values, true, &db_type, &mut params)
+                        .unwrap();
+                    format!("WHERE {}", seek)
+                }
+                None => String::new(),
+            };
+            let sql = format!(
+                "SELECT * FROM t {} {} LIMIT 2",
+                condition,
+                keys.order_clause(&db_type, true)
+            );
+            let page = fetch_rows(&native, &sql, params).await.unwrap();
+            if page.rows.is_empty() {
+                break;
+            }
+            seen.extend(page.rows.iter().map(|r| r["id"].as_i64().unwrap()));
+            boundary = page.rows.last().cloned();
+        }
+        assert_eq!(seen, vec![3, 6, 1, 4, 2, 5]);
+
+        // and one page backwards from the end
+        let mut params = Vec::new();
+        let seek = keys
+            .seek_condition(&[Value::Null, json!(5)], false, &db_type, &mut params)
+            .unwrap();
+        let sql = format!(
+            "SELECT * FROM t WHERE {} {} LIMIT 3",
+            seek,
+            keys.order_clause(&db_type, false)
+        );
+        let page = fetch_rows(&native, &sql, params).await.unwrap();
+        let ids: Vec<i64> = page
+            .rows
+            .iter()
+            .map(|r| r["id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![2, 4, 1]);
+    }
+}
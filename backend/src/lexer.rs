@@ -0,0 +1,211 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Word,
+    /// A quoted identifier: "x", `x` or [x]
+    Quoted,
+    /// A string literal
+    Text,
+    /// A parenthesised group, kept whole
+    Group,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// Byte offsets in the tokenized SQL
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Token<'_> {
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    /// The identifier the token names, if it is one
+    pub fn identifier(&self) -> Option<String> {
+        match self.kind {
+            TokenKind::Word => Some(self.text.to_string()),
+            TokenKind::Quoted => {
+                let inner = &self.text[1..self.text.len() - 1];
+                Some(match &self.text[..1] {
+                    "\"" => inner.replace("\"\"", "\""),
+                    "`" => inner.replace("``", "`"),
+                    _ => inner.to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Finds the end of a quoted run starting at `start`, where a doubled closing quote is an escape
+fn skip_quoted(bytes: &[u8], start: usize, close: u8) -> Result<usize, String> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == close {
+            if close != b']' && bytes.get(i + 1) == Some(&close) {
+                i += 2;
+                continue;
+            }
+            return Ok(i + 1);
+        }
+        i += 1;
+    }
+    Err("Unterminated quote in SQL".to_string())
+}
+
+/// Splits SQL into tokens, skipping whitespace and comments
+pub fn tokenize(sql: &str) -> Result<Vec<Token<'_>>, String> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let start = i;
+        let kind = match b {
+            _ if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = sql[i + 2..]
+                    .find("*/")
+                    .map(|p| i + 2 + p + 2)
+                    .unwrap_or(bytes.len());
+                continue;
+            }
+            b'\'' => {
+                i = skip_quoted(bytes, i, b'\'')?;
+                TokenKind::Text
+            }
+            b'"' | b'`' | b'[' => {
+                let close = if b == b'[' { b']' } else { b };
+                i = skip_quoted(bytes, i, close)?;
+                TokenKind::Quoted
+            }
+            b'(' => {
+                let mut depth = 0;
+                loop {
+                    match bytes.get(i) {
+                        None => return Err("Unbalanced parentheses in SQL".into()),
+                        Some(b'(') => depth += 1,
+                        Some(b')') => depth -= 1,
+                        Some(b'\'') => i = skip_quoted(bytes, i, b'\'')? - 1,
+                        Some(&q @ (b'"' | b'`')) => i = skip_quoted(bytes, i, q)? - 1,
+                        Some(b'[') => i = skip_quoted(bytes, i, b']')? - 1,
+                        Some(b'-') if bytes.get(i + 1) == Some(&b'-') => {
+                            i = sql[i..].find('\n').map(|p| i + p).unwrap_or(bytes.len())
+                        }
+                        Some(b'/') if bytes.get(i + 1) == Some(&b'*') => {
+                            i = sql[i + 2..]
+                                .find("*/")
+                                .map(|p| i + 2 + p + 1)
+                                .unwrap_or(bytes.len())
+                        }
+                        Some(_) => {}
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                TokenKind::Group
+            }
+            // non-ASCII bytes only occur inside identifiers
+            _ if b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80 => {
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric()
+                        || bytes[i] == b'_'
+                        || bytes[i] == b'$'
+                        || bytes[i] >= 0x80)
+                {
+                    i += 1;
+                }
+                TokenKind::Word
+            }
+            _ => {
+                i += 1;
+                TokenKind::Other
+            }
+        };
+        tokens.push(Token {
+            kind,
+            text: &sql[start..i],
+            start,
+            end: i,
+        });
+    }
+    Ok(tokens)
+}
+
+/// Whether SQL refers to an identifier anywhere, including inside parentheses
+pub fn mentions(sql: &str, name: &str) -> bool {
+    tokenize(sql).is_ok_and(|tokens| {
+        tokens.iter().any(|t| match t.kind {
+            TokenKind::Group => mentions(&t.text[1..t.text.len() - 1], name),
+            _ => t
+                .identifier()
+                .is_some_and(|id| id.eq_ignore_ascii_case(name)),
+        })
+    })
+}
+
+/// Whether SQL is a single expression: balanced, with `;` only inside literals and quoted names
+pub fn is_single_expression(sql: &str) -> bool {
+    fn balanced(sql: &str) -> bool {
+        tokenize(sql).is_ok_and(|tokens| {
+            tokens.iter().all(|t| match t.kind {
+                TokenKind::Group => balanced(&t.text[1..t.text.len() - 1]),
+                TokenKind::Other => t.text != ";" && t.text != ")",
+                _ => true,
+            })
+        })
+    }
+    tokenize(sql).is_ok_and(|tokens| !tokens.is_empty()) && balanced(sql)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comments_in_groups() {
+        let sql = "CREATE TABLE t (\n  id INTEGER, -- the user's id (or not\n  \
+                   name TEXT /* it's ) here */\n) STRICT";
+        let tokens = tokenize(sql).unwrap();
+        let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Word,
+                TokenKind::Word,
+                TokenKind::Word,
+                TokenKind::Group,
+                TokenKind::Word,
+            ]
+        );
+        assert!(tokens[3].text.ends_with("*/\n)"));
+        assert!(mentions(sql, "name"));
+        assert!(tokenize("(a -- unclosed )").is_err());
+    }
+
+    #[test]
+    fn test_single_expression() {
+        assert!(is_single_expression("deleted_at IS NULL"));
+        assert!(is_single_expression("note <> 'a;b' AND (\"x;y\" > now())"));
+        assert!(!is_single_expression("true; DROP TABLE users"));
+        assert!(!is_single_expression("(a = 1; DELETE FROM t)"));
+        assert!(!is_single_expression("a = 1) OR (b = 2"));
+        assert!(!is_single_expression("a = 'open"));
+        assert!(!is_single_expression("-- nothing"));
+    }
+}
@@ -1,11 +1,25 @@
 use crate::state::create_session_store;
 
+mod auth;
+mod binding;
+mod decode;
+mod dependents;
+mod diff;
+mod dump;
+mod filter;
+mod import;
+mod keyset;
+mod lexer;
 mod models;
+mod rebuild;
 mod routes;
 mod server;
+mod settings;
+mod snapshot;
 mod sql_utils;
 mod state;
-mod auth;
+mod statement;
+mod storage;
 
 #[tokio::main]
 async fn main() {
@@ -1,6 +1,8 @@
+use std::{collections::BTreeMap, fmt};
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum DbType {
     Postgres,
@@ -8,7 +10,7 @@ pub enum DbType {
     Sqlite,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct ConnectRequest {
     pub host: String,
     pub port: u16,
@@ -16,6 +18,28 @@ pub struct ConnectRequest {
     pub username: String,
     pub password: String,
     pub db_type: DbType,
+    /// Postgres schemas to search for unqualified names, in order. Defaults to the server's search_path.
+    #[serde(default)]
+    pub search_path: Vec<String>,
+    /// Settings applied to every connection of the session, by name
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+}
+
+/// Leaves the password out, as a session keeps its request for as long as it lasts
+impl fmt::Debug for ConnectRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectRequest")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("database", &self.database)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("db_type", &self.db_type)
+            .field("search_path", &self.search_path)
+            .field("settings", &self.settings)
+            .finish()
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -23,6 +47,7 @@ pub struct ConnectResponse {
     pub token: String,
     pub database: String,
     pub db_type: DbType,
+    pub schema: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -30,4 +55,5 @@ pub struct StatusResponse {
     pub connected: bool,
     pub database: Option<String>,
     pub db_type: Option<DbType>,
+    pub schema: Option<String>,
 }
@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
 #[allow(dead_code)]
@@ -7,4 +7,148 @@ pub struct PaginationParams {
     pub limit: Option<u32>,
     pub sort: Option<String>,
     pub order: Option<String>,
+    /// JSON-encoded `FilterNode`
+    pub filter: Option<String>,
+    pub count: Option<CountMode>,
+    pub pagination: Option<PaginationMode>,
+    /// Opaque token from a previous cursor-mode response
+    pub cursor: Option<String>,
+}
+
+/// How pages are addressed when reading rows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaginationMode {
+    /// `page` number, read with LIMIT/OFFSET
+    #[default]
+    Offset,
+    /// Keyset pagination on the sort column and primary key, driven by `cursor` tokens
+    Cursor,
+}
+
+/// How `total_count` is computed when reading rows
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CountMode {
+    /// Exact count on small tables, catalog estimate on large ones
+    #[default]
+    Auto,
+    Exact,
+    Estimate,
+}
+
+/// Name and database type of a column in a result set
+#[derive(Debug, Serialize)]
+pub struct ResultColumn {
+    pub name: String,
+    pub data_type: String,
+}
+
+/// Comparison operators available to table filters
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    Neq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Between,
+    Like,
+    Ilike,
+    In,
+    IsNull,
+    IsNotNull,
+}
+
+/// A single predicate on one column
+#[derive(Debug, Deserialize)]
+pub struct FilterCondition {
+    pub column: String,
+    pub op: FilterOp,
+    #[serde(default)]
+    pub value: serde_json::Value,
+}
+
+/// Filter tree, e.g. {"and": [{"column": "age", "op": "gte", "value": 18}, {"or": [...]}]}
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum FilterNode {
+    And { and: Vec<FilterNode> },
+    Or { or: Vec<FilterNode> },
+    Condition(FilterCondition),
+}
+
+/// Body of POST /api/table/{name}/query
+#[derive(Deserialize)]
+pub struct RowQueryRequest {
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+    pub sort: Option<String>,
+    pub order: Option<String>,
+    pub filter: Option<FilterNode>,
+    pub count: Option<CountMode>,
+    pub pagination: Option<PaginationMode>,
+    pub cursor: Option<String>,
+}
+
+/// Body of PUT /api/table/{name}
+#[derive(Deserialize)]
+pub struct RowUpdateRequest {
+    /// Every primary key column mapped to the row's value
+    pub key: serde_json::Map<String, serde_json::Value>,
+    /// Columns to set
+    pub values: serde_json::Map<String, serde_json::Value>,
+    /// Identify the row by all of its column values, for tables without a primary key. Json
+    /// and floating-point columns are left out of the match.
+    #[serde(default)]
+    pub match_full_row: bool,
+}
+
+/// Body of DELETE /api/table/{name}
+#[derive(Deserialize)]
+pub struct RowDeleteRequest {
+    /// Every primary key column mapped to the row's value
+    pub key: serde_json::Map<String, serde_json::Value>,
+    /// Identify the row by all of its column values, for tables without a primary key. Json
+    /// and floating-point columns are left out of the match.
+    #[serde(default)]
+    pub match_full_row: bool,
+}
+
+/// One operation in a bulk request, tagged by `op`
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BulkOperation {
+    Insert {
+        values: serde_json::Map<String, serde_json::Value>,
+    },
+    Update {
+        key: serde_json::Map<String, serde_json::Value>,
+        values: serde_json::Map<String, serde_json::Value>,
+        #[serde(default)]
+        match_full_row: bool,
+    },
+    Delete {
+        key: serde_json::Map<String, serde_json::Value>,
+        #[serde(default)]
+        match_full_row: bool,
+    },
+}
+
+impl BulkOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BulkOperation::Insert { .. } => "insert",
+            BulkOperation::Update { .. } => "update",
+            BulkOperation::Delete { .. } => "delete",
+        }
+    }
+}
+
+/// Body of POST /api/table/{name}/bulk
+#[derive(Deserialize)]
+pub struct BulkRequest {
+    pub operations: Vec<BulkOperation>,
 }
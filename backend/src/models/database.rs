@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+/// Database to create on the server
+#[derive(Debug, Deserialize)]
+pub struct CreateDatabaseRequest {
+    pub name: String,
+}
+
+/// Query parameters for dropping a database
+#[derive(Debug, Deserialize)]
+pub struct DropDatabaseParams {
+    /// Disconnect the database's other sessions first (PostgreSQL 13+)
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Database of the same server for the session to switch to
+#[derive(Debug, Deserialize)]
+pub struct SwitchDatabaseRequest {
+    pub database: String,
+}
+
+/// Storage figures of a database and the server hosting it; each is None when the server
+/// doesn't report it
+#[derive(Debug, Serialize)]
+pub struct DatabaseStats {
+    /// Size on disk, data and indexes included
+    pub size_bytes: Option<i64>,
+    pub encoding: Option<String>,
+    pub collation: Option<String>,
+    pub uptime_seconds: Option<i64>,
+    /// Client connections open on the whole server
+    pub connections: Option<i64>,
+    pub max_connections: Option<i64>,
+}
+
+/// Space a table takes on disk
+#[derive(Debug, Serialize)]
+pub struct TableStorage {
+    pub name: String,
+    pub data_bytes: i64,
+    pub index_bytes: i64,
+    pub total_bytes: i64,
+    /// The catalogs' row estimate, unknown on SQLite
+    pub row_estimate: Option<i64>,
+}
+
+/// Query parameters for listing server activity
+#[derive(Debug, Deserialize)]
+pub struct ActivityParams {
+    /// Leave out idle sessions
+    #[serde(default)]
+    pub active: bool,
+}
+
+/// A session connected to the server and what it is running
+#[derive(Debug, Serialize)]
+pub struct BackendActivity {
+    /// Process id (PostgreSQL) or connection id (MySQL) to cancel or terminate it by
+    pub id: i64,
+    pub user: Option<String>,
+    pub database: Option<String>,
+    /// Client address, with the port on MySQL
+    pub client: Option<String>,
+    /// Name the client gave itself, only reported by PostgreSQL
+    pub application: Option<String>,
+    /// Kind of process, such as client backend or autovacuum worker, only reported by PostgreSQL
+    pub backend_type: Option<String>,
+    /// active or idle on PostgreSQL, the command (Query, Sleep) on MySQL
+    pub state: Option<String>,
+    /// Category of what the session waits on, only reported by PostgreSQL
+    pub wait_event_type: Option<String>,
+    /// What the session waits on, the thread state on MySQL
+    pub wait_event: Option<String>,
+    /// Time spent in the current state, for an active session the running query
+    pub duration_seconds: Option<f64>,
+    /// The running query, or the last one of an idle PostgreSQL session
+    pub query: Option<String>,
+}
+
+/// Query parameters for stopping a backend
+#[derive(Debug, Deserialize)]
+pub struct KillParams {
+    /// Close the connection instead of cancelling its running query
+    #[serde(default)]
+    pub terminate: bool,
+}
+
+/// A lock held or awaited on a table, or awaited on anything else
+#[derive(Debug, Serialize)]
+pub struct LockInfo {
+    /// Backend holding or awaiting the lock, as listed by the activity monitor
+    pub backend_id: Option<i64>,
+    /// What is locked: relation, tuple or transactionid on PostgreSQL, TABLE or RECORD on MySQL
+    pub lock_type: String,
+    pub mode: String,
+    pub granted: bool,
+    pub schema: Option<String>,
+    pub relation: Option<String>,
+}
+
+/// A backend waiting on a lock another one holds
+#[derive(Debug, Serialize)]
+pub struct LockWait {
+    pub waiting_id: i64,
+    pub waiting_query: Option<String>,
+    pub blocking_id: i64,
+    /// The blocker's running query, or the last one of an idle transaction on PostgreSQL
+    pub blocking_query: Option<String>,
+    pub blocking_state: Option<String>,
+    pub lock_type: String,
+    pub mode: String,
+    /// Table the wait is over, when it is over a table or its rows
+    pub schema: Option<String>,
+    pub relation: Option<String>,
+    /// Time waited so far
+    pub wait_seconds: Option<f64>,
+    /// Age of the blocker's transaction
+    pub blocking_seconds: Option<f64>,
+}
+
+/// Query parameters for listing server settings
+#[derive(Debug, Deserialize)]
+pub struct SettingsParams {
+    /// Case-insensitive text to find in setting names and descriptions
+    pub search: Option<String>,
+}
+
+/// Level a setting is changed at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingScope {
+    /// Every connection of the session, until it disconnects
+    #[default]
+    Session,
+    /// The whole server, for new sessions as well
+    Global,
+}
+
+/// A server setting (PostgreSQL), system variable (MySQL) or PRAGMA (SQLite) with its value
+/// on the session's connections
+#[derive(Debug, Serialize)]
+pub struct SettingInfo {
+    pub name: String,
+    pub value: Option<String>,
+    pub unit: Option<String>,
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub session_editable: bool,
+    pub global_editable: bool,
+    /// Whether a global change only takes effect once the server restarts; unknown on MySQL,
+    /// which rejects changes to its read-only variables instead
+    pub requires_restart: Option<bool>,
+    /// A global change is waiting for a restart, only reported by PostgreSQL
+    pub pending_restart: bool,
+}
+
+/// New value for a setting
+#[derive(Debug, Deserialize)]
+pub struct UpdateSettingRequest {
+    pub value: String,
+    #[serde(default)]
+    pub scope: SettingScope,
+}
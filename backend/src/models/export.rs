@@ -3,4 +3,4 @@ use serde::Deserialize;
 #[derive(Deserialize)]
 pub struct ExportParams {
     pub format: Option<String>,
-}
\ No newline at end of file
+}
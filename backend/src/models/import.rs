@@ -0,0 +1,19 @@
+use serde::Deserialize;
+
+/// Layout of an uploaded import body
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// Comma-separated values with a header row naming the columns
+    Csv,
+    /// One JSON object per line, keyed by column name
+    Ndjson,
+}
+
+#[derive(Deserialize)]
+pub struct ImportParams {
+    /// Taken from the Content-Type header when not given
+    pub format: Option<ImportFormat>,
+    /// Rows inserted per transaction
+    pub batch_size: Option<usize>,
+}
@@ -1,15 +1,21 @@
 pub mod connection;
-pub mod response;
-pub mod schema;
 pub mod data;
+pub mod database;
+pub mod export;
+pub mod import;
 pub mod query;
+pub mod response;
+pub mod role;
+pub mod schema;
 pub mod session;
-pub mod export;
 
 pub use connection::*;
-pub use response::*;
-pub use schema::*;
 pub use data::*;
+pub use database::*;
+pub use export::*;
+pub use import::*;
 pub use query::*;
+pub use response::*;
+pub use role::*;
+pub use schema::*;
 pub use session::*;
-pub use export::*;
@@ -25,4 +25,14 @@ impl<T> ApiResponse<T> {
             error: Some(message.into()),
         }
     }
+
+    /// A failure that still reports what happened, e.g. the outcome of every operation of a
+    /// batch that was rolled back
+    pub fn error_with_data(message: impl Into<String>, data: T) -> Self {
+        Self {
+            success: false,
+            data: Some(data),
+            error: Some(message.into()),
+        }
+    }
 }
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+/// A role (PostgreSQL) or user account (MySQL) of the server
+#[derive(Debug, Serialize)]
+pub struct RoleInfo {
+    pub name: String,
+    /// Host the account connects from, only set on MySQL
+    pub host: Option<String>,
+    pub superuser: bool,
+    /// Whether the role may log in; MySQL accounts can't when locked
+    pub can_login: bool,
+    pub create_db: bool,
+    pub create_role: bool,
+    /// Roles granted to this one
+    pub member_of: Vec<String>,
+}
+
+/// What a privilege is held on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GrantObject {
+    /// Every database of the server, only on MySQL
+    Global,
+    Database,
+    Table,
+}
+
+/// A privilege held by a role
+#[derive(Debug, Serialize)]
+pub struct GrantInfo {
+    pub object_type: GrantObject,
+    /// Schema (PostgreSQL) or database (MySQL) of a table
+    pub schema: Option<String>,
+    /// Database or table name, none for global privileges
+    pub object: Option<String>,
+    pub privilege: String,
+    /// Whether the role may grant the privilege to others
+    pub grantable: bool,
+}
+
+/// Query parameters identifying a role
+#[derive(Debug, Deserialize)]
+pub struct RoleParams {
+    /// MySQL account host, `%` when omitted
+    pub host: Option<String>,
+}
+
+/// Role or user to create
+#[derive(Debug, Deserialize)]
+pub struct CreateRoleRequest {
+    pub name: String,
+    pub password: Option<String>,
+    /// MySQL account host, `%` when omitted
+    pub host: Option<String>,
+    /// A role that can't log in is a group on PostgreSQL and a locked account on MySQL
+    #[serde(default = "default_can_login")]
+    pub can_login: bool,
+}
+
+fn default_can_login() -> bool {
+    true
+}
+
+/// New password for a role
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub password: String,
+    /// MySQL account host, `%` when omitted
+    pub host: Option<String>,
+}
+
+/// Privileges to grant to or revoke from a role
+#[derive(Debug, Deserialize)]
+pub struct PrivilegeRequest {
+    /// Privilege names such as SELECT or CONNECT, or ALL
+    pub privileges: Vec<String>,
+    /// Either a table or a database
+    pub object_type: GrantObject,
+    /// Table of the session's schema (schema-qualified on PostgreSQL), or database name
+    pub object: String,
+    /// MySQL account host, `%` when omitted
+    pub host: Option<String>,
+    /// Let the role grant the privileges in turn, ignored when revoking
+    #[serde(default)]
+    pub with_grant_option: bool,
+}
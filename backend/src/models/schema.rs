@@ -2,34 +2,70 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TableInfo {
+    pub schema: String,
     pub name: String,
     pub table_type: String,
     pub row_count_estimate: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColumnInfo {
     pub name: String,
     pub data_type: String,
     pub nullable: bool,
     pub is_primary_key: bool,
+    /// Filled in by the database when omitted (SERIAL/IDENTITY, AUTO_INCREMENT, or a SQLite rowid alias)
+    pub auto_increment: bool,
     pub default_value: Option<String>,
+    /// The clause computing a generated column, e.g. `GENERATED ALWAYS AS (price * qty) STORED`.
+    /// Such columns take no values of their own.
+    #[serde(default)]
+    pub generated: Option<String>,
+    /// A PostgreSQL identity column declared GENERATED ALWAYS, which only accepts explicit
+    /// values with OVERRIDING SYSTEM VALUE
+    #[serde(default)]
+    pub identity_always: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexInfo {
     pub name: String,
+    /// Indexed table columns; expressions are only part of the definition
     pub column_names: Vec<String>,
     pub is_unique: bool,
     pub is_primary: bool,
+    /// What follows the table in the index's CREATE INDEX statement, e.g.
+    /// `USING btree (lower(email)) WHERE active`. None for the indexes SQLite creates behind
+    /// constraints.
+    #[serde(default)]
+    pub definition: Option<String>,
+    /// FULLTEXT or SPATIAL for those MySQL indexes, which CREATE INDEX names in place of UNIQUE
+    #[serde(default)]
+    pub kind: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A foreign key constraint, with its columns paired in key order
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ForeignKeyInfo {
     pub constraint_name: String,
-    pub column_name: String,
+    pub columns: Vec<String>,
+    pub foreign_schema: String,
     pub foreign_table: String,
-    pub foreign_column: String,
+    /// Empty when a SQLite key refers to the primary key implicitly
+    pub foreign_columns: Vec<String>,
+    /// Referential actions: NO ACTION, RESTRICT, CASCADE, SET NULL or SET DEFAULT
+    pub on_update: String,
+    pub on_delete: String,
+    /// Constraint timing, only ever set on PostgreSQL
+    pub deferrable: bool,
+    pub initially_deferred: bool,
+}
+
+/// Query parameters for listing tables
+#[derive(Debug, Deserialize)]
+pub struct TablesParams {
+    /// Postgres schema to list, instead of the session's default
+    pub schema: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -61,6 +97,31 @@ fn default_on_delete() -> String {
     "RESTRICT".to_string()
 }
 
+/// Foreign key constraint added to an existing table
+#[derive(Debug, Deserialize)]
+pub struct ForeignKeyConstraintDefinition {
+    /// Generated from the table and column names when omitted
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+    /// May be schema-qualified on PostgreSQL
+    pub target_table: String,
+    pub target_columns: Vec<String>,
+    #[serde(default = "default_on_delete")]
+    pub on_delete: String,
+    #[serde(default = "default_on_update")]
+    pub on_update: String,
+    /// DEFERRABLE, PostgreSQL only
+    #[serde(default)]
+    pub deferrable: bool,
+    /// INITIALLY DEFERRED, requires `deferrable`
+    #[serde(default)]
+    pub initially_deferred: bool,
+}
+
+fn default_on_update() -> String {
+    "NO ACTION".to_string()
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateTableRequest {
     pub name: String,
@@ -69,6 +130,28 @@ pub struct CreateTableRequest {
     pub foreign_keys: Vec<ForeignKeyDefinition>,
 }
 
+/// Column of an index definition
+#[derive(Debug, Deserialize)]
+pub struct IndexColumnDefinition {
+    pub name: String,
+    /// ASC or DESC, ascending when omitted
+    pub order: Option<String>,
+}
+
+/// Index definition for index creation
+#[derive(Debug, Deserialize)]
+pub struct CreateIndexRequest {
+    /// Generated from the table and column names when omitted
+    pub name: Option<String>,
+    pub columns: Vec<IndexColumnDefinition>,
+    #[serde(default)]
+    pub unique: bool,
+    /// Predicate of a partial index as raw SQL, a single expression (PostgreSQL and SQLite)
+    pub where_clause: Option<String>,
+    /// Index method (PostgreSQL only): btree, hash, gin, gist, spgist or brin
+    pub method: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub enum AlterType {
     RenameTable,
@@ -76,6 +159,8 @@ pub enum AlterType {
     DropColumn,
     ModifyColumn,
     RenameColumn,
+    AddForeignKey,
+    DropForeignKey,
 }
 
 #[derive(Debug, Deserialize)]
@@ -85,4 +170,146 @@ pub struct AlterTableRequest {
     pub column_definition: Option<ColumnDefinition>,
     pub column_name: Option<String>,
     pub old_column_name: Option<String>, // For ModifyColumn/RenameColumn
+    pub foreign_key: Option<ForeignKeyConstraintDefinition>, // For AddForeignKey
+    pub constraint_name: Option<String>, // For DropForeignKey
+}
+
+/// A view and the query it is defined by
+#[derive(Debug, Serialize)]
+pub struct ViewInfo {
+    pub schema: String,
+    pub name: String,
+    /// PostgreSQL materialized view
+    pub materialized: bool,
+    /// The SELECT statement, as the database reformatted it
+    pub definition: String,
+}
+
+/// View definition for view creation
+#[derive(Debug, Deserialize)]
+pub struct CreateViewRequest {
+    pub name: String,
+    /// The SELECT statement
+    pub definition: String,
+    /// Create a PostgreSQL materialized view
+    #[serde(default)]
+    pub materialized: bool,
+}
+
+/// New query for an existing view
+#[derive(Debug, Deserialize)]
+pub struct ReplaceViewRequest {
+    pub definition: String,
+}
+
+/// Query parameters for refreshing a materialized view
+#[derive(Debug, Deserialize)]
+pub struct RefreshViewParams {
+    /// Refresh without locking out reads, which needs a unique index on the view
+    #[serde(default)]
+    pub concurrently: bool,
+}
+
+/// Query parameters for table changes
+#[derive(Debug, Deserialize)]
+pub struct SchemaChangeParams {
+    /// Report the statements the change would run instead of running them
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// An object affected by dropping a table or column
+#[derive(Debug, Serialize)]
+pub struct DependentObject {
+    /// FOREIGN KEY or VIEW
+    pub object_type: String,
+    pub schema: String,
+    /// The view's name, or the foreign key's constraint name
+    pub name: String,
+    /// The table a foreign key belongs to
+    pub table: Option<String>,
+    pub on_delete: Option<String>,
+    /// Rows of that table referring to the dropped rows, for a foreign key referencing them
+    pub rows: Option<i64>,
+}
+
+/// Query parameters for a schema snapshot
+#[derive(Debug, Deserialize)]
+pub struct SnapshotParams {
+    /// Postgres schema to describe, instead of the session's default
+    pub schema: Option<String>,
+    /// json (default), mermaid, dot or graph
+    pub format: Option<String>,
+}
+
+/// Every table and view of a schema with its structure, as loaded for an ER diagram
+#[derive(Debug, Serialize)]
+pub struct SchemaSnapshot {
+    pub schema: String,
+    pub tables: Vec<TableSnapshot>,
+}
+
+/// A table or view within a schema snapshot
+#[derive(Debug, Serialize)]
+pub struct TableSnapshot {
+    pub name: String,
+    pub table_type: String,
+    pub columns: Vec<ColumnInfo>,
+    /// Primary key columns in declaration order
+    pub primary_key: Vec<String>,
+    pub indexes: Vec<IndexInfo>,
+    pub foreign_keys: Vec<ForeignKeyInfo>,
+}
+
+/// Request to compare the session's schema with another session's
+#[derive(Debug, Deserialize)]
+pub struct SchemaDiffRequest {
+    /// Token of the session to compare with, of the same database type, whose database the
+    /// migration is written for
+    pub target_token: String,
+    /// Postgres schema of the session, instead of its default
+    pub schema: Option<String>,
+    /// Postgres schema of the target session, instead of its default
+    pub target_schema: Option<String>,
+}
+
+/// How an object of the target differs from the session's
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    /// Only in the session's schema, so the migration creates it
+    Added,
+    /// Only in the target's schema, so the migration drops it
+    Removed,
+    /// In both, defined differently
+    Changed,
+}
+
+/// A column, index or foreign key that differs between two schemas
+#[derive(Debug, Serialize)]
+pub struct ObjectDiff<T> {
+    pub name: String,
+    pub kind: DiffKind,
+    /// The session's definition, absent when removed
+    pub source: Option<T>,
+    /// The target's definition, absent when added
+    pub target: Option<T>,
+}
+
+/// A table that differs between two schemas, with what differs within a changed table
+#[derive(Debug, Serialize)]
+pub struct TableDiff {
+    pub name: String,
+    pub kind: DiffKind,
+    pub columns: Vec<ObjectDiff<ColumnInfo>>,
+    pub indexes: Vec<ObjectDiff<IndexInfo>>,
+    pub foreign_keys: Vec<ObjectDiff<ForeignKeyInfo>>,
+}
+
+/// The tables of a target schema that differ from the session's
+#[derive(Debug, Serialize)]
+pub struct SchemaDiff {
+    pub source_schema: String,
+    pub target_schema: String,
+    pub tables: Vec<TableDiff>,
 }
@@ -1,13 +1,55 @@
-use crate::models::DbType;
-use sqlx::AnyPool;
+use crate::{
+    models::{ConnectRequest, DbType},
+    sql_utils::TableRef,
+};
+use sqlx::{AnyPool, MySqlPool, PgPool, SqlitePool};
 use std::time::Instant;
 
+/// Driver-specific pool, used where the Any driver can't decode column types
+#[derive(Clone)]
+pub enum NativePool {
+    Postgres(PgPool),
+    Mysql(MySqlPool),
+    Sqlite(SqlitePool),
+}
+
 #[derive(Clone)]
 #[allow(dead_code)]
 pub struct Session {
     pub token: String,
     pub pool: AnyPool,
+    pub native_pool: NativePool,
     pub database: String,
     pub db_type: DbType,
+    /// Schema unqualified table names resolve to: the first search_path entry on Postgres,
+    /// the database on MySQL and "main" on SQLite
+    pub schema: String,
     pub created_at: Instant,
+    /// The request the session connected with, reused to connect to another database or with
+    /// other settings. Reconnecting needs the password, so it stays in memory, in plain text,
+    /// for as long as the session does; it is never logged or sent back to the client.
+    pub connection: ConnectRequest,
+}
+
+impl NativePool {
+    pub async fn close(&self) {
+        match self {
+            NativePool::Postgres(pool) => pool.close().await,
+            NativePool::Mysql(pool) => pool.close().await,
+            NativePool::Sqlite(pool) => pool.close().await,
+        }
+    }
+}
+
+impl Session {
+    /// Closes both of the session's pools, once their running queries finish
+    pub async fn close(&self) {
+        self.pool.close().await;
+        self.native_pool.close().await;
+    }
+
+    /// Resolves a table name from a request, which may be schema-qualified on Postgres
+    pub fn table(&self, name: &str) -> Result<TableRef, String> {
+        TableRef::parse(name, &self.db_type, &self.schema)
+    }
 }
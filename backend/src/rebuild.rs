@@ -0,0 +1,1001 @@
+use std::ops::Range;
+
+use sqlx::{AnyConnection, AnyPool, Connection, Row};
+
+use crate::{
+    lexer::{TokenKind, mentions, tokenize},
+    models::DbType,
+    sql_utils::quote_identifier,
+};
+
+/// First SQLite release with ALTER TABLE DROP COLUMN
+const DROP_COLUMN_VERSION: (u32, u32) = (3, 35);
+
+/// Column definition keywords that start a new constraint
+const CONSTRAINT_KEYWORDS: &[&str] = &[
+    "CONSTRAINT",
+    "PRIMARY",
+    "NOT",
+    "NULL",
+    "UNIQUE",
+    "CHECK",
+    "DEFAULT",
+    "COLLATE",
+    "REFERENCES",
+    "GENERATED",
+    "AS",
+];
+
+/// Keywords that open a table constraint rather than a column definition
+const TABLE_CONSTRAINT_KEYWORDS: &[&str] = &["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"];
+
+/// The new shape of a rebuilt column
+pub struct ColumnSpec {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    pub primary_key: bool,
+    pub unique: bool,
+    /// Default as a SQL expression
+    pub default: Option<String>,
+}
+
+/// A change made by rebuilding the table
+pub enum TableChange {
+    /// Replaces the column's type, nullability, key and default, renaming it if the name differs.
+    /// Its CHECK, COLLATE, REFERENCES and generated clauses are kept.
+    ModifyColumn {
+        old_name: String,
+        column: ColumnSpec,
+    },
+    DropColumn(String),
+    /// Appends a table constraint, given as SQL
+    AddConstraint(String),
+    /// Removes the foreign key on these columns, whether declared on a column or the table
+    DropForeignKey {
+        columns: Vec<String>,
+        foreign_table: String,
+    },
+    /// Replaces the whole definition with a CREATE TABLE statement and the table's indexes with
+    /// these, given by name and CREATE INDEX statement. Only the columns both definitions
+    /// declare keep their values.
+    Redefine {
+        table_sql: String,
+        indexes: Vec<(String, String)>,
+    },
+}
+
+/// A CREATE TABLE statement split into its column and constraint definitions
+#[derive(Debug, PartialEq)]
+struct TableSql {
+    definitions: Vec<String>,
+    /// Table options after the definitions, e.g. WITHOUT ROWID or STRICT
+    options: String,
+}
+
+impl TableSql {
+    fn parse(sql: &str) -> Result<Self, String> {
+        let tokens = tokenize(sql)?;
+        let body = tokens
+            .iter()
+            .find(|t| t.kind == TokenKind::Group)
+            .ok_or("Table definition has no column list")?;
+
+        // each definition spans its first to last token, leaving out surrounding comments
+        let inner = &body.text[1..body.text.len() - 1];
+        let mut definitions = Vec::new();
+        let mut span: Option<(usize, usize)> = None;
+        for token in tokenize(inner)? {
+            if token.kind == TokenKind::Other && token.text == "," {
+                let (start, end) = span.take().ok_or("Empty definition in table")?;
+                definitions.push(inner[start..end].to_string());
+            } else {
+                span = Some((span.map_or(token.start, |(start, _)| start), token.end));
+            }
+        }
+        let (start, end) = span.ok_or("Empty definition in table")?;
+        definitions.push(inner[start..end].to_string());
+
+        Ok(Self {
+            definitions,
+            options: sql[body.end..].trim().trim_end_matches(';').to_string(),
+        })
+    }
+
+    fn to_sql(&self, table: &str) -> String {
+        format!(
+            "CREATE TABLE {} ({}) {}",
+            quote_identifier(table, &DbType::Sqlite),
+            self.definitions.join(", "),
+            self.options
+        )
+        .trim_end()
+        .to_string()
+    }
+
+    /// The foreign keys declared in the definitions
+    fn foreign_keys(&self) -> Result<Vec<DeclaredForeignKey>, String> {
+        let mut keys = Vec::new();
+        for (i, def) in self.definitions.iter().enumerate() {
+            match column_name(def) {
+                Some(column) => {
+                    for (keyword, range) in column_constraints(def)? {
+                        if keyword == "REFERENCES" {
+                            let (name, foreign_table) = foreign_key_target(&def[range.clone()])?;
+                            keys.push(DeclaredForeignKey {
+                                name,
+                                columns: vec![column.clone()],
+                                foreign_table,
+                                definition: i,
+                                clause: Some(range),
+                            });
+                        }
+                    }
+                }
+                None => {
+                    let tokens = tokenize(def)?;
+                    let Some(key) = tokens.iter().position(|t| t.is_keyword("FOREIGN")) else {
+                        continue;
+                    };
+                    let columns = tokens[key..]
+                        .iter()
+                        .find(|t| t.kind == TokenKind::Group)
+                        .map(|t| group_identifiers(t.text))
+                        .transpose()?
+                        .ok_or("Foreign key without columns")?;
+                    let (name, foreign_table) = foreign_key_target(def)?;
+                    keys.push(DeclaredForeignKey {
+                        name,
+                        columns,
+                        foreign_table,
+                        definition: i,
+                        clause: None,
+                    });
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Position of a column's definition
+    fn find_column(&self, name: &str) -> Option<usize> {
+        self.definitions
+            .iter()
+            .position(|def| column_name(def).is_some_and(|n| n.eq_ignore_ascii_case(name)))
+    }
+
+    fn has_table_primary_key(&self) -> bool {
+        self.definitions.iter().any(|def| {
+            column_name(def).is_none()
+                && tokenize(def).is_ok_and(|tokens| tokens.iter().any(|t| t.is_keyword("PRIMARY")))
+        })
+    }
+}
+
+/// A foreign key as declared in a CREATE TABLE statement
+struct DeclaredForeignKey {
+    name: Option<String>,
+    columns: Vec<String>,
+    foreign_table: String,
+    /// Index of the declaring definition
+    definition: usize,
+    /// Span of the REFERENCES clause in a column definition, None for a table constraint
+    clause: Option<Range<usize>>,
+}
+
+impl DeclaredForeignKey {
+    fn matches(&self, columns: &[String], foreign_table: &str) -> bool {
+        self.foreign_table.eq_ignore_ascii_case(foreign_table)
+            && self.columns.len() == columns.len()
+            && self
+                .columns
+                .iter()
+                .zip(columns)
+                .all(|(a, b)| a.eq_ignore_ascii_case(b))
+    }
+}
+
+/// The CONSTRAINT name and referenced table of a foreign key clause
+fn foreign_key_target(clause: &str) -> Result<(Option<String>, String), String> {
+    let tokens = tokenize(clause)?;
+    let name = match tokens.first() {
+        Some(t) if t.is_keyword("CONSTRAINT") => tokens.get(1).and_then(|t| t.identifier()),
+        _ => None,
+    };
+    let table = tokens
+        .iter()
+        .position(|t| t.is_keyword("REFERENCES"))
+        .and_then(|i| tokens.get(i + 1))
+        .and_then(|t| t.identifier())
+        .ok_or("Foreign key without a referenced table")?;
+    Ok((name, table))
+}
+
+/// The identifiers listed in a parenthesised group
+fn group_identifiers(group: &str) -> Result<Vec<String>, String> {
+    Ok(tokenize(&group[1..group.len() - 1])?
+        .iter()
+        .filter_map(|t| t.identifier())
+        .collect())
+}
+
+/// The CONSTRAINT name a SQLite table declares for a foreign key, which its pragmas leave out
+pub fn foreign_key_name(
+    table_sql: &str,
+    columns: &[String],
+    foreign_table: &str,
+) -> Option<String> {
+    TableSql::parse(table_sql)
+        .and_then(|table| table.foreign_keys())
+        .ok()?
+        .into_iter()
+        .find(|key| key.matches(columns, foreign_table))
+        .and_then(|key| key.name)
+}
+
+/// The clause computing a generated column in a SQLite CREATE TABLE statement, e.g.
+/// `GENERATED ALWAYS AS (a * 2) STORED`, which the table pragmas leave out
+pub fn generated_clause(table_sql: &str, column: &str) -> Option<String> {
+    let table = TableSql::parse(table_sql).ok()?;
+    let definition = &table.definitions[table.find_column(column)?];
+    column_constraints(definition)
+        .ok()?
+        .into_iter()
+        .find(|(keyword, _)| keyword == "GENERATED" || keyword == "AS")
+        .map(|(_, range)| definition[range].to_string())
+}
+
+/// The column a definition declares, or None for a table constraint
+fn column_name(definition: &str) -> Option<String> {
+    let tokens = tokenize(definition).ok()?;
+    let first = tokens.first()?;
+    if TABLE_CONSTRAINT_KEYWORDS
+        .iter()
+        .any(|k| first.is_keyword(k))
+    {
+        return None;
+    }
+    first.identifier()
+}
+
+/// Splits the constraints of a column definition, returning the span of each with its leading keyword
+fn column_constraints(definition: &str) -> Result<Vec<(String, Range<usize>)>, String> {
+    let tokens = tokenize(definition)?;
+    let mut segments: Vec<(String, usize, usize)> = Vec::new();
+    // a CONSTRAINT name belongs to the constraint that follows it
+    let mut named_from: Option<usize> = None;
+
+    let mut i = 1;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let keyword = CONSTRAINT_KEYWORDS
+            .iter()
+            .find(|k| token.is_keyword(k))
+            .map(|k| k.to_string());
+        let current = segments.last().map(|(k, _, _)| k.as_str());
+        let prev = &tokens[i - 1];
+        let continues = match (current, keyword.as_deref()) {
+            // ON DELETE SET NULL / SET DEFAULT, NOT DEFERRABLE
+            (Some("REFERENCES"), Some("NULL" | "DEFAULT")) => prev.is_keyword("SET"),
+            (Some("REFERENCES"), Some("NOT")) => tokens
+                .get(i + 1)
+                .is_some_and(|t| t.is_keyword("DEFERRABLE")),
+            (Some("GENERATED"), Some("AS")) => true,
+            // the NULL of NOT NULL
+            (Some("NOT"), Some("NULL")) => prev.is_keyword("NOT"),
+            (_, None) => true,
+            _ => false,
+        };
+
+        match keyword {
+            Some(k) if !continues && k == "CONSTRAINT" => {
+                named_from = Some(token.start);
+                i += 2;
+                continue;
+            }
+            Some(k) if !continues => {
+                let start = named_from.take().unwrap_or(token.start);
+                segments.push((k, start, token.end));
+            }
+            _ => {
+                if let Some(segment) = segments.last_mut() {
+                    segment.2 = token.end;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    Ok(segments
+        .into_iter()
+        .map(|(k, start, end)| (k, start..end))
+        .collect())
+}
+
+/// Whether a column definition declares a generated column, which cannot be copied into
+fn is_generated(definition: &str) -> bool {
+    column_constraints(definition)
+        .is_ok_and(|cs| cs.iter().any(|(k, _)| k == "GENERATED" || k == "AS"))
+}
+
+/// The definition replacing a modified column
+fn modified_definition(
+    old_definition: &str,
+    column: &ColumnSpec,
+    table_primary_key: bool,
+) -> Result<String, String> {
+    let mut def = format!(
+        "{} {}",
+        quote_identifier(&column.name, &DbType::Sqlite),
+        column.data_type
+    );
+    let old_constraints = column_constraints(old_definition)?;
+
+    if column.primary_key && !table_primary_key {
+        def.push_str(" PRIMARY KEY");
+        let autoincrement = old_constraints.iter().any(|(k, range)| {
+            k == "PRIMARY" && mentions(&old_definition[range.clone()], "AUTOINCREMENT")
+        });
+        if autoincrement {
+            def.push_str(" AUTOINCREMENT");
+        }
+    } else if column.unique {
+        def.push_str(" UNIQUE");
+    }
+    if !column.nullable {
+        def.push_str(" NOT NULL");
+    }
+    if let Some(default) = &column.default {
+        def.push_str(&format!(" DEFAULT {}", default));
+    }
+
+    for (keyword, range) in old_constraints {
+        if matches!(
+            keyword.as_str(),
+            "CHECK" | "COLLATE" | "REFERENCES" | "GENERATED" | "AS"
+        ) {
+            def.push(' ');
+            def.push_str(&old_definition[range]);
+        }
+    }
+    Ok(def)
+}
+
+/// Whether the connected SQLite supports ALTER TABLE DROP COLUMN
+pub async fn supports_drop_column(pool: &AnyPool) -> Result<bool, String> {
+    let version: String = sqlx::query_scalar("SELECT sqlite_version()")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().unwrap_or(0));
+    let (major, minor) = (parts.next().unwrap_or(0), parts.next().unwrap_or(0));
+    Ok((major, minor) >= DROP_COLUMN_VERSION)
+}
+
+/// The statements of a SQLite table rebuild, following the procedure in
+/// https://www.sqlite.org/lang_altertable.html#otheralter: the table is recreated with the
+/// new definition, its rows copied over, and its indexes and triggers recreated, all in one
+/// transaction with foreign key enforcement suspended.
+pub struct RebuildPlan {
+    quoted: String,
+    /// Whether the connections enforce foreign keys, restored and checked after the rebuild
+    foreign_keys: bool,
+    /// Statements run in the transaction before the table's indexes and triggers are recreated
+    steps: Vec<String>,
+    /// The table's indexes and triggers, as type, name and SQL
+    dependents: Vec<(String, String, String)>,
+}
+
+impl RebuildPlan {
+    // both pragmas are no-ops inside a transaction; legacy_alter_table stops the final rename
+    // from checking views and triggers while the table is missing
+    fn setup(&self) -> [String; 2] {
+        [
+            "PRAGMA foreign_keys = OFF".to_string(),
+            "PRAGMA legacy_alter_table = ON".to_string(),
+        ]
+    }
+
+    fn restore(&self) -> [String; 2] {
+        [
+            format!(
+                "PRAGMA foreign_keys = {}",
+                if self.foreign_keys { "ON" } else { "OFF" }
+            ),
+            "PRAGMA legacy_alter_table = OFF".to_string(),
+        ]
+    }
+
+    fn foreign_key_check(&self) -> Option<String> {
+        self.foreign_keys
+            .then(|| format!("PRAGMA foreign_key_check({})", self.quoted))
+    }
+
+    /// Every statement the rebuild runs, in order
+    pub fn statements(&self) -> Vec<String> {
+        let mut statements = self.setup().to_vec();
+        statements.push("BEGIN".to_string());
+        statements.extend(self.steps.iter().cloned());
+        statements.extend(self.dependents.iter().map(|(_, _, sql)| sql.clone()));
+        statements.extend(self.foreign_key_check());
+        statements.push("COMMIT".to_string());
+        statements.extend(self.restore());
+        statements
+    }
+}
+
+/// Plans a change to a SQLite table as a rebuild, reading the schema without changing the
+/// database. A renamed column is renamed first, so that SQLite updates the indexes, triggers
+/// and views using it; the SQL they are recreated from comes from the same rename on an empty
+/// in-memory copy of the schema.
+pub async fn plan_rebuild(
+    pool: &AnyPool,
+    table: &str,
+    change: TableChange,
+) -> Result<RebuildPlan, String> {
+    let db_type = DbType::Sqlite;
+    let quoted = quote_identifier(table, &db_type);
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    let foreign_keys: i64 = sqlx::query_scalar("PRAGMA foreign_keys")
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let table_sql: Option<String> =
+        sqlx::query_scalar("SELECT sql FROM sqlite_schema WHERE type = 'table' AND name = ?")
+            .bind(table)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    let mut table_sql = table_sql.ok_or_else(|| format!("Table '{}' not found", table))?;
+    // indexes behind PRIMARY KEY and UNIQUE constraints have no SQL and come back with the table
+    let mut dependents = fetch_dependents(&mut conn, table).await?;
+
+    let mut steps = Vec::new();
+    if let TableChange::ModifyColumn { old_name, column } = &change
+        && !old_name.eq_ignore_ascii_case(&column.name)
+    {
+        let exists: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ? COLLATE NOCASE",
+        )
+        .bind(table)
+        .bind(old_name)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+        if exists == 0 {
+            return Err(format!("Column '{}' not found", old_name));
+        }
+
+        let sql = format!(
+            "ALTER TABLE {} RENAME COLUMN {} TO {}",
+            quoted,
+            quote_identifier(old_name, &db_type),
+            quote_identifier(&column.name, &db_type)
+        );
+        (table_sql, dependents) = renamed_schema(&mut conn, table, &sql).await?;
+        steps.push(sql);
+    }
+
+    let old_table = TableSql::parse(&table_sql)?;
+    let mut new_table = TableSql::parse(&table_sql)?;
+    match &change {
+        TableChange::ModifyColumn { column, .. } => {
+            let pos = new_table
+                .find_column(&column.name)
+                .ok_or_else(|| format!("Column '{}' not found", column.name))?;
+            let table_primary_key = new_table.has_table_primary_key();
+            new_table.definitions[pos] =
+                modified_definition(&new_table.definitions[pos], column, table_primary_key)?;
+        }
+        TableChange::DropColumn(name) => {
+            let pos = new_table
+                .find_column(name)
+                .ok_or_else(|| format!("Column '{}' not found", name))?;
+            new_table.definitions.remove(pos);
+            if new_table
+                .definitions
+                .iter()
+                .all(|d| column_name(d).is_none())
+            {
+                return Err("Cannot drop the only column of a table".to_string());
+            }
+            if new_table
+                .definitions
+                .iter()
+                .any(|d| column_name(d).is_none() && mentions(d, name))
+            {
+                return Err(format!(
+                    "Column '{}' is used by a table constraint and cannot be dropped",
+                    name
+                ));
+            }
+        }
+        TableChange::AddConstraint(sql) => new_table.definitions.push(sql.clone()),
+        TableChange::DropForeignKey {
+            columns,
+            foreign_table,
+        } => {
+            let key = new_table
+                .foreign_keys()?
+                .into_iter()
+                .find(|key| key.matches(columns, foreign_table))
+                .ok_or("Foreign key not found in the table definition")?;
+            match key.clause {
+                Some(range) => {
+                    let def = &new_table.definitions[key.definition];
+                    new_table.definitions[key.definition] =
+                        format!("{}{}", def[..range.start].trim_end(), &def[range.end..]);
+                }
+                None => {
+                    new_table.definitions.remove(key.definition);
+                }
+            }
+        }
+        TableChange::Redefine { table_sql, indexes } => {
+            new_table = TableSql::parse(table_sql)?;
+            // the triggers stay, the indexes are the new definition's
+            dependents.retain(|(kind, _, _)| kind == "trigger");
+            dependents.extend(indexes.iter().map(|(name, sql)| {
+                let sql = sql.trim().trim_end_matches(';').to_string();
+                ("index".to_string(), name.clone(), sql)
+            }));
+        }
+    }
+
+    let copied: Vec<String> = new_table
+        .definitions
+        .iter()
+        .filter(|def| !is_generated(def))
+        .filter_map(|def| column_name(def))
+        .filter(|name| old_table.find_column(name).is_some())
+        .map(|name| quote_identifier(&name, &db_type))
+        .collect();
+    let copied = copied.join(", ");
+
+    let temp_name = format!("__rebuild_{}", table);
+    steps.extend([
+        new_table.to_sql(&temp_name),
+        format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {}",
+            quote_identifier(&temp_name, &db_type),
+            copied,
+            copied,
+            quoted
+        ),
+        format!("DROP TABLE {}", quoted),
+        format!(
+            "ALTER TABLE {} RENAME TO {}",
+            quote_identifier(&temp_name, &db_type),
+            quoted
+        ),
+    ]);
+
+    Ok(RebuildPlan {
+        quoted,
+        foreign_keys: foreign_keys == 1,
+        steps,
+        dependents,
+    })
+}
+
+/// The indexes and triggers of a table that have SQL, in creation order
+async fn fetch_dependents(
+    conn: &mut AnyConnection,
+    table: &str,
+) -> Result<Vec<(String, String, String)>, String> {
+    let rows = sqlx::query(
+        "SELECT type, name, sql FROM sqlite_schema
+         WHERE tbl_name = ? AND type IN ('index', 'trigger') AND sql IS NOT NULL
+         ORDER BY rowid",
+    )
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            (
+                row.try_get("type").unwrap_or_default(),
+                row.try_get("name").unwrap_or_default(),
+                row.try_get("sql").unwrap_or_default(),
+            )
+        })
+        .collect())
+}
+
+/// The table's SQL and its indexes and triggers after renaming one of its columns, found by
+/// running the rename on an empty in-memory copy of the database's schema
+async fn renamed_schema(
+    conn: &mut AnyConnection,
+    table: &str,
+    rename_sql: &str,
+) -> Result<(String, Vec<(String, String, String)>), String> {
+    // triggers need their table to exist, views are only resolved when used
+    let objects = sqlx::query(
+        "SELECT name, sql FROM sqlite_schema
+         WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'
+         ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'index' THEN 1 WHEN 'view' THEN 2 ELSE 3 END,
+                  rowid",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut scratch = AnyConnection::connect("sqlite::memory:")
+        .await
+        .map_err(|e| e.to_string())?;
+    sqlx::query("PRAGMA legacy_alter_table = ON")
+        .execute(&mut scratch)
+        .await
+        .map_err(|e| e.to_string())?;
+    for object in objects {
+        let name: String = object.try_get("name").unwrap_or_default();
+        let sql: String = object.try_get("sql").unwrap_or_default();
+        // virtual tables create their shadow tables themselves
+        let exists: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_schema WHERE name = ?")
+            .bind(&name)
+            .fetch_one(&mut scratch)
+            .await
+            .map_err(|e| e.to_string())?;
+        if exists == 0 {
+            sqlx::query(&sql)
+                .execute(&mut scratch)
+                .await
+                .map_err(|e| format!("Failed to copy '{}' to plan the rename: {}", name, e))?;
+        }
+    }
+    sqlx::query(rename_sql)
+        .execute(&mut scratch)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let table_sql: String =
+        sqlx::query_scalar("SELECT sql FROM sqlite_schema WHERE type = 'table' AND name = ?")
+            .bind(table)
+            .fetch_one(&mut scratch)
+            .await
+            .map_err(|e| e.to_string())?;
+    let dependents = fetch_dependents(&mut scratch, table).await?;
+    let _ = scratch.close().await;
+    Ok((table_sql, dependents))
+}
+
+/// Runs a planned rebuild on one of the pool's connections
+pub async fn run_rebuild(pool: &AnyPool, plan: &RebuildPlan) -> Result<(), String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    for sql in plan.setup() {
+        sqlx::query(&sql)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let result = run_in_transaction(&mut conn, plan).await;
+
+    for sql in plan.restore() {
+        if let Err(e) = sqlx::query(&sql).execute(&mut *conn).await {
+            // a connection left with the wrong settings must not go back to the pool
+            conn.detach();
+            return result.and(Err(e.to_string()));
+        }
+    }
+    result
+}
+
+/// Runs the statements between BEGIN and COMMIT, committing only when no rows are left
+/// violating foreign keys
+async fn run_in_transaction(conn: &mut AnyConnection, plan: &RebuildPlan) -> Result<(), String> {
+    let mut tx = conn.begin().await.map_err(|e| e.to_string())?;
+    for sql in &plan.steps {
+        sqlx::query(sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    for (kind, name, sql) in &plan.dependents {
+        sqlx::query(sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to recreate {} '{}': {}", kind, name, e))?;
+    }
+    if let Some(sql) = plan.foreign_key_check() {
+        let violations = sqlx::query(&sql)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        if !violations.is_empty() {
+            return Err(format!(
+                "The change leaves {} rows violating foreign keys",
+                violations.len()
+            ));
+        }
+    }
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::any::AnyPoolOptions;
+
+    use super::*;
+
+    async fn rebuild(pool: &AnyPool, table: &str, change: TableChange) -> Result<(), String> {
+        let plan = plan_rebuild(pool, table, change).await?;
+        run_rebuild(pool, &plan).await
+    }
+
+    #[test]
+    fn test_table_sql_parse() {
+        let sql = "CREATE TABLE \"t\" (\n  id INTEGER PRIMARY KEY, -- the key, really\n  \
+                   \"na,me\" TEXT CHECK (length(\"na,me\") > 0) DEFAULT 'a,b',\n  \
+                   UNIQUE (id, \"na,me\")\n) WITHOUT ROWID;";
+        let table = TableSql::parse(sql).unwrap();
+        assert_eq!(
+            table.definitions,
+            vec![
+                "id INTEGER PRIMARY KEY",
+                "\"na,me\" TEXT CHECK (length(\"na,me\") > 0) DEFAULT 'a,b'",
+                "UNIQUE (id, \"na,me\")",
+            ]
+        );
+        assert_eq!(table.options, "WITHOUT ROWID");
+        assert_eq!(column_name(&table.definitions[1]).as_deref(), Some("na,me"));
+        assert_eq!(column_name(&table.definitions[2]), None);
+        assert!(!table.has_table_primary_key());
+        assert!(mentions(&table.definitions[2], "NA,ME"));
+    }
+
+    #[test]
+    fn test_generated_clause() {
+        let sql = "CREATE TABLE t (a INT, b INT GENERATED ALWAYS AS (a * 2) STORED NOT NULL, \
+                   c AS (a + 1))";
+        assert_eq!(
+            generated_clause(sql, "b").as_deref(),
+            Some("GENERATED ALWAYS AS (a * 2) STORED")
+        );
+        assert_eq!(generated_clause(sql, "C").as_deref(), Some("AS (a + 1)"));
+        assert_eq!(generated_clause(sql, "a"), None);
+    }
+
+    #[test]
+    fn test_modified_definition_keeps_constraints() {
+        let old = "owner INT CONSTRAINT fk_owner REFERENCES users (id) ON DELETE SET NULL \
+                   NOT DEFERRABLE NOT NULL DEFAULT 0 COLLATE NOCASE CHECK (owner >= 0)";
+        let constraints: Vec<String> = column_constraints(old)
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(
+            constraints,
+            vec!["REFERENCES", "NOT", "DEFAULT", "COLLATE", "CHECK"]
+        );
+
+        let column = ColumnSpec {
+            name: "owner_id".to_string(),
+            data_type: "INTEGER".to_string(),
+            nullable: true,
+            primary_key: false,
+            unique: false,
+            default: None,
+        };
+        assert_eq!(
+            modified_definition(old, &column, false).unwrap(),
+            "\"owner_id\" INTEGER CONSTRAINT fk_owner REFERENCES users (id) ON DELETE SET NULL \
+             NOT DEFERRABLE COLLATE NOCASE CHECK (owner >= 0)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_foreign_keys() {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let setup = "
+            PRAGMA foreign_keys = ON;
+            CREATE TABLE teams (org INTEGER, id INTEGER, PRIMARY KEY (org, id));
+            CREATE TABLE users (id INTEGER PRIMARY KEY);
+            CREATE TABLE members (
+                user_id INTEGER CONSTRAINT fk_user REFERENCES users (id) NOT NULL,
+                org INTEGER,
+                team INTEGER
+            );
+            INSERT INTO users VALUES (1);
+            INSERT INTO teams VALUES (1, 1);
+            INSERT INTO members VALUES (1, 1, 1);
+        ";
+        sqlx::raw_sql(setup).execute(&pool).await.unwrap();
+
+        let constraint = "CONSTRAINT \"fk_team\" FOREIGN KEY (\"org\", \"team\") \
+                          REFERENCES \"teams\" (\"org\", \"id\") ON DELETE CASCADE";
+        rebuild(
+            &pool,
+            "members",
+            TableChange::AddConstraint(constraint.to_string()),
+        )
+        .await
+        .unwrap();
+        let drop = TableChange::DropForeignKey {
+            columns: vec!["USER_ID".to_string()],
+            foreign_table: "users".to_string(),
+        };
+        // planning only reads the schema
+        let statements = plan_rebuild(&pool, "members", drop)
+            .await
+            .unwrap()
+            .statements();
+        assert_eq!(
+            statements[..3],
+            [
+                "PRAGMA foreign_keys = OFF",
+                "PRAGMA legacy_alter_table = ON",
+                "BEGIN"
+            ]
+        );
+        assert!(statements.contains(&"PRAGMA foreign_key_check(\"members\")".to_string()));
+        let unchanged: String =
+            sqlx::query_scalar("SELECT sql FROM sqlite_schema WHERE name = 'members'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(unchanged.contains("fk_user"));
+
+        let drop = TableChange::DropForeignKey {
+            columns: vec!["USER_ID".to_string()],
+            foreign_table: "users".to_string(),
+        };
+        rebuild(&pool, "members", drop).await.unwrap();
+
+        let sql: String =
+            sqlx::query_scalar("SELECT sql FROM sqlite_schema WHERE name = 'members'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                "CREATE TABLE \"members\" (user_id INTEGER NOT NULL, org INTEGER, team INTEGER, {})",
+                constraint
+            )
+        );
+        let columns = vec!["org".to_string(), "team".to_string()];
+        assert_eq!(
+            foreign_key_name(&sql, &columns, "TEAMS").as_deref(),
+            Some("fk_team")
+        );
+        assert_eq!(foreign_key_name(&sql, &columns[..1], "teams"), None);
+
+        // the new key is enforced, and rows violating it are refused by the rebuild
+        sqlx::query("DELETE FROM teams")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM members")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0);
+        sqlx::raw_sql("PRAGMA foreign_keys = OFF; INSERT INTO members VALUES (2, 9, 9); PRAGMA foreign_keys = ON")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let constraint = "FOREIGN KEY (user_id) REFERENCES users (id)".to_string();
+        assert!(
+            rebuild(&pool, "members", TableChange::AddConstraint(constraint))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_table() {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let setup = "
+            CREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT);
+            CREATE TABLE posts (
+                id INTEGER PRIMARY KEY,
+                author INTEGER REFERENCES users (id),
+                score TEXT NOT NULL,
+                note TEXT
+            );
+            CREATE INDEX posts_score ON posts (score);
+            CREATE TABLE log (msg TEXT);
+            CREATE TRIGGER posts_log AFTER INSERT ON posts BEGIN INSERT INTO log VALUES (new.score); END;
+            CREATE VIEW top_posts AS SELECT id, score FROM posts;
+            INSERT INTO users (name) VALUES ('a');
+            INSERT INTO posts (author, score, note) VALUES (1, '10', 'x');
+        ";
+        sqlx::raw_sql(setup).execute(&pool).await.unwrap();
+
+        let change = TableChange::ModifyColumn {
+            old_name: "score".to_string(),
+            column: ColumnSpec {
+                name: "points".to_string(),
+                data_type: "INTEGER".to_string(),
+                nullable: true,
+                primary_key: false,
+                unique: false,
+                default: Some("0".to_string()),
+            },
+        };
+        let plan = plan_rebuild(&pool, "posts", change).await.unwrap();
+        let statements = plan.statements();
+        assert_eq!(
+            statements[3],
+            "ALTER TABLE \"posts\" RENAME COLUMN \"score\" TO \"points\""
+        );
+        assert!(statements.contains(
+            &"CREATE TRIGGER posts_log AFTER INSERT ON posts BEGIN INSERT INTO log VALUES (new.\"points\"); END"
+                .to_string()
+        ));
+        let unchanged: String =
+            sqlx::query_scalar("SELECT sql FROM sqlite_schema WHERE name = 'posts_score'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(unchanged.contains("score"));
+        run_rebuild(&pool, &plan).await.unwrap();
+        rebuild(&pool, "posts", TableChange::DropColumn("note".to_string()))
+            .await
+            .unwrap();
+
+        let sql: String = sqlx::query_scalar("SELECT sql FROM sqlite_schema WHERE name = 'posts'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            sql,
+            "CREATE TABLE \"posts\" (id INTEGER PRIMARY KEY, author INTEGER REFERENCES users (id), \
+             \"points\" INTEGER DEFAULT 0)"
+        );
+        let typed: String = sqlx::query_scalar("SELECT typeof(points) FROM top_posts")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(typed, "integer");
+
+        // the index and trigger were recreated, and foreign keys are enforced again
+        let index: String =
+            sqlx::query_scalar("SELECT sql FROM sqlite_schema WHERE name = 'posts_score'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(index.contains("points"));
+        sqlx::query("INSERT INTO posts (author, points) VALUES (1, 5)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let logged: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM log")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        // one entry from the setup insert and one from the trigger recreated by the rebuild
+        assert_eq!(logged, 2);
+        assert!(
+            sqlx::query("INSERT INTO posts (author, points) VALUES (99, 1)")
+                .execute(&pool)
+                .await
+                .is_err()
+        );
+
+        assert!(
+            rebuild(
+                &pool,
+                "posts",
+                TableChange::DropColumn("missing".to_string())
+            )
+            .await
+            .is_err()
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use std::time::Instant;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use axum::{
     Json, Router,
@@ -6,14 +9,24 @@ use axum::{
     http::HeaderMap,
     routing::{get, post},
 };
-use sqlx::any::AnyPoolOptions;
+use sqlx::{
+    AnyConnection, Connection, Database, Executor, any::AnyPoolOptions, mysql::MySqlPoolOptions,
+    pool::PoolOptions, postgres::PgPoolOptions, sqlite::SqlitePoolOptions,
+};
 
 use crate::{
     auth::AuthSession,
-    models::{ApiResponse, ConnectRequest, ConnectResponse, DbType, Session, StatusResponse},
+    models::{
+        ApiResponse, ConnectRequest, ConnectResponse, DbType, NativePool, Session, StatusResponse,
+    },
+    settings::session_init_sql,
+    sql_utils::{is_valid_identifier, quote_identifier},
     state::SessionStore,
 };
 
+/// How long the native pool keeps a connection nobody uses
+const NATIVE_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub fn routes(session_store: SessionStore) -> Router {
     Router::new()
         .route("/connect", post(connect))
@@ -30,49 +43,204 @@ fn build_connection_string(req: &ConnectRequest) -> String {
         host = "host.docker.internal".to_string();
     }
 
+    // credentials and names may hold characters with a meaning in URLs, such as @, : or /
+    let (username, password, database) = (
+        percent_encode(&req.username),
+        percent_encode(&req.password),
+        percent_encode(&req.database),
+    );
     match req.db_type {
+        DbType::Postgres if !req.search_path.is_empty() => format!(
+            "postgres://{}:{}@{}:{}/{}?options={}",
+            username,
+            password,
+            host,
+            req.port,
+            database,
+            search_path_option(&req.search_path)
+        ),
         DbType::Postgres => format!(
             "postgres://{}:{}@{}:{}/{}",
-            req.username, req.password, host, req.port, req.database
+            username, password, host, req.port, database
         ),
         DbType::Mysql => format!(
             "mysql://{}:{}@{}:{}/{}",
-            req.username, req.password, host, req.port, req.database
+            username, password, host, req.port, database
         ),
         DbType::Sqlite => format!("sqlite:{}", req.database),
     }
 }
 
+/// Percent-encodes everything but the characters URLs never reserve
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+/// Builds the URL-encoded startup option that sets search_path on every pooled connection
+fn search_path_option(search_path: &[String]) -> String {
+    let schemas: Vec<String> = search_path
+        .iter()
+        .map(|s| quote_identifier(s, &DbType::Postgres))
+        .collect();
+    percent_encode(&format!("-c search_path={}", schemas.join(",")))
+}
+
+/// Checks the requested search_path and picks the schema unqualified table names resolve to,
+/// none when the server's search_path decides it
+fn default_schema(req: &ConnectRequest) -> Result<Option<String>, String> {
+    match req.db_type {
+        DbType::Postgres => {
+            if let Some(invalid) = req.search_path.iter().find(|s| !is_valid_identifier(s)) {
+                return Err(format!("Invalid schema name in search_path: {}", invalid));
+            }
+            Ok(req.search_path.first().cloned())
+        }
+        _ if !req.search_path.is_empty() => {
+            Err("search_path is only supported on PostgreSQL".to_string())
+        }
+        DbType::Mysql => Ok(Some(req.database.clone())),
+        DbType::Sqlite => Ok(Some("main".to_string())),
+    }
+}
+
+/// Builds the driver-specific pool rows are decoded with, from the Any pool's connection string
+/// and settings. It connects lazily, since the Any pool has already verified the credentials,
+/// and lets idle connections go soon, so it only holds connections while rows are being read.
+fn build_native_pool(
+    db_type: &DbType,
+    connection_string: &str,
+    init_sql: &str,
+) -> Result<NativePool, sqlx::Error> {
+    Ok(match db_type {
+        DbType::Postgres => NativePool::Postgres(
+            with_init_sql(PgPoolOptions::new(), init_sql)
+                .max_connections(5)
+                .idle_timeout(NATIVE_IDLE_TIMEOUT)
+                .connect_lazy(connection_string)?,
+        ),
+        DbType::Mysql => NativePool::Mysql(
+            with_init_sql(MySqlPoolOptions::new(), init_sql)
+                .max_connections(5)
+                .idle_timeout(NATIVE_IDLE_TIMEOUT)
+                .connect_lazy(connection_string)?,
+        ),
+        DbType::Sqlite => NativePool::Sqlite(
+            with_init_sql(SqlitePoolOptions::new(), init_sql)
+                .max_connections(5)
+                .idle_timeout(NATIVE_IDLE_TIMEOUT)
+                .connect_lazy(connection_string)?,
+        ),
+    })
+}
+
+/// Runs the session's setting statements on each connection a pool opens
+fn with_init_sql<DB>(options: PoolOptions<DB>, init_sql: &str) -> PoolOptions<DB>
+where
+    DB: Database,
+    for<'c> &'c mut DB::Connection: Executor<'c>,
+{
+    if init_sql.is_empty() {
+        return options;
+    }
+    let init_sql: Arc<str> = init_sql.into();
+    options.after_connect(move |conn, _| {
+        let init_sql = init_sql.clone();
+        Box::pin(async move {
+            sqlx::raw_sql(&init_sql).execute(&mut *conn).await?;
+            Ok(())
+        })
+    })
+}
+
+/// Connects as requested, opening the pools of a session with the given token
+pub async fn open_session(token: String, request: ConnectRequest) -> Result<Session, String> {
+    let schema = default_schema(&request)?;
+    let init_sql = session_init_sql(&request)?;
+    // both pools must open the same in-memory database, which lives as long as a connection to it
+    let in_memory = request.db_type == DbType::Sqlite && request.database == ":memory:";
+    let connection_string = if in_memory {
+        format!("sqlite:file:{}?mode=memory", token)
+    } else {
+        build_connection_string(&request)
+    };
+    // a pool retries connections its settings fail on until it times out, so they are tried first
+    if !init_sql.is_empty() {
+        let mut conn = AnyConnection::connect(&connection_string)
+            .await
+            .map_err(|e| e.to_string())?;
+        let applied = conn.execute(init_sql.as_str()).await;
+        let _ = conn.close().await;
+        applied.map_err(|e| e.to_string())?;
+    }
+    let pool = with_init_sql(AnyPoolOptions::new(), &init_sql)
+        .max_connections(5)
+        .min_connections(if in_memory { 1 } else { 0 })
+        .connect(&connection_string)
+        .await
+        .map_err(|e| e.to_string())?;
+    // the first schema of the server's search_path that exists, as for unqualified names
+    let schema = match schema {
+        Some(schema) => schema,
+        None => sqlx::query_scalar::<_, Option<String>>("SELECT current_schema()::text")
+            .fetch_one(&pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .unwrap_or_else(|| "public".to_string()),
+    };
+    let native_pool = build_native_pool(&request.db_type, &connection_string, &init_sql)
+        .map_err(|e| e.to_string())?;
+    Ok(Session {
+        token,
+        pool,
+        native_pool,
+        database: request.database.clone(),
+        db_type: request.db_type.clone(),
+        schema,
+        created_at: Instant::now(),
+        connection: request,
+    })
+}
+
+/// Puts a reopened session in place of the stored one with the same token. The session left
+/// over, the replaced one or the new one if the session disconnected meanwhile, has its pools
+/// closed once their running queries finish.
+pub async fn replace_session(session_store: &SessionStore, session: Session) -> Result<(), String> {
+    let (stale, result) = match session_store.write().await.get_mut(&session.token) {
+        Some(current) => (std::mem::replace(current, session), Ok(())),
+        None => (session, Err("Invalid or expired session".to_string())),
+    };
+    tokio::spawn(async move { stale.close().await });
+    result
+}
+
 // POST /api/connect
 async fn connect(
     State(session_store): State<SessionStore>,
     Json(payload): Json<ConnectRequest>,
 ) -> Json<ApiResponse<ConnectResponse>> {
-    let connection_string = build_connection_string(&payload);
-    match AnyPoolOptions::new()
-        .max_connections(5)
-        .connect(&connection_string)
-        .await
-    {
-        Ok(pool) => {
-            let token = uuid::Uuid::new_v4().to_string();
-            let session = Session {
+    let token = uuid::Uuid::new_v4().to_string();
+    match open_session(token.clone(), payload).await {
+        Ok(session) => {
+            let response = ConnectResponse {
                 token: token.clone(),
-                pool,
-                database: payload.database.clone(),
-                db_type: payload.db_type.clone(),
-                created_at: Instant::now(),
+                database: session.database.clone(),
+                db_type: session.db_type.clone(),
+                schema: session.schema.clone(),
             };
             let mut store = session_store.write().await;
-            store.insert(token.clone(), session);
+            store.insert(token, session);
 
-            Json(ApiResponse::success(ConnectResponse {
-                token,
-                database: payload.database,
-                db_type: payload.db_type,
-            }))
+            Json(ApiResponse::success(response))
         }
-        Err(e) => Json(ApiResponse::error(e.to_string())),
+        Err(e) => Json(ApiResponse::error(e)),
     }
 }
 
@@ -82,10 +250,11 @@ async fn status(AuthSession(session): AuthSession) -> Json<ApiResponse<StatusRes
         connected: true,
         database: Some(session.database),
         db_type: Some(session.db_type),
+        schema: Some(session.schema),
     }))
 }
 
-// POST /api/disconnect - Removes session from store
+// POST /api/disconnect - Removes session from store and closes its pools
 async fn disconnect(
     State(session_store): State<SessionStore>,
     headers: HeaderMap,
@@ -100,13 +269,16 @@ async fn disconnect(
     };
 
     if !token.is_empty() {
-        let mut store = session_store.write().await;
-        store.remove(token);
+        let removed = session_store.write().await.remove(token);
+        if let Some(session) = removed {
+            tokio::spawn(async move { session.close().await });
+        }
     }
 
     Json(ApiResponse::success(StatusResponse {
         connected: false,
         database: None,
         db_type: None,
+        schema: None,
     }))
 }
@@ -1,47 +1,130 @@
+use std::collections::HashMap;
+
 use axum::{
     Json, Router,
     extract::{Path, Query},
     routing::{delete, get, post, put},
 };
 use serde_json::{Value, json};
-use sqlx::{Column, Row};
+use sqlx::Row;
 
 use crate::{
     auth::AuthSession,
-    models::{ApiResponse, DbType, PaginationParams},
-    sql_utils::{is_valid_identifier, quote_identifier},
+    binding::{BindValue, ColumnType, fetch_column_types, get_primary_key_columns},
+    decode::fetch_rows,
+    filter::compile_filter,
+    keyset::{CursorDirection, Keyset},
+    models::{
+        ApiResponse, BulkOperation, BulkRequest, CountMode, DbType, FilterNode, PaginationMode,
+        PaginationParams, RowDeleteRequest, RowQueryRequest, RowUpdateRequest, Session,
+    },
+    sql_utils::{TableRef, quote_identifier},
     state::SessionStore,
+    statement::{Statement, load_writer},
 };
 
+/// Largest number of operations accepted by one bulk request
+const MAX_BULK_OPERATIONS: usize = 1000;
+
+/// Tables estimated below this many rows are counted exactly in `CountMode::Auto`
+const EXACT_COUNT_THRESHOLD: i64 = 100_000;
+
 pub fn routes(session_store: SessionStore) -> Router {
     Router::new()
         .route("/{name}", get(read_rows))
         .route("/{name}", post(insert_row))
+        .route("/{name}", put(update_row_by_key))
+        .route("/{name}", delete(delete_row_by_key))
+        .route("/{name}/query", post(query_rows))
+        .route("/{name}/bulk", post(bulk_rows))
         .route("/{name}/{id}", put(update_row))
         .route("/{name}/{id}", delete(delete_row))
         .with_state(session_store)
 }
 
-/// GET /api/table/{name}?page=&limit=&sort=&order= - Read rows (paginated)
+/// GET /api/table/{name}?page=&limit=&sort=&order=&filter=&count=&pagination=&cursor= - Read rows (paginated)
+/// `filter` is a URL-encoded JSON filter tree, see `FilterNode`.
+/// `pagination=cursor` (or any `cursor`) switches from page numbers to keyset pagination
 async fn read_rows(
     AuthSession(session): AuthSession,
     Path(name): Path<String>,
     Query(params): Query<PaginationParams>,
 ) -> Json<ApiResponse<Value>> {
-    if !is_valid_identifier(&name) {
-        return Json(ApiResponse::error("Invalid table name"));
-    }
+    let filter = match params.filter.as_deref() {
+        Some(raw) if !raw.trim().is_empty() => match serde_json::from_str::<FilterNode>(raw) {
+            Ok(f) => Some(f),
+            Err(e) => return Json(ApiResponse::error(format!("Invalid filter: {}", e))),
+        },
+        _ => None,
+    };
+
+    let request = RowQueryRequest {
+        page: params.page,
+        limit: params.limit,
+        sort: params.sort,
+        order: params.order,
+        filter,
+        count: params.count,
+        pagination: params.pagination,
+        cursor: params.cursor.filter(|c| !c.trim().is_empty()),
+    };
+    select_rows(session, name, request).await
+}
+
+/// POST /api/table/{name}/query - Read rows (paginated) with the filter tree in the body
+async fn query_rows(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Json(payload): Json<RowQueryRequest>,
+) -> Json<ApiResponse<Value>> {
+    select_rows(session, name, payload).await
+}
+
+/// Shared implementation of read_rows and query_rows
+async fn select_rows(
+    session: Session,
+    name: String,
+    params: RowQueryRequest,
+) -> Json<ApiResponse<Value>> {
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
 
-    let pool = session.pool;
     let db_type = session.db_type.clone();
 
-    let table_quoted = quote_identifier(&name, &db_type);
+    let table_quoted = table.quoted(&db_type);
     let page = params.page.unwrap_or(1).max(1);
-    let limit = params.page.unwrap_or(50).min(100);
-    let offset = (page - 1) * limit;
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+    let offset = match (page - 1).checked_mul(limit) {
+        Some(offset) => offset,
+        None => return Json(ApiResponse::error("Page number is too large")),
+    };
+    let pagination = if params.cursor.is_some() {
+        PaginationMode::Cursor
+    } else {
+        params.pagination.unwrap_or_default()
+    };
+
+    let column_types =
+        if params.filter.is_some() || params.sort.is_some() || pagination == PaginationMode::Cursor
+        {
+            match fetch_column_types(&session.pool, &db_type, &table).await {
+                Ok(types) => types,
+                Err(e) => return Json(ApiResponse::error(e)),
+            }
+        } else {
+            HashMap::new()
+        };
 
     let order_clause = match &params.sort {
-        Some(sort_col) if is_valid_identifier(sort_col) => {
+        Some(sort_col) if !column_types.contains_key(sort_col) => {
+            return Json(ApiResponse::error(format!(
+                "Unknown sort column: {}",
+                sort_col
+            )));
+        }
+        Some(sort_col) => {
             let dir = params.order.as_deref().unwrap_or("ASC");
             let dir = if dir.eq_ignore_ascii_case("DESC") {
                 "DESC"
@@ -50,195 +133,290 @@ async fn read_rows(
             };
             format!("ORDER BY {} {}", quote_identifier(sort_col, &db_type), dir)
         }
-        _ => String::new(),
+        None => String::new(),
     };
 
-    // For MySQL and PostgreSQL, we need to cast columns to avoid type issues with Any driver
-    let sql = if matches!(db_type, DbType::Mysql) {
-        // First get column names
-        let db_name = session.database.clone();
-        let cols_sql = format!(
-            "SELECT CAST(COLUMN_NAME AS CHAR) as col_name FROM information_schema.columns WHERE table_schema = '{}' AND table_name = '{}' ORDER BY ordinal_position",
-            db_name.replace("'", "''"),
-            name.replace("'", "''")
-        );
-        let col_rows = sqlx::query(&cols_sql).fetch_all(&pool).await;
-
-        match col_rows {
-            Ok(rows) => {
-                let column_casts: Vec<String> = rows
-                    .iter()
-                    .filter_map(|row| {
-                        row.try_get::<String, _>("col_name")
-                            .ok()
-                            .or_else(|| {
-                                row.try_get::<Vec<u8>, _>("col_name")
-                                    .ok()
-                                    .map(|b| String::from_utf8_lossy(&b).to_string())
-                            })
-                            .map(|col| format!("CAST(`{}` AS CHAR) as `{}`", col, col))
-                    })
-                    .collect();
-
-                if column_casts.is_empty() {
-                    format!(
-                        "SELECT * FROM {} {} LIMIT {} OFFSET {}",
-                        table_quoted, order_clause, limit, offset
-                    )
-                } else {
-                    format!(
-                        "SELECT {} FROM {} {} LIMIT {} OFFSET {}",
-                        column_casts.join(", "),
-                        table_quoted,
-                        order_clause,
-                        limit,
-                        offset
-                    )
-                }
-            }
-            Err(_) => format!(
-                "SELECT * FROM {} {} LIMIT {} OFFSET {}",
-                table_quoted, order_clause, limit, offset
-            ),
+    let mut bind_params = Vec::new();
+    let filter_condition = match &params.filter {
+        Some(filter) => match compile_filter(filter, &column_types, &db_type, &mut bind_params) {
+            Ok(condition) => Some(condition),
+            Err(e) => return Json(ApiResponse::error(e)),
+        },
+        None => None,
+    };
+
+    if pagination == PaginationMode::Cursor {
+        return match select_rows_keyset(
+            &session,
+            &table,
+            &params,
+            &column_types,
+            filter_condition,
+            bind_params,
+            limit,
+        )
+        .await
+        {
+            Ok(data) => Json(ApiResponse::success(data)),
+            Err(e) => Json(ApiResponse::error(e)),
+        };
+    }
+
+    let where_clause = filter_condition
+        .map(|condition| format!("WHERE {}", condition))
+        .unwrap_or_default();
+
+    // one extra row is fetched to tell whether another page exists
+    let sql = format!(
+        "SELECT * FROM {} {} {} LIMIT {} OFFSET {}",
+        table_quoted,
+        where_clause,
+        order_clause,
+        limit + 1,
+        offset
+    );
+
+    let mut result = match fetch_rows(&session.native_pool, &sql, bind_params.clone()).await {
+        Ok(result) => result,
+        Err(e) => return Json(ApiResponse::error(e.to_string())),
+    };
+    let has_more = result.rows.len() > limit as usize;
+    result.rows.truncate(limit as usize);
+
+    let (total_count, total_count_exact) = if !has_more && (page == 1 || !result.rows.is_empty()) {
+        // last page reached, so the total is known without counting
+        (Some(offset as i64 + result.rows.len() as i64), true)
+    } else {
+        let mode = params.count.unwrap_or_default();
+        match count_rows(&session, &table, &where_clause, bind_params, mode).await {
+            Ok(count) => count,
+            Err(e) => return Json(ApiResponse::error(e)),
         }
-    } else if matches!(db_type, DbType::Postgres) {
-        // PostgreSQL: cast all columns to text to avoid Any driver type issues
-        let cols_sql = format!(
-            "SELECT column_name::text as col_name FROM information_schema.columns WHERE table_schema = 'public' AND table_name = '{}' ORDER BY ordinal_position",
-            name.replace("'", "''")
-        );
-        let col_rows = sqlx::query(&cols_sql).fetch_all(&pool).await;
-
-        match col_rows {
-            Ok(rows) => {
-                let column_casts: Vec<String> = rows
-                    .iter()
-                    .filter_map(|row| {
-                        row.try_get::<String, _>("col_name")
-                            .ok()
-                            .map(|col| format!("\"{}\"::text as \"{}\"", col, col))
-                    })
-                    .collect();
-
-                if column_casts.is_empty() {
-                    format!(
-                        "SELECT * FROM {} {} LIMIT {} OFFSET {}",
-                        table_quoted, order_clause, limit, offset
-                    )
-                } else {
-                    format!(
-                        "SELECT {} FROM {} {} LIMIT {} OFFSET {}",
-                        column_casts.join(", "),
-                        table_quoted,
-                        order_clause,
-                        limit,
-                        offset
-                    )
-                }
-            }
-            Err(_) => format!(
-                "SELECT * FROM {} {} LIMIT {} OFFSET {}",
-                table_quoted, order_clause, limit, offset
-            ),
+    };
+    let total_pages = total_count.map(|total| (total + limit as i64 - 1) / limit as i64);
+
+    Json(ApiResponse::success(json!({
+        "rows": result.rows,
+        "columns": result.columns,
+        "page": page,
+        "limit": limit,
+        "total_count": total_count,
+        "total_count_exact": total_count_exact,
+        "total_pages": total_pages,
+        "has_more": has_more
+    })))
+}
+
+/// Cursor-mode page read, which seeks past the cursor's boundary row instead of skipping
+/// rows with OFFSET. Rows are keyed on the sort column (if any) followed by the primary key columns.
+async fn select_rows_keyset(
+    session: &Session,
+    table: &TableRef,
+    params: &RowQueryRequest,
+    column_types: &HashMap<String, ColumnType>,
+    filter_condition: Option<String>,
+    mut bind_params: Vec<BindValue>,
+    limit: u32,
+) -> Result<Value, String> {
+    let db_type = &session.db_type;
+
+    let pk_cols = get_primary_key_columns(&session.pool, db_type, table).await?;
+    if pk_cols.is_empty() {
+        return Err("Cursor pagination needs a primary key".to_string());
+    }
+
+    let mut key_columns = Vec::new();
+    if let Some(sort_col) = &params.sort {
+        let sort_type = column_types
+            .get(sort_col)
+            .ok_or_else(|| format!("Unknown sort column: {}", sort_col))?;
+        key_columns.push((sort_col.clone(), sort_type.clone()));
+    }
+    for pk_col in pk_cols {
+        if params.sort.as_ref() == Some(&pk_col) {
+            continue;
         }
+        let pk_type = column_types
+            .get(&pk_col)
+            .ok_or_else(|| format!("Unknown column: {}", pk_col))?;
+        key_columns.push((pk_col.clone(), pk_type.clone()));
+    }
+    if let Some((name, column)) = key_columns
+        .iter()
+        .find(|(_, column)| !Keyset::orderable(column, db_type))
+    {
+        return Err(format!(
+            "Cursor pagination cannot sort on column '{}' of type {}",
+            name, column.data_type
+        ));
+    }
+
+    let descending = params
+        .order
+        .as_deref()
+        .is_some_and(|dir| dir.eq_ignore_ascii_case("DESC"));
+    let keyset = Keyset::new(key_columns, descending);
+
+    let count_params = bind_params.clone();
+    let (forward, seek_condition) = match &params.cursor {
+        Some(cursor) => {
+            let (direction, values) = keyset.decode_cursor(cursor)?;
+            let forward = direction == CursorDirection::Next;
+            let seek = keyset.seek_condition(&values, forward, db_type, &mut bind_params)?;
+            (forward, Some(seek))
+        }
+        None => (true, None),
+    };
+
+    let conditions: Vec<&str> = filter_condition
+        .iter()
+        .chain(seek_condition.iter())
+        .map(String::as_str)
+        .collect();
+    let where_clause = if conditions.is_empty() {
+        String::new()
     } else {
-        // SQLite: cast columns to avoid Any driver type issues with DATETIME/DATE
-        let cols_sql = format!("SELECT name, type FROM pragma_table_info(?)");
-        let col_rows = sqlx::query(&cols_sql).bind(&name).fetch_all(&pool).await;
-
-        match col_rows {
-            Ok(rows) => {
-                let column_casts: Vec<String> = rows
-                    .iter()
-                    .filter_map(|row| {
-                        let col_name: String = row.try_get("name").ok()?;
-                        let col_type: String = row.try_get("type").ok().unwrap_or_default();
-                        let upper_type = col_type.to_uppercase();
-
-                        // Cast DATETIME/DATE/TIMESTAMP columns to TEXT to avoid Any driver issues
-                        if upper_type.contains("DATETIME")
-                            || upper_type.contains("DATE")
-                            || upper_type.contains("TIMESTAMP")
-                            || upper_type.contains("TIME")
-                        {
-                            Some(format!(
-                                "CAST(\"{}\" AS TEXT) as \"{}\"",
-                                col_name, col_name
-                            ))
-                        } else {
-                            Some(format!("\"{}\"", col_name))
-                        }
-                    })
-                    .collect();
-
-                if column_casts.is_empty() {
-                    format!(
-                        "SELECT * FROM {} {} LIMIT {} OFFSET {}",
-                        table_quoted, order_clause, limit, offset
-                    )
-                } else {
-                    format!(
-                        "SELECT {} FROM {} {} LIMIT {} OFFSET {}",
-                        column_casts.join(", "),
-                        table_quoted,
-                        order_clause,
-                        limit,
-                        offset
-                    )
-                }
-            }
-            Err(_) => format!(
-                "SELECT * FROM {} {} LIMIT {} OFFSET {}",
-                table_quoted, order_clause, limit, offset
-            ),
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    // one extra row is fetched to tell whether the table continues in the reading direction
+    let sql = format!(
+        "SELECT * FROM {} {} {} LIMIT {}",
+        table.quoted(db_type),
+        where_clause,
+        keyset.order_clause(db_type, forward),
+        limit + 1
+    );
+    let mut result = fetch_rows(&session.native_pool, &sql, bind_params)
+        .await
+        .map_err(|e| e.to_string())?;
+    let continues = result.rows.len() > limit as usize;
+    result.rows.truncate(limit as usize);
+
+    // a page read backwards comes out in reverse order
+    let (has_next, has_prev) = if forward {
+        (continues, params.cursor.is_some())
+    } else {
+        result.rows.reverse();
+        (true, continues)
+    };
+    let next_cursor = result
+        .rows
+        .last()
+        .filter(|_| has_next)
+        .map(|row| keyset.encode_cursor(row, CursorDirection::Next));
+    let prev_cursor = result
+        .rows
+        .first()
+        .filter(|_| has_prev)
+        .map(|row| keyset.encode_cursor(row, CursorDirection::Prev));
+
+    let filter_where = filter_condition
+        .map(|condition| format!("WHERE {}", condition))
+        .unwrap_or_default();
+    let mode = params.count.unwrap_or_default();
+    let (total_count, total_count_exact) =
+        count_rows(session, table, &filter_where, count_params, mode).await?;
+
+    Ok(json!({
+        "rows": result.rows,
+        "columns": result.columns,
+        "limit": limit,
+        "next_cursor": next_cursor,
+        "prev_cursor": prev_cursor,
+        "total_count": total_count,
+        "total_count_exact": total_count_exact,
+        "has_more": next_cursor.is_some()
+    }))
+}
+
+/// Reads the planner's row estimate for a table, if the database keeps one
+async fn estimate_row_count(
+    pool: &sqlx::Pool<sqlx::Any>,
+    db_type: &DbType,
+    table: &TableRef,
+) -> Option<i64> {
+    let row = match db_type {
+        DbType::Postgres => {
+            // reltuples is -1 for tables that have never been vacuumed or analyzed
+            let sql = "
+                SELECT c.reltuples::bigint as estimate
+                FROM pg_class c
+                JOIN pg_namespace n ON n.oid = c.relnamespace
+                WHERE n.nspname = $1 AND c.relname = $2 AND c.relkind IN ('r', 'p', 'm')
+            ";
+            sqlx::query(sql)
+                .bind(&table.schema)
+                .bind(&table.name)
+                .fetch_optional(pool)
+                .await
         }
+        DbType::Mysql => {
+            let sql = "
+                SELECT CAST(table_rows AS SIGNED) as estimate
+                FROM information_schema.tables
+                WHERE table_schema = ? AND table_name = ? AND table_type = 'BASE TABLE'
+            ";
+            sqlx::query(sql)
+                .bind(&table.schema)
+                .bind(&table.name)
+                .fetch_optional(pool)
+                .await
+        }
+        // SQLite keeps no row statistics
+        DbType::Sqlite => return None,
     };
 
-    match sqlx::query(&sql).fetch_all(&pool).await {
-        Ok(rows) => {
-            let data: Vec<Value> = rows
-                .iter()
-                .map(|row| {
-                    let mut obj = serde_json::Map::new();
-                    for (i, col) in row.columns().iter().enumerate() {
-                        let value: Value = row
-                            .try_get_raw(i)
-                            .ok()
-                            .and_then(|_| {
-                                // Try common types first
-                                if let Ok(s) = row.try_get::<String, _>(i) {
-                                    return Some(json!(s));
-                                }
-                                if let Ok(n) = row.try_get::<i64, _>(i) {
-                                    return Some(json!(n));
-                                }
-                                if let Ok(f) = row.try_get::<f64, _>(i) {
-                                    return Some(json!(f));
-                                }
-                                if let Ok(b) = row.try_get::<bool, _>(i) {
-                                    return Some(json!(b));
-                                }
-                                // For MySQL DATETIME/BLOB types, try bytes and convert to string
-                                if let Ok(bytes) = row.try_get::<Vec<u8>, _>(i) {
-                                    let s = String::from_utf8_lossy(&bytes).to_string();
-                                    return Some(json!(s));
-                                }
-                                None
-                            })
-                            .unwrap_or(Value::Null);
-                        obj.insert(col.name().to_string(), value);
-                    }
-                    Value::Object(obj)
-                })
-                .collect();
-            Json(ApiResponse::success(json!({
-                "rows": data,
-                "page": page,
-                "limit": limit
-            })))
+    row.ok()
+        .flatten()
+        .and_then(|r| r.try_get::<Option<i64>, _>("estimate").ok().flatten())
+        .filter(|n| *n >= 0)
+}
+
+/// Computes the total row count for read_rows, returning the count and whether it is exact
+async fn count_rows(
+    session: &Session,
+    table: &TableRef,
+    where_clause: &str,
+    params: Vec<BindValue>,
+    mode: CountMode,
+) -> Result<(Option<i64>, bool), String> {
+    let estimate = match mode {
+        CountMode::Exact => None,
+        CountMode::Auto | CountMode::Estimate => {
+            estimate_row_count(&session.pool, &session.db_type, table).await
         }
-        Err(e) => Json(ApiResponse::error(e.to_string())),
+    };
+
+    let exact = match mode {
+        CountMode::Exact => true,
+        CountMode::Estimate => false,
+        CountMode::Auto => estimate.is_none_or(|n| n < EXACT_COUNT_THRESHOLD),
+    };
+
+    if !exact {
+        // a table-wide estimate says nothing about how many rows match a filter
+        let estimate = if where_clause.is_empty() {
+            estimate
+        } else {
+            None
+        };
+        return Ok((estimate, false));
     }
+
+    let sql = format!(
+        "SELECT COUNT(*) as total_count FROM {} {}",
+        table.quoted(&session.db_type),
+        where_clause
+    );
+    let result = fetch_rows(&session.native_pool, &sql, params)
+        .await
+        .map_err(|e| e.to_string())?;
+    let total = result
+        .rows
+        .first()
+        .and_then(|row| row["total_count"].as_i64());
+    Ok((total, true))
 }
 
 /// POST /api/table/{name} - Insert new row
@@ -247,272 +425,338 @@ async fn insert_row(
     Path(name): Path<String>,
     Json(payload): Json<Value>,
 ) -> Json<ApiResponse<Value>> {
-    if !is_valid_identifier(&name) {
-        return Json(ApiResponse::error("Invalid table name"));
-    }
-
-    let pool = session.pool;
-    let db_type = session.db_type;
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
 
     let obj = match payload.as_object() {
         Some(o) => o,
         None => return Json(ApiResponse::error("Request body must be a JSON object")),
     };
-    if obj.is_empty() {
-        return Json(ApiResponse::error("No data provided"));
-    }
-
-    let mut columns = Vec::new();
-    let mut sql_values = Vec::new();
 
-    for (col, val) in obj.iter() {
-        if !is_valid_identifier(col) {
-            return Json(ApiResponse::error(&format!("Invalid column name: {}", col)));
-        }
-        columns.push(quote_identifier(col, &db_type));
-
-        // Format value as SQL literal
-        let sql_val = match val {
-            Value::Null => "NULL".to_string(),
-            Value::Bool(b) => {
-                if *b {
-                    "TRUE".to_string()
-                } else {
-                    "FALSE".to_string()
-                }
-            }
-            Value::Number(n) => n.to_string(),
-            Value::String(s) => {
-                // Escape single quotes for SQL
-                let escaped = s.replace('\'', "''");
-                format!("'{}'", escaped)
-            }
-            _ => {
-                let s = val.to_string();
-                let escaped = s.replace('\'', "''");
-                format!("'{}'", escaped)
-            }
-        };
-        sql_values.push(sql_val);
-    }
-
-    let table_quoted = quote_identifier(&name, &db_type);
-    let sql = format!(
-        "INSERT INTO {} ({}) VALUES ({})",
-        table_quoted,
-        columns.join(", "),
-        sql_values.join(", ")
-    );
-
-    // execute the query
+    let writer = match load_writer(&session, &table).await {
+        Ok(w) => w,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let statement = match writer.insert(obj) {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
 
-    match sqlx::query(&sql).execute(&pool).await {
-        Ok(result) => Json(ApiResponse::success(json!({
+    match statement.execute(&session.pool).await {
+        Ok(rows_affected) => Json(ApiResponse::success(json!({
             "message": "Row inserted successfully",
-            "rows_affected": result.rows_affected()
+            "rows_affected": rows_affected
         }))),
         Err(e) => Json(ApiResponse::error(e.to_string())),
     }
 }
 
-/// Helper to get primary key column name
-async fn get_primary_key(
-    pool: &sqlx::Pool<sqlx::Any>,
-    db_type: &DbType,
-    table_name: &str,
-    db_name: &str,
-) -> Result<String, String> {
-    match db_type {
-        DbType::Postgres => {
-            let sql = "
-                SELECT ku.column_name::text
-                FROM information_schema.key_column_usage ku
-                JOIN information_schema.table_constraints tc 
-                    ON ku.constraint_name = tc.constraint_name 
-                    AND ku.table_schema = tc.table_schema
-                WHERE tc.constraint_type = 'PRIMARY KEY'
-                    AND tc.table_schema = 'public' 
-                    AND tc.table_name = $1
-                LIMIT 1
-            ";
-            let row = sqlx::query(sql)
-                .bind(table_name)
-                .fetch_optional(pool)
-                .await
-                .map_err(|e| e.to_string())?;
-
-            match row {
-                Some(r) => r.try_get("column_name").map_err(|e| e.to_string()),
-                None => Ok("id".to_string()), // Fallback
-            }
+/// Turns the `{id}` path segment into a key map, for tables with a single-column primary key
+async fn key_from_id(
+    session: &Session,
+    table: &TableRef,
+    id: String,
+) -> Result<serde_json::Map<String, Value>, String> {
+    let pk_cols = get_primary_key_columns(&session.pool, &session.db_type, table)
+        .await
+        .map_err(|e| format!("Failed to determine primary key: {}", e))?;
+
+    match pk_cols.as_slice() {
+        [pk_col] => {
+            let mut key = serde_json::Map::new();
+            key.insert(pk_col.clone(), Value::String(id));
+            Ok(key)
         }
-        DbType::Mysql => {
-            let safe_db_name = db_name.replace("'", "''");
-            let safe_table_name = table_name.replace("'", "''");
-            let sql = format!(
-                "SELECT COLUMN_NAME 
-                 FROM information_schema.COLUMNS 
-                 WHERE TABLE_SCHEMA = '{}' 
-                 AND TABLE_NAME = '{}' 
-                 AND COLUMN_KEY = 'PRI' 
-                 LIMIT 1",
-                safe_db_name, safe_table_name
-            );
-            let row = sqlx::query(&sql)
-                .fetch_optional(pool)
-                .await
-                .map_err(|e| e.to_string())?;
+        [] => Err(format!(
+            "Table '{}' has no primary key; use PUT or DELETE /api/table/{} with match_full_row",
+            table, table
+        )),
+        _ => Err(format!(
+            "Table '{}' has a composite primary key ({}); use PUT or DELETE /api/table/{} with a key map",
+            table,
+            pk_cols.join(", "),
+            table
+        )),
+    }
+}
 
-            match row {
-                Some(r) => r.try_get("COLUMN_NAME").map_err(|e| e.to_string()),
-                None => Ok("id".to_string()), // Fallback
-            }
-        }
-        DbType::Sqlite => {
-            let rows = sqlx::query(&format!(
-                "PRAGMA table_info({})",
-                quote_identifier(table_name, db_type)
-            ))
-            .fetch_all(pool)
-            .await
-            .map_err(|e| e.to_string())?;
-
-            for row in rows {
-                let pk: i64 = row.try_get("pk").unwrap_or(0);
-                if pk > 0 {
-                    let name: String = row.try_get("name").unwrap_or_default();
-                    return Ok(name);
-                }
-            }
-            Ok("id".to_string()) // Fallback
-        }
+/// Runs an UPDATE or DELETE in a transaction, rolling it back if it touched more than one row
+async fn execute_single_row(
+    pool: &sqlx::Pool<sqlx::Any>,
+    statement: &Statement,
+) -> Result<u64, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let rows_affected = statement
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if rows_affected > 1 {
+        tx.rollback().await.map_err(|e| e.to_string())?;
+        return Err(format!(
+            "Key matched {} rows, so nothing was changed",
+            rows_affected
+        ));
     }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(rows_affected)
 }
 
-/// PUT /api/table/{name}/{id} - Update row by ID
+/// PUT /api/table/{name}/{id} - Update row by ID (single-column primary keys)
 async fn update_row(
     AuthSession(session): AuthSession,
     Path((name, id)): Path<(String, String)>,
     Json(payload): Json<Value>,
 ) -> Json<ApiResponse<Value>> {
-    if !is_valid_identifier(&name) {
-        return Json(ApiResponse::error("Invalid table name"));
-    }
-
-    let pool = session.pool;
-    let db_type = session.db_type;
-    let db_name = session.database;
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
 
     let obj = match payload.as_object() {
         Some(o) => o,
         None => return Json(ApiResponse::error("Request body must be a JSON object")),
     };
-    if obj.is_empty() {
-        return Json(ApiResponse::error("No data provided"));
+    let key = match key_from_id(&session, &table, id).await {
+        Ok(key) => key,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    match update_by_key(&session, &table, &key, obj, false).await {
+        Ok(data) => Json(ApiResponse::success(data)),
+        Err(e) => Json(ApiResponse::error(e)),
     }
+}
 
-    // Get Primary Key column name
-    let pk_col = match get_primary_key(&pool, &db_type, &name, &db_name).await {
-        Ok(pk) => pk,
-        Err(e) => {
-            return Json(ApiResponse::error(format!(
-                "Failed to determine primary key: {}",
-                e
-            )));
-        }
+/// PUT /api/table/{name} - Update the row identified by a key map
+async fn update_row_by_key(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Json(payload): Json<RowUpdateRequest>,
+) -> Json<ApiResponse<Value>> {
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
     };
 
-    let mut set_clauses = Vec::new();
-    for (col, val) in obj.iter() {
-        if !is_valid_identifier(col) {
-            return Json(ApiResponse::error(&format!("Invalid column name: {}", col)));
-        }
-
-        // Format value as SQL literal
-        let sql_val = match val {
-            Value::Null => "NULL".to_string(),
-            Value::Bool(b) => {
-                if *b {
-                    "TRUE".to_string()
-                } else {
-                    "FALSE".to_string()
-                }
-            }
-            Value::Number(n) => n.to_string(),
-            Value::String(s) => {
-                let escaped = s.replace('\'', "''");
-                format!("'{}'", escaped)
-            }
-            _ => {
-                let s = val.to_string();
-                let escaped = s.replace('\'', "''");
-                format!("'{}'", escaped)
-            }
-        };
-        set_clauses.push(format!("{} = {}", quote_identifier(col, &db_type), sql_val));
+    match update_by_key(
+        &session,
+        &table,
+        &payload.key,
+        &payload.values,
+        payload.match_full_row,
+    )
+    .await
+    {
+        Ok(data) => Json(ApiResponse::success(data)),
+        Err(e) => Json(ApiResponse::error(e)),
     }
+}
 
-    // Escape ID value
-    let id_escaped = id.replace('\'', "''");
-    let table_quoted = quote_identifier(&name, &db_type);
-    let pk_quoted = quote_identifier(&pk_col, &db_type);
-    let sql = format!(
-        "UPDATE {} SET {} WHERE {} = '{}'",
-        table_quoted,
-        set_clauses.join(", "),
-        pk_quoted,
-        id_escaped
-    );
+/// Shared implementation of update_row and update_row_by_key
+async fn update_by_key(
+    session: &Session,
+    table: &TableRef,
+    key: &serde_json::Map<String, Value>,
+    obj: &serde_json::Map<String, Value>,
+    match_full_row: bool,
+) -> Result<Value, String> {
+    let writer = load_writer(session, table).await?;
+    let statement = writer.update(key, obj, match_full_row)?;
+    let rows_affected = execute_single_row(&session.pool, &statement).await?;
+
+    Ok(json!({
+        "message": "Row updated successfully",
+        "rows_affected": rows_affected
+    }))
+}
 
-    // execute the query
+/// DELETE /api/table/{name}/{id} - Delete row by ID (single-column primary keys)
+async fn delete_row(
+    AuthSession(session): AuthSession,
+    Path((name, id)): Path<(String, String)>,
+) -> Json<ApiResponse<Value>> {
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
 
-    match sqlx::query(&sql).execute(&pool).await {
-        Ok(result) => Json(ApiResponse::success(json!({
-            "message": "Row updated successfully",
-            "rows_affected": result.rows_affected()
-        }))),
-        Err(e) => Json(ApiResponse::error(e.to_string())),
+    let key = match key_from_id(&session, &table, id).await {
+        Ok(key) => key,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    match delete_by_key(&session, &table, &key, false).await {
+        Ok(data) => Json(ApiResponse::success(data)),
+        Err(e) => Json(ApiResponse::error(e)),
     }
 }
 
-/// DELETE /api/table/{name}/{id} - Delete row by ID
-async fn delete_row(
+/// DELETE /api/table/{name} - Delete the row identified by a key map
+async fn delete_row_by_key(
     AuthSession(session): AuthSession,
-    Path((name, id)): Path<(String, String)>,
+    Path(name): Path<String>,
+    Json(payload): Json<RowDeleteRequest>,
 ) -> Json<ApiResponse<Value>> {
-    if !is_valid_identifier(&name) {
-        return Json(ApiResponse::error("Invalid table name"));
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    match delete_by_key(&session, &table, &payload.key, payload.match_full_row).await {
+        Ok(data) => Json(ApiResponse::success(data)),
+        Err(e) => Json(ApiResponse::error(e)),
     }
+}
 
-    let pool = session.pool;
-    let db_type = session.db_type;
-    let db_name = session.database;
+/// Shared implementation of delete_row and delete_row_by_key
+async fn delete_by_key(
+    session: &Session,
+    table: &TableRef,
+    key: &serde_json::Map<String, Value>,
+    match_full_row: bool,
+) -> Result<Value, String> {
+    let writer = load_writer(session, table).await?;
+    let statement = writer.delete(key, match_full_row)?;
+    let rows_affected = execute_single_row(&session.pool, &statement).await?;
+
+    Ok(json!({
+        "message": "Row deleted successfully",
+        "rows_affected": rows_affected
+    }))
+}
 
-    // Get Primary Key column name
-    let pk_col = match get_primary_key(&pool, &db_type, &name, &db_name).await {
-        Ok(pk) => pk,
-        Err(e) => {
-            return Json(ApiResponse::error(format!(
-                "Failed to determine primary key: {}",
-                e
-            )));
+/// POST /api/table/{name}/bulk - Run inserts, updates and deletes in a single transaction
+/// Any failing operation, or an update or delete matching more than one row, rolls back all of them.
+/// Every operation's outcome is reported by index, whether applied, rolled back, failed or skipped.
+async fn bulk_rows(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Json(payload): Json<BulkRequest>,
+) -> Json<ApiResponse<Value>> {
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    if payload.operations.is_empty() {
+        return Json(ApiResponse::error("No operations provided"));
+    }
+    if payload.operations.len() > MAX_BULK_OPERATIONS {
+        return Json(ApiResponse::error(format!(
+            "Too many operations, at most {} are allowed per request",
+            MAX_BULK_OPERATIONS
+        )));
+    }
+
+    let writer = match load_writer(&session, &table).await {
+        Ok(w) => w,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    // every operation's outcome once the one at `failed` fails: those that ran were rolled
+    // back, the rest never ran
+    let outcomes = |failed: usize, error: &str, affected: &[u64]| -> Vec<Value> {
+        payload
+            .operations
+            .iter()
+            .enumerate()
+            .map(|(i, operation)| match affected.get(i) {
+                Some(n) => json!({
+                    "index": i,
+                    "op": operation.name(),
+                    "status": "rolled_back",
+                    "rows_affected": n
+                }),
+                None if i == failed => json!({
+                    "index": i,
+                    "op": operation.name(),
+                    "status": "failed",
+                    "error": error
+                }),
+                None => json!({ "index": i, "op": operation.name(), "status": "skipped" }),
+            })
+            .collect()
+    };
+
+    // build every statement up front, so invalid input fails before anything is written
+    let mut statements = Vec::new();
+    for (i, operation) in payload.operations.iter().enumerate() {
+        let statement = match operation {
+            BulkOperation::Insert { values } => writer.insert(values),
+            BulkOperation::Update {
+                key,
+                values,
+                match_full_row,
+            } => writer.update(key, values, *match_full_row),
+            BulkOperation::Delete {
+                key,
+                match_full_row,
+            } => writer.delete(key, *match_full_row),
+        };
+        match statement {
+            Ok(s) => statements.push(s),
+            Err(e) => {
+                return Json(ApiResponse::error_with_data(
+                    format!("Operation {} ({}): {}", i, operation.name(), e),
+                    json!({ "results": outcomes(i, &e, &[]) }),
+                ));
+            }
         }
+    }
+
+    let mut tx = match session.pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => return Json(ApiResponse::error(e.to_string())),
     };
 
-    let table_quoted = quote_identifier(&name, &db_type);
-    let pk_quoted = quote_identifier(&pk_col, &db_type);
+    let mut affected = Vec::new();
+    for (i, (operation, statement)) in payload.operations.iter().zip(&statements).enumerate() {
+        let error = match statement.execute(&mut *tx).await {
+            Ok(n) if n > 1 && !matches!(operation, BulkOperation::Insert { .. }) => {
+                format!("key matched {} rows", n)
+            }
+            Ok(n) => {
+                affected.push(n);
+                continue;
+            }
+            Err(e) => e.to_string(),
+        };
 
-    // Note: treating ID as string literal to rely on DB implicit casting (e.g. '1' -> 1). This matches update_row strategy and avoids specific type binding issues with Any driver
-    let id_escaped = id.replace('\'', "''");
-    let sql = format!(
-        "DELETE FROM {} WHERE {} = '{}'",
-        table_quoted, pk_quoted, id_escaped
-    );
+        if let Err(e) = tx.rollback().await {
+            return Json(ApiResponse::error(e.to_string()));
+        }
+        return Json(ApiResponse::error_with_data(
+            format!(
+                "Operation {} ({}) failed: {}; all operations were rolled back",
+                i,
+                operation.name(),
+                error
+            ),
+            json!({ "results": outcomes(i, &error, &affected) }),
+        ));
+    }
 
-    match sqlx::query(&sql).execute(&pool).await {
-        Ok(result) => Json(ApiResponse::success(json!({
-            "message": "Row deleted successfully",
-            "rows_affected": result.rows_affected()
+    let results: Vec<Value> = payload
+        .operations
+        .iter()
+        .zip(&affected)
+        .enumerate()
+        .map(|(i, (operation, n))| {
+            json!({
+                "index": i,
+                "op": operation.name(),
+                "status": "applied",
+                "rows_affected": n
+            })
+        })
+        .collect();
+    match tx.commit().await {
+        Ok(()) => Json(ApiResponse::success(json!({
+            "message": format!("{} operations applied", results.len()),
+            "results": results
         }))),
         Err(e) => Json(ApiResponse::error(e.to_string())),
     }
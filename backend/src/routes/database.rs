@@ -1,24 +1,69 @@
-use axum::{Json, Router, routing::get};
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    routing::{delete, get, post, put},
+};
 use serde_json::{Value, json};
 use sqlx::Row;
 
 use crate::{
     auth::AuthSession,
-    models::{ApiResponse, DbType},
+    models::{
+        ActivityParams, ApiResponse, BackendActivity, ConnectRequest, ConnectResponse,
+        CreateDatabaseRequest, DbType, DropDatabaseParams, KillParams, LockInfo, LockWait,
+        SettingScope, SettingsParams, SwitchDatabaseRequest, TablesParams, UpdateSettingRequest,
+    },
+    routes::{
+        connection::{open_session, replace_session},
+        schema::requested_schema,
+    },
+    settings::{fetch_settings, global_statements, is_valid_setting_name},
+    sql_utils::{is_valid_identifier, quote_identifier},
     state::SessionStore,
+    storage::{fetch_database_stats, fetch_table_storage},
 };
 
 pub fn routes(session_store: SessionStore) -> Router {
     Router::new()
         .route("/info", get(info))
+        .route("/storage", get(storage))
+        .route("/databases", get(list_databases).post(create_database))
+        .route("/databases/{name}", delete(drop_database))
+        .route("/switch", post(switch_database))
+        .route("/activity", get(activity))
+        .route("/activity/{id}", delete(kill_backend))
+        .route("/locks", get(locks))
+        .route("/settings", get(list_settings))
+        .route("/settings/{name}", put(update_setting))
         .with_state(session_store)
 }
 
+/// SQLite has a single database per file, where servers host many
+fn check_server_databases(db_type: &DbType) -> Result<(), String> {
+    match db_type {
+        DbType::Postgres | DbType::Mysql => Ok(()),
+        DbType::Sqlite => {
+            Err("Managing databases is only supported on PostgreSQL and MySQL".to_string())
+        }
+    }
+}
+
+/// SQLite runs inside the process that opened it, with no server sessions to watch
+fn check_server_activity(db_type: &DbType) -> Result<(), String> {
+    match db_type {
+        DbType::Postgres | DbType::Mysql => Ok(()),
+        DbType::Sqlite => {
+            Err("Monitoring activity is only supported on PostgreSQL and MySQL".to_string())
+        }
+    }
+}
+
 /// GET /api/database/info - Get database version, size, charset, etc.
 async fn info(AuthSession(session): AuthSession) -> Json<ApiResponse<Value>> {
     let pool = session.pool;
     let db_type = session.db_type;
     let db_name = session.database;
+    let schema = session.schema;
 
     // Get database version
     let version_sql = match db_type {
@@ -31,26 +76,31 @@ async fn info(AuthSession(session): AuthSession) -> Json<ApiResponse<Value>> {
         Err(_) => "Unknown".to_string(),
     };
 
-    // Get table count
-    let table_count_sql = match db_type {
+    // Get table count, for the session's default schema on PostgreSQL
+    let table_count_query = match db_type {
         DbType::Postgres => {
-            "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = 'public'"
-                .to_string()
+            sqlx::query("SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = $1")
+                .bind(&schema)
         }
-        DbType::Mysql => format!(
-            "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = '{}'",
-            db_name
-        ),
-        DbType::Sqlite => {
-            "SELECT COUNT(*) FROM sqlite_schema WHERE type = 'table' AND name NOT LIKE 'sqlite_%'"
-                .to_string()
+        DbType::Mysql => {
+            sqlx::query("SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = ?")
+                .bind(&db_name)
         }
+        DbType::Sqlite => sqlx::query(
+            "SELECT COUNT(*) FROM sqlite_schema WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        ),
     };
-    let table_count: i64 = match sqlx::query(&table_count_sql).fetch_one(&pool).await {
+    let table_count: i64 = match table_count_query.fetch_one(&pool).await {
         Ok(row) => row.try_get(0).unwrap_or(0),
         Err(_) => 0,
     };
 
+    // Size, encoding and connections, left out where the server won't tell
+    let stats = match fetch_database_stats(&pool, &db_type, &db_name).await {
+        Ok(stats) => json!(stats),
+        Err(_) => json!({}),
+    };
+
     // Build response
     let db_type_str = match db_type {
         DbType::Postgres => "PostgreSQL",
@@ -58,10 +108,567 @@ async fn info(AuthSession(session): AuthSession) -> Json<ApiResponse<Value>> {
         DbType::Sqlite => "SQLite",
     };
 
-    Json(ApiResponse::success(json!({
+    let mut response = json!({
         "database": db_name,
         "db_type": db_type_str,
         "version": version,
         "table_count": table_count
+    });
+    if let (Some(response), Value::Object(stats)) = (response.as_object_mut(), stats) {
+        response.extend(stats);
+    }
+    Json(ApiResponse::success(response))
+}
+
+/// GET /api/database/storage?schema= - Data size, index size and row estimate of each table
+async fn storage(
+    AuthSession(session): AuthSession,
+    Query(params): Query<TablesParams>,
+) -> Json<ApiResponse<Value>> {
+    let schema = match requested_schema(&session, params.schema) {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    match fetch_table_storage(&session.pool, &session.db_type, &schema).await {
+        Ok(tables) => {
+            let total_bytes: i64 = tables.iter().map(|t| t.total_bytes).sum();
+            Json(ApiResponse::success(json!({
+                "schema": schema,
+                "tables": tables,
+                "total_bytes": total_bytes
+            })))
+        }
+        Err(e) => Json(ApiResponse::error(e)),
+    }
+}
+
+/// GET /api/database/databases - List the databases of the server
+async fn list_databases(AuthSession(session): AuthSession) -> Json<ApiResponse<Value>> {
+    if let Err(e) = check_server_databases(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+    let sql = match session.db_type {
+        DbType::Postgres => {
+            "SELECT datname::text AS name FROM pg_database WHERE NOT datistemplate ORDER BY datname"
+        }
+        _ => {
+            "SELECT CAST(SCHEMA_NAME AS CHAR) AS name FROM information_schema.SCHEMATA ORDER BY SCHEMA_NAME"
+        }
+    };
+
+    match sqlx::query(sql).fetch_all(&session.pool).await {
+        Ok(rows) => {
+            let databases: Vec<String> = rows
+                .into_iter()
+                .map(|row| row.try_get("name").unwrap_or_default())
+                .collect();
+            Json(ApiResponse::success(json!({
+                "databases": databases,
+                "current": session.database
+            })))
+        }
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// POST /api/database/databases - Create a database on the server
+async fn create_database(
+    AuthSession(session): AuthSession,
+    Json(payload): Json<CreateDatabaseRequest>,
+) -> Json<ApiResponse<Value>> {
+    if let Err(e) = check_server_databases(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+    if !is_valid_identifier(&payload.name) {
+        return Json(ApiResponse::error("Invalid database name"));
+    }
+
+    let sql = format!(
+        "CREATE DATABASE {}",
+        quote_identifier(&payload.name, &session.db_type)
+    );
+    match sqlx::query(&sql).execute(&session.pool).await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "Database created successfully",
+            "database": payload.name
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// DELETE /api/database/databases/{name}?force= - Drop a database other than the session's
+async fn drop_database(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Query(params): Query<DropDatabaseParams>,
+) -> Json<ApiResponse<Value>> {
+    if let Err(e) = check_server_databases(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+    if !is_valid_identifier(&name) {
+        return Json(ApiResponse::error("Invalid database name"));
+    }
+    if name == session.database {
+        return Json(ApiResponse::error(
+            "Cannot drop the database the session is connected to",
+        ));
+    }
+    if params.force && !matches!(session.db_type, DbType::Postgres) {
+        return Json(ApiResponse::error(
+            "Forcing a drop is only supported on PostgreSQL",
+        ));
+    }
+
+    let sql = format!(
+        "DROP DATABASE {}{}",
+        quote_identifier(&name, &session.db_type),
+        if params.force { " WITH (FORCE)" } else { "" }
+    );
+    match sqlx::query(&sql).execute(&session.pool).await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "Database dropped successfully",
+            "database": name
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// POST /api/database/switch - Reconnect the session to another database of the same server.
+/// The token stays valid; the previous pools are closed once their running queries finish.
+async fn switch_database(
+    State(session_store): State<SessionStore>,
+    AuthSession(session): AuthSession,
+    Json(payload): Json<SwitchDatabaseRequest>,
+) -> Json<ApiResponse<ConnectResponse>> {
+    if let Err(e) = check_server_databases(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+    if !is_valid_identifier(&payload.database) {
+        return Json(ApiResponse::error("Invalid database name"));
+    }
+
+    let request = ConnectRequest {
+        database: payload.database,
+        ..session.connection.clone()
+    };
+    let switched = match open_session(session.token.clone(), request).await {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let response = ConnectResponse {
+        token: switched.token.clone(),
+        database: switched.database.clone(),
+        db_type: switched.db_type.clone(),
+        schema: switched.schema.clone(),
+    };
+
+    if let Err(e) = replace_session(&session_store, switched).await {
+        return Json(ApiResponse::error(e));
+    }
+
+    Json(ApiResponse::success(response))
+}
+
+/// GET /api/database/activity?active= - List the sessions of the server and their running queries,
+/// longest running first
+async fn activity(
+    AuthSession(session): AuthSession,
+    Query(params): Query<ActivityParams>,
+) -> Json<ApiResponse<Value>> {
+    if let Err(e) = check_server_activity(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+    let sql = match session.db_type {
+        DbType::Postgres => format!(
+            "
+                SELECT
+                    pid::bigint AS id,
+                    usename::text AS user_name,
+                    datname::text AS database,
+                    host(client_addr) AS client,
+                    NULLIF(application_name, '') AS application,
+                    backend_type,
+                    state,
+                    wait_event_type,
+                    wait_event,
+                    EXTRACT(EPOCH FROM now() - CASE
+                        WHEN state = 'active' THEN query_start
+                        ELSE COALESCE(state_change, backend_start)
+                    END)::float8 AS duration_seconds,
+                    NULLIF(query, '') AS query
+                FROM pg_stat_activity
+                WHERE pid <> pg_backend_pid() {}
+                ORDER BY duration_seconds DESC NULLS LAST, pid
+            ",
+            if params.active {
+                "AND state <> 'idle'"
+            } else {
+                ""
+            }
+        ),
+        // the table SHOW FULL PROCESSLIST reads, which can be filtered and cast
+        _ => format!(
+            "
+                SELECT
+                    CAST(ID AS SIGNED) AS id,
+                    CAST(USER AS CHAR) AS user_name,
+                    CAST(DB AS CHAR) AS `database`,
+                    CAST(HOST AS CHAR) AS client,
+                    NULL AS application,
+                    NULL AS backend_type,
+                    CAST(COMMAND AS CHAR) AS state,
+                    NULL AS wait_event_type,
+                    CAST(NULLIF(STATE, '') AS CHAR) AS wait_event,
+                    TIME * 1.0e0 AS duration_seconds,
+                    CAST(INFO AS CHAR) AS query
+                FROM information_schema.PROCESSLIST
+                WHERE ID <> CONNECTION_ID() {}
+                ORDER BY TIME DESC, ID
+            ",
+            if params.active {
+                "AND COMMAND <> 'Sleep'"
+            } else {
+                ""
+            }
+        ),
+    };
+
+    match sqlx::query(&sql).fetch_all(&session.pool).await {
+        Ok(rows) => {
+            let backends: Vec<BackendActivity> = rows
+                .into_iter()
+                .map(|row| BackendActivity {
+                    id: row.try_get("id").unwrap_or_default(),
+                    user: row.try_get("user_name").unwrap_or(None),
+                    database: row.try_get("database").unwrap_or(None),
+                    client: row.try_get("client").unwrap_or(None),
+                    application: row.try_get("application").unwrap_or(None),
+                    backend_type: row.try_get("backend_type").unwrap_or(None),
+                    state: row.try_get("state").unwrap_or(None),
+                    wait_event_type: row.try_get("wait_event_type").unwrap_or(None),
+                    wait_event: row.try_get("wait_event").unwrap_or(None),
+                    duration_seconds: row.try_get("duration_seconds").unwrap_or(None),
+                    query: row.try_get("query").unwrap_or(None),
+                })
+                .collect();
+            Json(ApiResponse::success(json!({ "backends": backends })))
+        }
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// DELETE /api/database/activity/{id}?terminate= - Cancel the query a backend is running, or
+/// close its connection altogether
+async fn kill_backend(
+    AuthSession(session): AuthSession,
+    Path(id): Path<i64>,
+    Query(params): Query<KillParams>,
+) -> Json<ApiResponse<Value>> {
+    if let Err(e) = check_server_activity(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+
+    let result = match session.db_type {
+        DbType::Postgres => {
+            let sql = if params.terminate {
+                "SELECT pg_terminate_backend($1::int)"
+            } else {
+                "SELECT pg_cancel_backend($1::int)"
+            };
+            match sqlx::query(sql).bind(id).fetch_one(&session.pool).await {
+                // false, with a warning, when no backend has the pid
+                Ok(row) if row.try_get(0).unwrap_or(false) => Ok(()),
+                Ok(_) => Err(format!("No backend with id {}", id)),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+        _ => {
+            let sql = format!(
+                "KILL {}{}",
+                if params.terminate { "" } else { "QUERY " },
+                id
+            );
+            sqlx::query(&sql)
+                .execute(&session.pool)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        }
+    };
+
+    match result {
+        Ok(()) => Json(ApiResponse::success(json!({
+            "message": if params.terminate {
+                "Backend terminated successfully"
+            } else {
+                "Query cancelled successfully"
+            },
+            "id": id
+        }))),
+        Err(e) => Json(ApiResponse::error(e)),
+    }
+}
+
+/// GET /api/database/locks - List the locks on tables and the waits between backends, with the
+/// backends at the head of each blocking chain
+async fn locks(AuthSession(session): AuthSession) -> Json<ApiResponse<Value>> {
+    if let Err(e) = check_server_activity(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+    // pg_locks reports when a wait started from PostgreSQL 14, before only the statement's
+    // start is known
+    let wait_start = if matches!(session.db_type, DbType::Postgres) {
+        match sqlx::query_scalar::<_, i32>("SELECT current_setting('server_version_num')::int")
+            .fetch_one(&session.pool)
+            .await
+        {
+            Ok(version) if version >= 140000 => "l.waitstart",
+            Ok(_) => "w.query_start",
+            Err(e) => return Json(ApiResponse::error(e.to_string())),
+        }
+    } else {
+        ""
+    };
+    let (locks_sql, waits_sql) = match session.db_type {
+        // every transaction holds a lock on its own ids, only those waited on are listed
+        DbType::Postgres => (
+            "
+                SELECT
+                    l.pid::bigint AS backend_id,
+                    l.locktype AS lock_type,
+                    l.mode,
+                    l.granted,
+                    n.nspname::text AS schema,
+                    c.relname::text AS relation
+                FROM pg_locks l
+                LEFT JOIN pg_class c ON c.oid = l.relation
+                LEFT JOIN pg_namespace n ON n.oid = c.relnamespace
+                WHERE l.pid <> pg_backend_pid()
+                  AND (l.relation IS NOT NULL OR NOT l.granted)
+                  AND n.nspname IS DISTINCT FROM 'pg_catalog'
+                ORDER BY l.granted, l.pid, schema, relation
+            ",
+            // a row wait is on the holder's transaction, after locking the row's tuple
+            format!(
+                "
+                    SELECT
+                        w.pid::bigint AS waiting_id,
+                        w.query AS waiting_query,
+                        b.pid::bigint AS blocking_id,
+                        b.query AS blocking_query,
+                        b.state AS blocking_state,
+                        l.locktype AS lock_type,
+                        l.mode,
+                        n.nspname::text AS schema,
+                        c.relname::text AS relation,
+                        EXTRACT(EPOCH FROM now() - {})::float8 AS wait_seconds,
+                        EXTRACT(EPOCH FROM now() - b.xact_start)::float8 AS blocking_seconds
+                    FROM pg_locks l
+                    JOIN pg_stat_activity w ON w.pid = l.pid
+                    CROSS JOIN LATERAL unnest(pg_blocking_pids(l.pid)) AS blocker(pid)
+                    JOIN pg_stat_activity b ON b.pid = blocker.pid
+                    LEFT JOIN pg_class c ON c.oid = COALESCE(l.relation, (
+                        SELECT t.relation FROM pg_locks t
+                        WHERE t.pid = l.pid AND t.locktype = 'tuple' AND t.granted
+                        LIMIT 1
+                    ))
+                    LEFT JOIN pg_namespace n ON n.oid = c.relnamespace
+                    WHERE NOT l.granted
+                    ORDER BY wait_seconds DESC NULLS LAST, waiting_id, blocking_id
+                ",
+                wait_start
+            ),
+        ),
+        // InnoDB locks, from the performance schema of MySQL 8. Every row a transaction touches
+        // is locked, only table locks and waits are listed.
+        _ => (
+            "
+                SELECT
+                    CAST(t.PROCESSLIST_ID AS SIGNED) AS backend_id,
+                    CAST(l.LOCK_TYPE AS CHAR) AS lock_type,
+                    CAST(l.LOCK_MODE AS CHAR) AS mode,
+                    l.LOCK_STATUS = 'GRANTED' AS granted,
+                    CAST(l.OBJECT_SCHEMA AS CHAR) AS `schema`,
+                    CAST(l.OBJECT_NAME AS CHAR) AS relation
+                FROM performance_schema.data_locks l
+                LEFT JOIN performance_schema.threads t ON t.THREAD_ID = l.THREAD_ID
+                WHERE l.LOCK_TYPE = 'TABLE' OR l.LOCK_STATUS = 'WAITING'
+                ORDER BY granted, backend_id, `schema`, relation
+            ",
+            "
+                SELECT
+                    CAST(rt.PROCESSLIST_ID AS SIGNED) AS waiting_id,
+                    CAST(rt.PROCESSLIST_INFO AS CHAR) AS waiting_query,
+                    CAST(bt.PROCESSLIST_ID AS SIGNED) AS blocking_id,
+                    CAST(bt.PROCESSLIST_INFO AS CHAR) AS blocking_query,
+                    CAST(bt.PROCESSLIST_COMMAND AS CHAR) AS blocking_state,
+                    CAST(rl.LOCK_TYPE AS CHAR) AS lock_type,
+                    CAST(rl.LOCK_MODE AS CHAR) AS mode,
+                    CAST(rl.OBJECT_SCHEMA AS CHAR) AS `schema`,
+                    CAST(rl.OBJECT_NAME AS CHAR) AS relation,
+                    TIMESTAMPDIFF(SECOND, rtrx.trx_wait_started, NOW()) * 1.0e0 AS wait_seconds,
+                    TIMESTAMPDIFF(SECOND, btrx.trx_started, NOW()) * 1.0e0 AS blocking_seconds
+                FROM performance_schema.data_lock_waits w
+                JOIN performance_schema.data_locks rl
+                  ON rl.ENGINE_LOCK_ID = w.REQUESTING_ENGINE_LOCK_ID
+                JOIN performance_schema.threads rt ON rt.THREAD_ID = w.REQUESTING_THREAD_ID
+                JOIN performance_schema.threads bt ON bt.THREAD_ID = w.BLOCKING_THREAD_ID
+                LEFT JOIN information_schema.INNODB_TRX rtrx
+                  ON rtrx.trx_mysql_thread_id = rt.PROCESSLIST_ID
+                LEFT JOIN information_schema.INNODB_TRX btrx
+                  ON btrx.trx_mysql_thread_id = bt.PROCESSLIST_ID
+                ORDER BY wait_seconds DESC, waiting_id, blocking_id
+            "
+            .to_string(),
+        ),
+    };
+
+    let locks: Vec<LockInfo> = match sqlx::query(locks_sql).fetch_all(&session.pool).await {
+        Ok(rows) => rows
+            .into_iter()
+            .map(|row| LockInfo {
+                backend_id: row.try_get("backend_id").unwrap_or(None),
+                lock_type: row.try_get("lock_type").unwrap_or_default(),
+                mode: row.try_get("mode").unwrap_or_default(),
+                // a boolean on PostgreSQL, an integer on MySQL
+                granted: row
+                    .try_get("granted")
+                    .unwrap_or_else(|_| row.try_get::<i64, _>("granted").unwrap_or(0) == 1),
+                schema: row.try_get("schema").unwrap_or(None),
+                relation: row.try_get("relation").unwrap_or(None),
+            })
+            .collect(),
+        Err(e) => return Json(ApiResponse::error(e.to_string())),
+    };
+    let waits: Vec<LockWait> = match sqlx::query(&waits_sql).fetch_all(&session.pool).await {
+        Ok(rows) => rows
+            .into_iter()
+            .map(|row| LockWait {
+                waiting_id: row.try_get("waiting_id").unwrap_or_default(),
+                waiting_query: row.try_get("waiting_query").unwrap_or(None),
+                blocking_id: row.try_get("blocking_id").unwrap_or_default(),
+                blocking_query: row.try_get("blocking_query").unwrap_or(None),
+                blocking_state: row.try_get("blocking_state").unwrap_or(None),
+                lock_type: row.try_get("lock_type").unwrap_or_default(),
+                mode: row.try_get("mode").unwrap_or_default(),
+                schema: row.try_get("schema").unwrap_or(None),
+                relation: row.try_get("relation").unwrap_or(None),
+                wait_seconds: row.try_get("wait_seconds").unwrap_or(None),
+                blocking_seconds: row.try_get("blocking_seconds").unwrap_or(None),
+            })
+            .collect(),
+        Err(e) => return Json(ApiResponse::error(e.to_string())),
+    };
+
+    // blockers that aren't waiting themselves, whose transactions hold the chains up
+    let mut root_blockers: Vec<i64> = waits
+        .iter()
+        .map(|w| w.blocking_id)
+        .filter(|id| !waits.iter().any(|w| w.waiting_id == *id))
+        .collect();
+    root_blockers.sort_unstable();
+    root_blockers.dedup();
+
+    Json(ApiResponse::success(json!({
+        "locks": locks,
+        "waits": waits,
+        "root_blockers": root_blockers
+    })))
+}
+
+/// GET /api/database/settings?search= - List the server's settings as the session sees them
+async fn list_settings(
+    AuthSession(session): AuthSession,
+    Query(params): Query<SettingsParams>,
+) -> Json<ApiResponse<Value>> {
+    let mut settings = match fetch_settings(&session.pool, &session.db_type).await {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    if let Some(search) = params.search.filter(|s| !s.is_empty()) {
+        let search = search.to_lowercase();
+        settings.retain(|setting| {
+            setting.name.to_lowercase().contains(&search)
+                || setting
+                    .description
+                    .as_ref()
+                    .is_some_and(|d| d.to_lowercase().contains(&search))
+        });
+    }
+
+    Json(ApiResponse::success(json!({ "settings": settings })))
+}
+
+/// PUT /api/database/settings/{name} - Change a setting for the session's connections, which are
+/// reopened with it, or for the whole server
+async fn update_setting(
+    State(session_store): State<SessionStore>,
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Json(payload): Json<UpdateSettingRequest>,
+) -> Json<ApiResponse<Value>> {
+    if !is_valid_setting_name(&name) {
+        return Json(ApiResponse::error("Invalid setting name"));
+    }
+    let settings = match fetch_settings(&session.pool, &session.db_type).await {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    // MySQL and SQLite names are case-insensitive, as are PostgreSQL's unquoted
+    let setting = match settings.iter().find(|s| s.name.eq_ignore_ascii_case(&name)) {
+        Some(s) => s,
+        None => return Json(ApiResponse::error(format!("Unknown setting: '{}'", name))),
+    };
+    let editable = match payload.scope {
+        SettingScope::Session => setting.session_editable,
+        SettingScope::Global => setting.global_editable,
+    };
+    if !editable {
+        return Json(ApiResponse::error(format!(
+            "'{}' can't be changed at {} level",
+            setting.name,
+            match payload.scope {
+                SettingScope::Session => "session",
+                SettingScope::Global => "global",
+            }
+        )));
+    }
+
+    match payload.scope {
+        SettingScope::Session => {
+            let mut request = session.connection.clone();
+            request
+                .settings
+                .insert(setting.name.clone(), payload.value.clone());
+            // a rejected value fails the new pool's first connection, leaving the session as is
+            let updated = match open_session(session.token.clone(), request).await {
+                Ok(s) => s,
+                Err(e) => return Json(ApiResponse::error(e)),
+            };
+            if let Err(e) = replace_session(&session_store, updated).await {
+                return Json(ApiResponse::error(e));
+            }
+        }
+        SettingScope::Global => {
+            let statements =
+                match global_statements(&session.db_type, &setting.name, &payload.value) {
+                    Ok(s) => s,
+                    Err(e) => return Json(ApiResponse::error(e)),
+                };
+            for statement in statements {
+                if let Err(e) = sqlx::query(&statement).execute(&session.pool).await {
+                    return Json(ApiResponse::error(e.to_string()));
+                }
+            }
+        }
+    }
+
+    Json(ApiResponse::success(json!({
+        "message": "Setting changed successfully",
+        "setting": setting.name,
+        "value": payload.value,
+        "scope": payload.scope,
+        "requires_restart": setting.requires_restart
     })))
 }
@@ -6,18 +6,32 @@ use axum::{
     response::Response,
     routing::get,
 };
-use serde_json::json;
-use sqlx::{Column, Row};
+use futures_util::{StreamExt, stream};
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
 
 use crate::{
     auth::AuthSession,
-    models::{DbType, ExportParams},
-    sql_utils::{is_valid_identifier, quote_identifier},
+    decode::{decodable_sql, fetch_rows_stream},
+    dump::{INSERT_BATCH_SIZE, TableSchema, dump_header},
+    models::{DbType, ExportParams, Session},
+    routes::schema::{fetch_schemas, fetch_tables},
+    sql_utils::{TableRef, quote_identifier},
     state::SessionStore,
 };
 
+/// CSV output is sent to the client in chunks of about this many bytes
+const CSV_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Chunks produced ahead of the client before the export waits for it to catch up
+const BUFFERED_CHUNKS: usize = 8;
+
+/// Sending half of an export body; an `Err` aborts the download
+type ChunkSender = mpsc::Sender<Result<String, String>>;
+
 pub fn routes(session_store: SessionStore) -> Router {
     Router::new()
+        .route("/", get(export_database))
         .route("/{name}", get(export_table))
         .with_state(session_store)
 }
@@ -41,189 +55,268 @@ fn csv_escape(value: &str) -> String {
     }
 }
 
-/// GET /api/export/{name}?format=csv - Export table data as CSV file
-async fn export_table(
-    AuthSession(session): AuthSession,
-    Path(name): Path<String>,
-    Query(params): Query<ExportParams>,
-) -> Response {
-    if !is_valid_identifier(&name) {
-        return error_response(StatusCode::BAD_REQUEST, "Invalid table name");
+/// Helper: format a decoded JSON value as a CSV field
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => csv_escape(s),
+        other => csv_escape(&other.to_string()),
     }
+}
 
-    let format = params.format.unwrap_or_else(|| "csv".to_string());
-    if format != "csv" {
-        return error_response(
-            StatusCode::BAD_REQUEST,
-            &format!("Unsupported format: '{}'. Supported formats: csv", format),
-        );
-    }
+/// Sends a chunk of the export, failing once the client has gone away
+async fn send(tx: &ChunkSender, chunk: String) -> Result<(), String> {
+    tx.send(Ok(chunk))
+        .await
+        .map_err(|_| "Client disconnected".to_string())
+}
 
-    let pool = session.pool;
-    let db_type = session.db_type;
+/// Runs an export in a background task and streams what it sends as a file download.
+/// The bounded channel holds the task back while the client is slow, and the task stops
+/// when the client disconnects and the body is dropped. An error before the first chunk
+/// becomes a JSON error response; one after it aborts the download.
+async fn stream_attachment<F, Fut>(content_type: &str, filename: &str, export: F) -> Response
+where
+    F: FnOnce(ChunkSender) -> Fut,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::channel(BUFFERED_CHUNKS);
+    let task = export(tx.clone());
+    tokio::spawn(async move {
+        if let Err(e) = task.await {
+            let _ = tx.send(Err(e)).await;
+        }
+    });
 
-    let table_quoted = quote_identifier(&name, &db_type);
+    let first = match rx.recv().await {
+        Some(Err(e)) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e),
+        first => first,
+    };
+    let rest = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let body = stream::iter(first).chain(rest);
 
-    let sql = match &db_type {
-        DbType::Postgres => {
-            let cols_sql = format!(
-                "SELECT column_name::text as col_name FROM information_schema.columns \
-                 WHERE table_schema = 'public' AND table_name = '{}' \
-                 ORDER BY ordinal_position",
-                name.replace("'", "''")
-            );
-            match sqlx::query(&cols_sql).fetch_all(&pool).await {
-                Ok(rows) => {
-                    let casts: Vec<String> = rows
-                        .iter()
-                        .filter_map(|row| {
-                            row.try_get::<String, _>("col_name")
-                                .ok()
-                                .map(|col| format!("\"{}\"::text as \"{}\"", col, col))
-                        })
-                        .collect();
-                    if casts.is_empty() {
-                        format!("SELECT * FROM {}", table_quoted)
-                    } else {
-                        format!("SELECT {} FROM {}", casts.join(", "), table_quoted)
-                    }
-                }
-                Err(_) => format!("SELECT * FROM {}", table_quoted),
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        )
+        .body(Body::from_stream(body))
+        .unwrap()
+}
+
+/// Writes a SQL dump of the given tables: per table its CREATE TABLE, its rows as batched
+/// INSERTs, its sequences set past them and then its indexes, so the data loads without
+/// maintaining them row by row.
+/// Foreign keys are added at the end, once every table is loaded, so rows can be inserted in any order.
+async fn write_dump(
+    session: Session,
+    tables: Vec<TableRef>,
+    tx: ChunkSender,
+) -> Result<(), String> {
+    let db_type = &session.db_type;
+
+    // all metadata is read up front, so a missing table fails before anything is sent
+    let mut schemas = Vec::new();
+    for table in &tables {
+        schemas.push(TableSchema::load(&session, table).await?);
+    }
+
+    let mut out = dump_header(&session.database, db_type);
+
+    // Postgres tables are schema-qualified, and every schema but public has to be created
+    if matches!(db_type, DbType::Postgres) {
+        let mut created: Vec<&str> = vec!["public"];
+        for table in &tables {
+            if !created.contains(&table.schema.as_str()) {
+                out.push_str(&format!(
+                    "CREATE SCHEMA IF NOT EXISTS {};\n",
+                    quote_identifier(&table.schema, db_type)
+                ));
+                created.push(&table.schema);
             }
         }
-        DbType::Mysql => {
-            let db_name = session.database.clone();
-            let cols_sql = format!(
-                "SELECT CAST(COLUMN_NAME AS CHAR) as col_name \
-                 FROM information_schema.columns \
-                 WHERE table_schema = '{}' AND table_name = '{}' \
-                 ORDER BY ordinal_position",
-                db_name.replace("'", "''"),
-                name.replace("'", "''")
-            );
-            match sqlx::query(&cols_sql).fetch_all(&pool).await {
-                Ok(rows) => {
-                    let casts: Vec<String> = rows
-                        .iter()
-                        .filter_map(|row| {
-                            row.try_get::<String, _>("col_name")
-                                .ok()
-                                .or_else(|| {
-                                    row.try_get::<Vec<u8>, _>("col_name")
-                                        .ok()
-                                        .map(|b| String::from_utf8_lossy(&b).to_string())
-                                })
-                                .map(|col| format!("CAST(`{}` AS CHAR) as `{}`", col, col))
-                        })
-                        .collect();
-                    if casts.is_empty() {
-                        format!("SELECT * FROM {}", table_quoted)
-                    } else {
-                        format!("SELECT {} FROM {}", casts.join(", "), table_quoted)
-                    }
-                }
-                Err(_) => format!("SELECT * FROM {}", table_quoted),
+    }
+
+    for schema in &schemas {
+        out.push_str(&format!("\n-- Table: {}\n\n", schema.table));
+        out.push_str(&schema.create_table(db_type));
+
+        let sql = format!("SELECT * FROM {}", schema.table.quoted(db_type));
+        let sql = decodable_sql(&session.native_pool, &sql)
+            .await
+            .map_err(|e| format!("Failed to fetch data from '{}': {}", schema.table, e))?;
+        let mut batches = fetch_rows_stream(&session.native_pool, &sql).chunks(INSERT_BATCH_SIZE);
+        let mut first_batch = true;
+        while let Some(batch) = batches.next().await {
+            let rows = batch
+                .into_iter()
+                .collect::<Result<Vec<Value>, _>>()
+                .map_err(|e| format!("Failed to fetch data from '{}': {}", schema.table, e))?;
+            if first_batch {
+                out.push('\n');
+                first_batch = false;
             }
+            out.push_str(&schema.insert(&rows, db_type));
+            send(&tx, std::mem::take(&mut out)).await?;
         }
-        DbType::Sqlite => {
-            let cols_sql = "SELECT name, type FROM pragma_table_info(?)".to_string();
-            match sqlx::query(&cols_sql).bind(&name).fetch_all(&pool).await {
-                Ok(rows) => {
-                    let casts: Vec<String> = rows
-                        .iter()
-                        .filter_map(|row| {
-                            let col_name: String = row.try_get("name").ok()?;
-                            let col_type: String = row.try_get("type").ok().unwrap_or_default();
-                            let upper = col_type.to_uppercase();
-                            if upper.contains("DATETIME")
-                                || upper.contains("DATE")
-                                || upper.contains("TIMESTAMP")
-                                || upper.contains("TIME")
-                            {
-                                Some(format!(
-                                    "CAST(\"{}\" AS TEXT) as \"{}\"",
-                                    col_name, col_name
-                                ))
-                            } else {
-                                Some(format!("\"{}\"", col_name))
-                            }
-                        })
-                        .collect();
-                    if casts.is_empty() {
-                        format!("SELECT * FROM {}", table_quoted)
-                    } else {
-                        format!("SELECT {} FROM {}", casts.join(", "), table_quoted)
-                    }
-                }
-                Err(_) => format!("SELECT * FROM {}", table_quoted),
-            }
+
+        let sequences = schema.reset_sequences(db_type);
+        if !sequences.is_empty() {
+            out.push('\n');
+            out.push_str(&sequences.concat());
+        }
+
+        let indexes = schema.create_indexes(db_type);
+        if !indexes.is_empty() {
+            out.push('\n');
+            out.push_str(&indexes.concat());
+        }
+    }
+
+    let foreign_keys: Vec<String> = schemas
+        .iter()
+        .flat_map(|schema| schema.add_foreign_keys(db_type))
+        .collect();
+    if !foreign_keys.is_empty() {
+        out.push_str("\n-- Foreign keys\n\n");
+        out.push_str(&foreign_keys.concat());
+    }
+    send(&tx, out).await
+}
+
+/// Writes a table as CSV, with a header row taken from the first row's columns
+async fn write_csv(session: Session, table: TableRef, tx: ChunkSender) -> Result<(), String> {
+    let sql = format!("SELECT * FROM {}", table.quoted(&session.db_type));
+    let sql = decodable_sql(&session.native_pool, &sql)
+        .await
+        .map_err(|e| format!("Failed to fetch data: {}", e))?;
+    let mut rows = fetch_rows_stream(&session.native_pool, &sql);
+
+    let mut csv = String::new();
+    let mut wrote_header = false;
+    while let Some(row) = rows.next().await {
+        let row = row.map_err(|e| format!("Failed to fetch data: {}", e))?;
+        let Value::Object(fields) = row else {
+            continue;
+        };
+
+        if !wrote_header {
+            let header: Vec<String> = fields.keys().map(|name| csv_escape(name)).collect();
+            csv.push_str(&header.join(","));
+            csv.push('\n');
+            wrote_header = true;
+        }
+
+        let values: Vec<String> = fields.values().map(csv_value).collect();
+        csv.push_str(&values.join(","));
+        csv.push('\n');
+
+        if csv.len() >= CSV_CHUNK_BYTES {
+            send(&tx, std::mem::take(&mut csv)).await?;
         }
+    }
+
+    if !wrote_header {
+        csv.push_str("No data found");
+    }
+    send(&tx, csv).await
+}
+
+/// The base tables to include in a database dump: those of every user schema on Postgres,
+/// and of the connected database elsewhere
+async fn list_base_tables(session: &Session) -> Result<Vec<TableRef>, String> {
+    let (pool, db_type) = (&session.pool, &session.db_type);
+    let schemas = match db_type {
+        DbType::Postgres => fetch_schemas(pool, db_type, &session.schema).await?,
+        DbType::Mysql | DbType::Sqlite => vec![session.schema.clone()],
     };
 
-    let rows = match sqlx::query(&sql).fetch_all(&pool).await {
-        Ok(rows) => rows,
+    let mut tables = Vec::new();
+    for schema in &schemas {
+        for info in fetch_tables(pool, db_type, schema).await? {
+            if info.table_type == "TABLE" {
+                tables.push(TableRef::new(&info.schema, &info.name, db_type));
+            }
+        }
+    }
+    Ok(tables)
+}
+
+/// GET /api/export?format=sql - Export every table of the database as a SQL dump
+/// On Postgres this covers every user schema. Views are not included.
+async fn export_database(
+    AuthSession(session): AuthSession,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    let format = params.format.unwrap_or_else(|| "sql".to_string());
+    if format != "sql" {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Unsupported format: '{}'. Supported formats: sql", format),
+        );
+    }
+
+    let tables = match list_base_tables(&session).await {
+        Ok(tables) => tables,
         Err(e) => {
             return error_response(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                &format!("Failed to fetch data: {}", e),
+                &format!("Failed to list tables: {}", e),
             );
         }
     };
 
-    let mut csv = String::new();
+    // SQLite databases are file paths, so only the file name is used
+    let stem = std::path::Path::new(&session.database)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("database")
+        .replace('"', "");
+    stream_attachment(
+        "application/sql; charset=utf-8",
+        &format!("{}.sql", stem),
+        |tx| write_dump(session, tables, tx),
+    )
+    .await
+}
 
-    if rows.is_empty() {
-        csv.push_str("No data found");
-    } else {
-        let columns: Vec<String> = rows[0]
-            .columns()
-            .iter()
-            .map(|col| col.name().to_string())
-            .collect();
-
-        csv.push_str(
-            &columns
-                .iter()
-                .map(|c| csv_escape(c))
-                .collect::<Vec<_>>()
-                .join(","),
-        );
-        csv.push('\n');
+/// GET /api/export/{name}?format=csv|sql - Export table data as a CSV file or a SQL dump
+async fn export_table(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+    };
 
-        // Data rows
-        for row in &rows {
-            let values: Vec<String> = (0..columns.len())
-                .map(|i| {
-                    // Try extracting as different types (same approach as data.rs)
-                    if let Ok(s) = row.try_get::<String, _>(i) {
-                        csv_escape(&s)
-                    } else if let Ok(n) = row.try_get::<i64, _>(i) {
-                        n.to_string()
-                    } else if let Ok(f) = row.try_get::<f64, _>(i) {
-                        f.to_string()
-                    } else if let Ok(b) = row.try_get::<bool, _>(i) {
-                        b.to_string()
-                    } else if let Ok(bytes) = row.try_get::<Vec<u8>, _>(i) {
-                        csv_escape(&String::from_utf8_lossy(&bytes))
-                    } else {
-                        String::new()
-                    }
-                })
-                .collect();
-
-            csv.push_str(&values.join(","));
-            csv.push('\n');
+    let format = params.format.unwrap_or_else(|| "csv".to_string());
+    match format.as_str() {
+        "csv" => {
+            stream_attachment("text/csv; charset=utf-8", &format!("{}.csv", name), |tx| {
+                write_csv(session, table.clone(), tx)
+            })
+            .await
+        }
+        "sql" => {
+            stream_attachment(
+                "application/sql; charset=utf-8",
+                &format!("{}.sql", name),
+                |tx| write_dump(session, vec![table.clone()], tx),
+            )
+            .await
         }
+        _ => error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Unsupported format: '{}'. Supported formats: csv, sql",
+                format
+            ),
+        ),
     }
-
-    // Return the CSV as a file download
-    Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, "text/csv; charset=utf-8")
-        .header(
-            header::CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{}.csv\"", name),
-        )
-        .body(Body::from(csv))
-        .unwrap()
 }
@@ -0,0 +1,339 @@
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Path, Query},
+    http::{HeaderMap, header},
+    routing::post,
+};
+use futures_util::StreamExt;
+use serde_json::{Map, Value, json};
+use sqlx::Connection;
+
+use crate::{
+    auth::AuthSession,
+    import::{CsvMapping, CsvReader, LineReader, json_line_values},
+    models::{ApiResponse, ImportFormat, ImportParams, Session},
+    state::SessionStore,
+    statement::{TableWriter, load_writer},
+};
+
+/// Rows inserted per transaction unless the request asks otherwise
+const DEFAULT_BATCH_SIZE: usize = 500;
+
+const MAX_BATCH_SIZE: usize = 5000;
+
+/// Rejections listed in the response; any beyond this are only counted
+const MAX_REPORTED_REJECTIONS: usize = 1000;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+pub fn routes(session_store: SessionStore) -> Router {
+    Router::new()
+        .route("/{name}", post(import_table))
+        .with_state(session_store)
+}
+
+/// Picks the upload format from the Content-Type header, defaulting to CSV
+fn format_from_headers(headers: &HeaderMap) -> ImportFormat {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    if content_type.contains("json") {
+        ImportFormat::Ndjson
+    } else {
+        ImportFormat::Csv
+    }
+}
+
+/// Inserts imported rows in batches, one transaction per batch. Each row runs in its own
+/// savepoint, so a row the database refuses is rejected without losing the rest of its batch.
+struct Importer {
+    writer: TableWriter,
+    batch_size: usize,
+    /// Row number and values of the rows waiting for the next batch
+    pending: Vec<(usize, Map<String, Value>)>,
+    inserted: u64,
+    rejected: usize,
+    rejections: Vec<Value>,
+}
+
+impl Importer {
+    fn new(writer: TableWriter, batch_size: usize) -> Self {
+        Self {
+            writer,
+            batch_size,
+            pending: Vec::new(),
+            inserted: 0,
+            rejected: 0,
+            rejections: Vec::new(),
+        }
+    }
+
+    fn reject(&mut self, row: usize, error: String) {
+        self.rejected += 1;
+        if self.rejections.len() < MAX_REPORTED_REJECTIONS {
+            self.rejections.push(json!({ "row": row, "error": error }));
+        }
+    }
+
+    async fn push(
+        &mut self,
+        session: &Session,
+        row: usize,
+        values: Map<String, Value>,
+    ) -> Result<(), String> {
+        self.pending.push((row, values));
+        if self.pending.len() >= self.batch_size {
+            self.flush(session).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self, session: &Session) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = session.pool.begin().await.map_err(|e| e.to_string())?;
+        let mut inserted = 0;
+        for (row, values) in std::mem::take(&mut self.pending) {
+            let statement = match self.writer.insert(&values) {
+                Ok(s) => s,
+                Err(e) => {
+                    self.reject(row, e);
+                    continue;
+                }
+            };
+            let mut savepoint = tx.begin().await.map_err(|e| e.to_string())?;
+            match statement.execute(&mut *savepoint).await {
+                Ok(n) => {
+                    savepoint.commit().await.map_err(|e| e.to_string())?;
+                    inserted += n;
+                }
+                Err(e) => {
+                    savepoint.rollback().await.map_err(|e| e.to_string())?;
+                    self.reject(row, e.to_string());
+                }
+            }
+        }
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        self.inserted += inserted;
+        Ok(())
+    }
+
+    fn summary(&self) -> Value {
+        json!({
+            "message": format!("{} rows imported", self.inserted),
+            "inserted": self.inserted,
+            "rejected": self.rejected,
+            "rejections": self.rejections,
+            "rejections_truncated": self.rejected > self.rejections.len()
+        })
+    }
+}
+
+/// POST /api/import/{name}?format=csv|ndjson&batch_size= - Import rows from a streamed upload
+/// CSV needs a header row naming the columns; NDJSON has one object per line.
+/// Rows that fail conversion or insertion are skipped and reported by row number.
+async fn import_table(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Query(params): Query<ImportParams>,
+    headers: HeaderMap,
+    body: Body,
+) -> Json<ApiResponse<Value>> {
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    let format = params
+        .format
+        .unwrap_or_else(|| format_from_headers(&headers));
+    let batch_size = params
+        .batch_size
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+        .clamp(1, MAX_BATCH_SIZE);
+
+    let writer = match load_writer(&session, &table).await {
+        Ok(w) => w,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let mut importer = Importer::new(writer, batch_size);
+
+    let result = match format {
+        ImportFormat::Csv => import_csv(&session, &mut importer, body).await,
+        ImportFormat::Ndjson => import_ndjson(&session, &mut importer, body).await,
+    };
+
+    match result {
+        Ok(()) => Json(ApiResponse::success(importer.summary())),
+        // earlier batches are already committed, so say how far the import got
+        Err(e) if importer.inserted > 0 => Json(ApiResponse::error(format!(
+            "{}; {} rows were imported before the failure",
+            e, importer.inserted
+        ))),
+        Err(e) => Json(ApiResponse::error(e)),
+    }
+}
+
+async fn import_csv(session: &Session, importer: &mut Importer, body: Body) -> Result<(), String> {
+    let mut reader = CsvReader::new();
+    let mut mapping: Option<CsvMapping> = None;
+    let mut row = 0;
+
+    let mut stream = body.into_data_stream();
+    let mut first = true;
+    let mut records = Vec::new();
+    let mut done = false;
+    while !done {
+        match stream.next().await {
+            Some(chunk) => {
+                let chunk = chunk.map_err(|e| format!("Failed to read upload: {}", e))?;
+                let chunk = if first {
+                    chunk.strip_prefix(UTF8_BOM).unwrap_or(&chunk)
+                } else {
+                    &chunk
+                };
+                first = false;
+                reader.feed(chunk, &mut records)?;
+            }
+            None => {
+                reader.finish(&mut records)?;
+                done = true;
+            }
+        }
+
+        for record in records.drain(..) {
+            let Some(mapping) = &mapping else {
+                let header = record.map_err(|e| format!("Invalid header: {}", e))?;
+                mapping = Some(CsvMapping::new(&header, importer.writer.columns())?);
+                continue;
+            };
+            row += 1;
+            match record.and_then(|fields| mapping.values(fields)) {
+                Ok(values) => importer.push(session, row, values).await?,
+                Err(e) => importer.reject(row, e),
+            }
+        }
+    }
+
+    if mapping.is_none() {
+        return Err("Upload is empty; CSV imports need a header row".to_string());
+    }
+    importer.flush(session).await
+}
+
+async fn import_ndjson(
+    session: &Session,
+    importer: &mut Importer,
+    body: Body,
+) -> Result<(), String> {
+    let mut reader = LineReader::new();
+    let mut row = 0;
+
+    let mut stream = body.into_data_stream();
+    let mut first = true;
+    let mut lines = Vec::new();
+    let mut done = false;
+    while !done {
+        match stream.next().await {
+            Some(chunk) => {
+                let chunk = chunk.map_err(|e| format!("Failed to read upload: {}", e))?;
+                let chunk = if first {
+                    chunk.strip_prefix(UTF8_BOM).unwrap_or(&chunk)
+                } else {
+                    &chunk
+                };
+                first = false;
+                reader.feed(chunk, &mut lines)?;
+            }
+            None => {
+                reader.finish(&mut lines);
+                done = true;
+            }
+        }
+
+        for line in lines.drain(..) {
+            row += 1;
+            match json_line_values(&line, importer.writer.columns()) {
+                Ok(values) => importer.push(session, row, values).await?,
+                Err(e) => importer.reject(row, e),
+            }
+        }
+    }
+
+    importer.flush(session).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use sqlx::{any::AnyPoolOptions, sqlite::SqlitePoolOptions};
+
+    use super::*;
+    use crate::models::{ConnectRequest, DbType, NativePool};
+
+    #[tokio::test]
+    async fn test_import_csv_sqlite() {
+        sqlx::any::install_default_drivers();
+        // a single connection, since every in-memory connection is its own database
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT NOT NULL, score REAL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let session = Session {
+            token: String::new(),
+            pool: pool.clone(),
+            native_pool: NativePool::Sqlite(
+                SqlitePoolOptions::new()
+                    .connect_lazy("sqlite::memory:")
+                    .unwrap(),
+            ),
+            database: "main".to_string(),
+            db_type: DbType::Sqlite,
+            schema: "main".to_string(),
+            created_at: Instant::now(),
+            connection: ConnectRequest {
+                host: String::new(),
+                port: 0,
+                database: ":memory:".to_string(),
+                username: String::new(),
+                password: String::new(),
+                db_type: DbType::Sqlite,
+                search_path: Vec::new(),
+                settings: Default::default(),
+            },
+        };
+
+        let table = session.table("t").unwrap();
+        let writer = load_writer(&session, &table).await.unwrap();
+        let mut importer = Importer::new(writer, 2);
+        let body =
+            Body::from("\u{feff}ID,name,score\n1,a,1.5\n2,,\n1,dup,\n3,b,x\n4,c\n5,\"e, f\",2");
+        import_csv(&session, &mut importer, body).await.unwrap();
+
+        assert_eq!(importer.inserted, 2);
+        let rejected_rows: Vec<i64> = importer
+            .rejections
+            .iter()
+            .map(|r| r["row"].as_i64().unwrap())
+            .collect();
+        assert_eq!(rejected_rows, vec![2, 3, 4, 5]);
+
+        let names: Vec<String> = sqlx::query_scalar("SELECT name FROM t ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(names, vec!["a", "e, f"]);
+    }
+}
@@ -2,8 +2,11 @@ pub mod connection;
 pub mod data;
 pub mod database;
 pub mod export;
+pub mod import;
 pub mod query;
+pub mod roles;
 pub mod schema;
+pub mod views;
 
 use axum::Router;
 
@@ -14,7 +17,10 @@ pub fn api_routes(session_store: SessionStore) -> Router {
         .merge(connection::routes(session_store.clone()))
         .nest("/database", database::routes(session_store.clone()))
         .nest("/schema", schema::routes(session_store.clone()))
+        .nest("/schema/views", views::routes(session_store.clone()))
         .nest("/table", data::routes(session_store.clone()))
         .nest("/query", query::routes(session_store.clone()))
         .nest("/export", export::routes(session_store.clone()))
+        .nest("/import", import::routes(session_store.clone()))
+        .nest("/roles", roles::routes(session_store.clone()))
 }
@@ -0,0 +1,614 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query},
+    routing::{get, put},
+};
+use serde_json::{Value, json};
+use sqlx::{Row, any::AnyRow};
+
+use crate::{
+    auth::AuthSession,
+    models::{
+        ApiResponse, ChangePasswordRequest, CreateRoleRequest, DbType, GrantInfo, GrantObject,
+        PrivilegeRequest, RoleInfo, RoleParams, Session,
+    },
+    sql_utils::{escape_string_literal, is_valid_identifier, quote_identifier},
+    state::SessionStore,
+};
+
+pub fn routes(session_store: SessionStore) -> Router {
+    Router::new()
+        .route("/", get(list_roles).post(create_role))
+        .route(
+            "/{name}/grants",
+            get(list_grants)
+                .post(grant_privileges)
+                .delete(revoke_privileges),
+        )
+        .route("/{name}/password", put(change_password))
+        .with_state(session_store)
+}
+
+/// SQLite has no users; access is whatever the file permissions allow
+fn check_roles(db_type: &DbType) -> Result<(), String> {
+    match db_type {
+        DbType::Postgres | DbType::Mysql => Ok(()),
+        DbType::Sqlite => Err("Roles are only supported on PostgreSQL and MySQL".to_string()),
+    }
+}
+
+/// How SQL names a role: a quoted identifier on PostgreSQL, a 'user'@'host' account on MySQL.
+/// Names are quoted, so they may hold any character, e.g. `app.user` or `svc@corp`, within the
+/// server's length limit.
+fn account(name: &str, host: Option<&str>, db_type: &DbType) -> Result<String, String> {
+    // PostgreSQL truncates names to 63 bytes, MySQL refuses user names over 32 characters
+    let too_long = match db_type {
+        DbType::Mysql => name.chars().count() > 32,
+        _ => name.len() > 63,
+    };
+    if name.is_empty() || too_long || name.contains('\0') {
+        return Err("Invalid role name".to_string());
+    }
+    if host.is_some_and(|h| h.is_empty() || h.len() > 255 || h.contains('\0')) {
+        return Err("Invalid account host".to_string());
+    }
+    match db_type {
+        DbType::Mysql => Ok(format!(
+            "{}@{}",
+            escape_string_literal(name, db_type),
+            escape_string_literal(host.unwrap_or("%"), db_type)
+        )),
+        _ if host.is_some() => Err("Account hosts are only supported on MySQL".to_string()),
+        _ => Ok(quote_identifier(name, db_type)),
+    }
+}
+
+/// Validates privilege names against what can be granted on the object, as a SQL list
+fn privilege_list(
+    privileges: &[String],
+    object_type: GrantObject,
+    db_type: &DbType,
+) -> Result<String, String> {
+    let allowed: &[&str] = match (db_type, object_type) {
+        (_, GrantObject::Global) => {
+            return Err("Privileges can only be granted on tables and databases".to_string());
+        }
+        (DbType::Postgres, GrantObject::Table) => &[
+            "SELECT",
+            "INSERT",
+            "UPDATE",
+            "DELETE",
+            "TRUNCATE",
+            "REFERENCES",
+            "TRIGGER",
+        ],
+        (DbType::Postgres, GrantObject::Database) => &["CONNECT", "CREATE", "TEMPORARY", "TEMP"],
+        (_, GrantObject::Table) => &[
+            "SELECT",
+            "INSERT",
+            "UPDATE",
+            "DELETE",
+            "CREATE",
+            "DROP",
+            "ALTER",
+            "INDEX",
+            "REFERENCES",
+            "TRIGGER",
+            "CREATE VIEW",
+            "SHOW VIEW",
+        ],
+        (_, GrantObject::Database) => &[
+            "SELECT",
+            "INSERT",
+            "UPDATE",
+            "DELETE",
+            "CREATE",
+            "DROP",
+            "ALTER",
+            "INDEX",
+            "REFERENCES",
+            "TRIGGER",
+            "CREATE VIEW",
+            "SHOW VIEW",
+            "CREATE TEMPORARY TABLES",
+            "LOCK TABLES",
+            "EXECUTE",
+            "CREATE ROUTINE",
+            "ALTER ROUTINE",
+            "EVENT",
+        ],
+    };
+    if privileges.is_empty() {
+        return Err("No privileges given".to_string());
+    }
+
+    let mut list = Vec::with_capacity(privileges.len());
+    for privilege in privileges {
+        let privilege = privilege.split_whitespace().collect::<Vec<_>>().join(" ");
+        let privilege = privilege.to_uppercase();
+        if privilege == "ALL" || privilege == "ALL PRIVILEGES" {
+            list.push("ALL PRIVILEGES".to_string());
+        } else if allowed.contains(&privilege.as_str()) {
+            list.push(privilege);
+        } else {
+            return Err(format!(
+                "Unsupported privilege: '{}'. Supported privileges: {}, ALL",
+                privilege,
+                allowed.join(", ")
+            ));
+        }
+    }
+    Ok(list.join(", "))
+}
+
+/// The ON clause target of a GRANT or REVOKE
+fn grant_target(
+    session: &Session,
+    object_type: GrantObject,
+    object: &str,
+) -> Result<String, String> {
+    let db_type = &session.db_type;
+    match object_type {
+        GrantObject::Table => {
+            let table = session.table(object)?;
+            Ok(match db_type {
+                DbType::Mysql => format!(
+                    "{}.{}",
+                    quote_identifier(&table.schema, db_type),
+                    quote_identifier(&table.name, db_type)
+                ),
+                _ => format!("TABLE {}", table.quoted(db_type)),
+            })
+        }
+        GrantObject::Database => {
+            if !is_valid_identifier(object) {
+                return Err("Invalid database name".to_string());
+            }
+            Ok(match db_type {
+                DbType::Mysql => format!("{}.*", quote_identifier(object, db_type)),
+                _ => format!("DATABASE {}", quote_identifier(object, db_type)),
+            })
+        }
+        GrantObject::Global => {
+            Err("Privileges can only be granted on tables and databases".to_string())
+        }
+    }
+}
+
+/// Reads a flag that PostgreSQL returns as a boolean and MySQL as an integer
+fn flag(row: &AnyRow, column: &str) -> bool {
+    row.try_get(column)
+        .unwrap_or_else(|_| row.try_get::<i64, _>(column).unwrap_or(0) != 0)
+}
+
+/// Splits a comma separated list of names, empty when there are none
+fn name_list(row: &AnyRow, column: &str) -> Vec<String> {
+    let names: String = row.try_get(column).unwrap_or_default();
+    names
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// GET /api/roles - List the roles (PostgreSQL) or user accounts (MySQL) of the server
+async fn list_roles(AuthSession(session): AuthSession) -> Json<ApiResponse<Value>> {
+    if let Err(e) = check_roles(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+    // PostgreSQL's predefined pg_* roles are left out
+    let sql = match session.db_type {
+        DbType::Postgres => {
+            "
+                SELECT
+                    r.rolname::text AS name,
+                    NULL::text AS host,
+                    r.rolsuper AS superuser,
+                    r.rolcanlogin AS can_login,
+                    r.rolcreatedb AS create_db,
+                    r.rolcreaterole AS create_role,
+                    COALESCE((
+                        SELECT string_agg(g.rolname::text, ',' ORDER BY g.rolname)
+                        FROM pg_auth_members m
+                        JOIN pg_roles g ON g.oid = m.roleid
+                        WHERE m.member = r.oid
+                    ), '') AS member_of
+                FROM pg_roles r
+                WHERE r.rolname !~ '^pg_'
+                ORDER BY r.rolname
+            "
+        }
+        _ => {
+            "
+                SELECT
+                    CAST(u.User AS CHAR) AS name,
+                    CAST(u.Host AS CHAR) AS host,
+                    u.Super_priv = 'Y' AS superuser,
+                    u.account_locked = 'N' AS can_login,
+                    u.Create_priv = 'Y' AS create_db,
+                    u.Create_user_priv = 'Y' AS create_role,
+                    COALESCE((
+                        SELECT GROUP_CONCAT(CAST(e.FROM_USER AS CHAR) ORDER BY e.FROM_USER)
+                        FROM mysql.role_edges e
+                        WHERE e.TO_USER = u.User AND e.TO_HOST = u.Host
+                    ), '') AS member_of
+                FROM mysql.user u
+                ORDER BY u.User, u.Host
+            "
+        }
+    };
+
+    match sqlx::query(sql).fetch_all(&session.pool).await {
+        Ok(rows) => {
+            let roles: Vec<RoleInfo> = rows
+                .iter()
+                .map(|row| RoleInfo {
+                    name: row.try_get("name").unwrap_or_default(),
+                    host: row.try_get("host").unwrap_or(None),
+                    superuser: flag(row, "superuser"),
+                    can_login: flag(row, "can_login"),
+                    create_db: flag(row, "create_db"),
+                    create_role: flag(row, "create_role"),
+                    member_of: name_list(row, "member_of"),
+                })
+                .collect();
+            Json(ApiResponse::success(json!({ "roles": roles })))
+        }
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// POST /api/roles - Create a role or user account
+async fn create_role(
+    AuthSession(session): AuthSession,
+    Json(payload): Json<CreateRoleRequest>,
+) -> Json<ApiResponse<Value>> {
+    if let Err(e) = check_roles(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+    let db_type = &session.db_type;
+    let account = match account(&payload.name, payload.host.as_deref(), db_type) {
+        Ok(a) => a,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let password = payload
+        .password
+        .as_deref()
+        .map(|p| escape_string_literal(p, db_type));
+
+    let sql = match db_type {
+        DbType::Postgres => format!(
+            "CREATE ROLE {} WITH {}{}",
+            account,
+            if payload.can_login {
+                "LOGIN"
+            } else {
+                "NOLOGIN"
+            },
+            password
+                .map(|p| format!(" PASSWORD {}", p))
+                .unwrap_or_default()
+        ),
+        _ => format!(
+            "CREATE USER {}{}{}",
+            account,
+            password
+                .map(|p| format!(" IDENTIFIED BY {}", p))
+                .unwrap_or_default(),
+            if payload.can_login {
+                ""
+            } else {
+                " ACCOUNT LOCK"
+            }
+        ),
+    };
+    match sqlx::query(&sql).execute(&session.pool).await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "Role created successfully",
+            "role": payload.name
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// PUT /api/roles/{name}/password - Change the password of a role or user account
+async fn change_password(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Json<ApiResponse<Value>> {
+    if let Err(e) = check_roles(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+    let db_type = &session.db_type;
+    let account = match account(&name, payload.host.as_deref(), db_type) {
+        Ok(a) => a,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    let password = escape_string_literal(&payload.password, db_type);
+    let sql = match db_type {
+        DbType::Postgres => format!("ALTER ROLE {} WITH PASSWORD {}", account, password),
+        _ => format!("ALTER USER {} IDENTIFIED BY {}", account, password),
+    };
+    match sqlx::query(&sql).execute(&session.pool).await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "Password changed successfully",
+            "role": name
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// GET /api/roles/{name}/grants?host= - List the privileges a role holds explicitly, plus the
+/// SHOW GRANTS statements of MySQL accounts
+async fn list_grants(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Query(params): Query<RoleParams>,
+) -> Json<ApiResponse<Value>> {
+    if let Err(e) = check_roles(&session.db_type) {
+        return Json(ApiResponse::error(e));
+    }
+    let db_type = &session.db_type;
+    let account = match account(&name, params.host.as_deref(), db_type) {
+        Ok(a) => a,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    let rows = match db_type {
+        DbType::Postgres => {
+            let sql = "
+                SELECT
+                    'table' AS object_type,
+                    table_schema::text AS schema,
+                    table_name::text AS object,
+                    privilege_type::text AS privilege,
+                    is_grantable = 'YES' AS grantable
+                FROM information_schema.table_privileges
+                WHERE grantee = $1
+                  AND table_schema NOT IN ('pg_catalog', 'information_schema')
+                UNION ALL
+                SELECT
+                    'database',
+                    NULL::text,
+                    d.datname::text,
+                    a.privilege_type,
+                    a.is_grantable
+                FROM pg_database d
+                CROSS JOIN LATERAL aclexplode(d.datacl) a
+                JOIN pg_roles r ON r.oid = a.grantee
+                WHERE r.rolname = $1
+                ORDER BY 1, 2, 3, 4
+            ";
+            sqlx::query(sql).bind(&name).fetch_all(&session.pool).await
+        }
+        _ => {
+            // information_schema names grantees 'user'@'host', quoted without escaping
+            let grantee = format!("'{}'@'{}'", name, params.host.as_deref().unwrap_or("%"))
+                .replace('\\', "\\\\")
+                .replace('\'', "''");
+            let sql = format!(
+                "
+                    SELECT 'global' AS object_type, NULL AS `schema`, NULL AS object,
+                        CAST(PRIVILEGE_TYPE AS CHAR) AS privilege, IS_GRANTABLE = 'YES' AS grantable
+                    FROM information_schema.USER_PRIVILEGES
+                    WHERE GRANTEE = '{0}' AND PRIVILEGE_TYPE <> 'USAGE'
+                    UNION ALL
+                    SELECT 'database', NULL, CAST(TABLE_SCHEMA AS CHAR),
+                        CAST(PRIVILEGE_TYPE AS CHAR), IS_GRANTABLE = 'YES'
+                    FROM information_schema.SCHEMA_PRIVILEGES
+                    WHERE GRANTEE = '{0}'
+                    UNION ALL
+                    SELECT 'table', CAST(TABLE_SCHEMA AS CHAR), CAST(TABLE_NAME AS CHAR),
+                        CAST(PRIVILEGE_TYPE AS CHAR), IS_GRANTABLE = 'YES'
+                    FROM information_schema.TABLE_PRIVILEGES
+                    WHERE GRANTEE = '{0}'
+                    ORDER BY 1, 2, 3, 4
+                ",
+                grantee
+            );
+            sqlx::query(&sql).fetch_all(&session.pool).await
+        }
+    };
+    let rows = match rows {
+        Ok(r) => r,
+        Err(e) => return Json(ApiResponse::error(e.to_string())),
+    };
+    let grants: Vec<GrantInfo> = rows
+        .iter()
+        .map(|row| {
+            let object_type: String = row.try_get("object_type").unwrap_or_default();
+            GrantInfo {
+                object_type: match object_type.as_str() {
+                    "global" => GrantObject::Global,
+                    "database" => GrantObject::Database,
+                    _ => GrantObject::Table,
+                },
+                schema: row.try_get("schema").unwrap_or(None),
+                object: row.try_get("object").unwrap_or(None),
+                privilege: row.try_get("privilege").unwrap_or_default(),
+                grantable: flag(row, "grantable"),
+            }
+        })
+        .collect();
+
+    // Role memberships and column privileges only show up in MySQL's own summary
+    let statements: Vec<String> = match db_type {
+        DbType::Mysql => {
+            match sqlx::query(&format!("SHOW GRANTS FOR {}", account))
+                .fetch_all(&session.pool)
+                .await
+            {
+                Ok(rows) => rows
+                    .iter()
+                    .map(|row| row.try_get(0).unwrap_or_default())
+                    .collect(),
+                Err(e) => return Json(ApiResponse::error(e.to_string())),
+            }
+        }
+        _ => Vec::new(),
+    };
+
+    Json(ApiResponse::success(json!({
+        "role": name,
+        "host": params.host,
+        "grants": grants,
+        "statements": statements
+    })))
+}
+
+/// POST /api/roles/{name}/grants - Grant privileges on a table or database
+async fn grant_privileges(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Json(payload): Json<PrivilegeRequest>,
+) -> Json<ApiResponse<Value>> {
+    let sql = match privilege_statement(&session, &name, &payload, true) {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    match sqlx::query(&sql).execute(&session.pool).await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "Privileges granted successfully",
+            "sql": sql
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// DELETE /api/roles/{name}/grants - Revoke privileges on a table or database
+async fn revoke_privileges(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Json(payload): Json<PrivilegeRequest>,
+) -> Json<ApiResponse<Value>> {
+    let sql = match privilege_statement(&session, &name, &payload, false) {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    match sqlx::query(&sql).execute(&session.pool).await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "Privileges revoked successfully",
+            "sql": sql
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// The GRANT or REVOKE statement for a privilege request
+fn privilege_statement(
+    session: &Session,
+    name: &str,
+    payload: &PrivilegeRequest,
+    grant: bool,
+) -> Result<String, String> {
+    let db_type = &session.db_type;
+    check_roles(db_type)?;
+    let account = account(name, payload.host.as_deref(), db_type)?;
+    let privileges = privilege_list(&payload.privileges, payload.object_type, db_type)?;
+    let target = grant_target(session, payload.object_type, &payload.object)?;
+
+    Ok(if grant {
+        format!(
+            "GRANT {} ON {} TO {}{}",
+            privileges,
+            target,
+            account,
+            if payload.with_grant_option {
+                " WITH GRANT OPTION"
+            } else {
+                ""
+            }
+        )
+    } else {
+        format!("REVOKE {} ON {} FROM {}", privileges, target, account)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn privileges(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_account() {
+        assert_eq!(account("app", None, &DbType::Mysql).unwrap(), "'app'@'%'");
+        assert_eq!(
+            account("app", Some("10.0.%' OR '1"), &DbType::Mysql).unwrap(),
+            "'app'@'10.0.%'' OR ''1'"
+        );
+        assert_eq!(account("App", None, &DbType::Postgres).unwrap(), "\"App\"");
+        assert!(account("app", Some("localhost"), &DbType::Postgres).is_err());
+        assert_eq!(
+            account("app'@'%", None, &DbType::Mysql).unwrap(),
+            "'app''@''%'@'%'"
+        );
+        assert_eq!(
+            account("svc@corp", Some("localhost"), &DbType::Mysql).unwrap(),
+            "'svc@corp'@'localhost'"
+        );
+        assert_eq!(
+            account("app.user\"; --", None, &DbType::Postgres).unwrap(),
+            "\"app.user\"\"; --\""
+        );
+        assert!(account("", None, &DbType::Postgres).is_err());
+        assert!(account(&"a".repeat(33), None, &DbType::Mysql).is_err());
+    }
+
+    #[test]
+    fn test_privilege_list() {
+        assert_eq!(
+            privilege_list(
+                &privileges(&["select", " insert ", "create  view"]),
+                GrantObject::Table,
+                &DbType::Mysql
+            )
+            .unwrap(),
+            "SELECT, INSERT, CREATE VIEW"
+        );
+        assert_eq!(
+            privilege_list(&privileges(&["all"]), GrantObject::Table, &DbType::Postgres).unwrap(),
+            "ALL PRIVILEGES"
+        );
+        assert_eq!(
+            privilege_list(
+                &privileges(&["All Privileges"]),
+                GrantObject::Database,
+                &DbType::Mysql
+            )
+            .unwrap(),
+            "ALL PRIVILEGES"
+        );
+
+        // names outside the object's list, including injected SQL, are refused
+        let rejected = [
+            (&["CONNECT"][..], GrantObject::Table, DbType::Postgres),
+            (&["TRUNCATE"][..], GrantObject::Database, DbType::Postgres),
+            (&["SUPER"][..], GrantObject::Database, DbType::Mysql),
+            (
+                &["SELECT ON *.* TO x; --"][..],
+                GrantObject::Table,
+                DbType::Mysql,
+            ),
+            (&["SELECT"][..], GrantObject::Global, DbType::Mysql),
+            (&[][..], GrantObject::Table, DbType::Postgres),
+        ];
+        for (names, object_type, db_type) in rejected {
+            assert!(privilege_list(&privileges(names), object_type, &db_type).is_err());
+        }
+        let error = privilege_list(
+            &privileges(&["usage"]),
+            GrantObject::Database,
+            &DbType::Postgres,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "Unsupported privilege: 'USAGE'. Supported privileges: CONNECT, CREATE, TEMPORARY, TEMP, ALL"
+        );
+    }
+}
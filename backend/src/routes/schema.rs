@@ -1,50 +1,147 @@
+use std::collections::HashMap;
+
 use axum::{
     Json, Router,
-    extract::Path,
+    extract::{Path, Query, State},
     routing::{delete, get, post, put},
 };
 use serde_json::{Value, json};
-use sqlx::Row;
+use sqlx::{AnyPool, Column, Row};
 
 use crate::{
     auth::AuthSession,
+    dependents::{count_dropped_rows, fetch_dependents},
+    diff::{SchemaModel, diff_schemas, migration},
+    dump::TableSchema,
+    lexer::{is_single_expression, tokenize},
     models::{
-        AlterTableRequest, AlterType, ApiResponse, ColumnInfo, CreateTableRequest, DbType,
-        ForeignKeyInfo, IndexInfo, TableInfo,
+        AlterTableRequest, AlterType, ApiResponse, ColumnInfo, CreateIndexRequest,
+        CreateTableRequest, DbType, DependentObject, ForeignKeyInfo, IndexInfo, SchemaChangeParams,
+        SchemaDiffRequest, Session, SnapshotParams, TableInfo, TablesParams,
+    },
+    rebuild::{
+        ColumnSpec, RebuildPlan, TableChange, foreign_key_name, generated_clause, plan_rebuild,
+        run_rebuild, supports_drop_column,
     },
-    sql_utils::{is_valid_identifier, quote_identifier},
+    routes::views::fetch_view,
+    snapshot::{dot, graph, load_snapshot, mermaid},
+    sql_utils::{TableRef, escape_string_literal, is_valid_identifier, quote_identifier},
     state::SessionStore,
 };
 
 pub fn routes(session_store: SessionStore) -> Router {
     Router::new()
         // read operations
+        .route("/schemas", get(list_schemas))
         .route("/tables", get(list_tables))
+        .route("/snapshot", get(get_snapshot))
+        .route("/diff", post(diff_schema))
         .route("/table/{name}", get(get_table))
         .route("/table/{name}/indexes", get(get_indexes))
         .route("/table/{name}/foreign-keys", get(get_foreign_keys))
+        .route("/table/{name}/ddl", get(get_table_ddl))
         // write operations
         .route("/table", post(create_table))
         .route("/table/{name}", put(alter_table))
         .route("/table/{name}", delete(drop_table))
+        .route("/table/{name}/indexes", post(create_index))
+        .route("/table/{name}/indexes/{index}", delete(drop_index))
         .with_state(session_store)
 }
 
-/// GET /api/schema/tables - List all tables
-async fn list_tables(AuthSession(session): AuthSession) -> Json<ApiResponse<Value>> {
-    let pool = session.pool;
-    let db_type = session.db_type;
-    let db_name = session.database;
+/// GET /api/schema/schemas - List the schemas tables can be addressed in
+async fn list_schemas(AuthSession(session): AuthSession) -> Json<ApiResponse<Value>> {
+    match fetch_schemas(&session.pool, &session.db_type, &session.schema).await {
+        Ok(schemas) => Json(ApiResponse::success(json!({
+            "schemas": schemas,
+            "default_schema": session.schema
+        }))),
+        Err(e) => Json(ApiResponse::error(e)),
+    }
+}
 
+/// Lists the user schemas of the connected database, leaving out the system catalogs.
+/// MySQL only has the connected database, and SQLite its attached databases.
+pub async fn fetch_schemas(
+    pool: &AnyPool,
+    db_type: &DbType,
+    default_schema: &str,
+) -> Result<Vec<String>, String> {
     let query = match db_type {
-        DbType::Postgres => format!(
-            "SELECT table_name::text as name, table_type::text as table_type, NULL::bigint as row_count_estimate 
-             FROM information_schema.tables 
-             WHERE table_schema = 'public' 
-             ORDER BY table_name"
-        ),
+        DbType::Postgres => {
+            "SELECT nspname::text as name FROM pg_namespace
+             WHERE nspname NOT LIKE 'pg\\_%' AND nspname <> 'information_schema'
+             ORDER BY nspname"
+        }
+        DbType::Mysql => return Ok(vec![default_schema.to_string()]),
+        DbType::Sqlite => "SELECT name FROM pragma_database_list ORDER BY seq",
+    };
+
+    let rows = sqlx::query(query)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows
+        .into_iter()
+        .map(|row| row.try_get("name").unwrap_or_default())
+        .collect())
+}
+
+/// GET /api/schema/tables?schema= - List all tables of a schema, by default the session's
+async fn list_tables(
+    AuthSession(session): AuthSession,
+    Query(params): Query<TablesParams>,
+) -> Json<ApiResponse<Value>> {
+    let schema = match requested_schema(&session, params.schema) {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    match fetch_tables(&session.pool, &session.db_type, &schema).await {
+        Ok(tables) => Json(ApiResponse::success(json!({ "tables": tables }))),
+        Err(e) => Json(ApiResponse::error(e)),
+    }
+}
+
+/// The schema a listing asks for, the session's by default. Only PostgreSQL can list another.
+pub fn requested_schema(session: &Session, schema: Option<String>) -> Result<String, String> {
+    match schema {
+        Some(schema) if !matches!(session.db_type, DbType::Postgres) => {
+            if schema != session.schema {
+                return Err("Listing other schemas is only supported on PostgreSQL".to_string());
+            }
+            Ok(schema)
+        }
+        Some(schema) if !is_valid_identifier(&schema) => Err("Invalid schema name".to_string()),
+        Some(schema) => Ok(schema),
+        None => Ok(session.schema.clone()),
+    }
+}
+
+/// Lists the tables and views of a schema
+pub async fn fetch_tables(
+    pool: &AnyPool,
+    db_type: &DbType,
+    schema: &str,
+) -> Result<Vec<TableInfo>, String> {
+    let query = match db_type {
+        // materialized views are missing from information_schema
+        DbType::Postgres => "SELECT t.table_name::text as name, t.table_type::text as table_type,
+                    CASE WHEN c.reltuples < 0 THEN NULL ELSE c.reltuples::bigint END as row_count_estimate
+             FROM information_schema.tables t
+             LEFT JOIN pg_namespace n ON n.nspname = t.table_schema
+             LEFT JOIN pg_class c ON c.relnamespace = n.oid AND c.relname = t.table_name
+             WHERE t.table_schema = $1
+             UNION ALL
+             SELECT c.relname::text, 'MATERIALIZED VIEW',
+                    CASE WHEN c.reltuples < 0 THEN NULL ELSE c.reltuples::bigint END
+             FROM pg_class c
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             WHERE n.nspname = $1 AND c.relkind = 'm'
+             ORDER BY name"
+            .to_string(),
         DbType::Mysql => {
-            let safe_db_name = db_name.replace("'", "''");
+            let safe_db_name = schema.replace("'", "''");
             format!(
                 "SELECT CAST(table_name AS CHAR) as name, CAST(table_type AS CHAR) as table_type, table_rows as row_count_estimate 
                  FROM information_schema.tables 
@@ -53,42 +150,123 @@ async fn list_tables(AuthSession(session): AuthSession) -> Json<ApiResponse<Valu
                 safe_db_name
             )
         }
-        DbType::Sqlite => format!(
-            "SELECT name, type as table_type, NULL as row_count_estimate 
+        DbType::Sqlite => "SELECT name, type as table_type, NULL as row_count_estimate 
              FROM sqlite_schema 
              WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' 
              ORDER BY name"
-        ),
+            .to_string(),
     };
     // execute query with or without parameter binding
+    let result = if matches!(db_type, DbType::Postgres) {
+        sqlx::query(&query).bind(schema).fetch_all(pool).await
+    } else {
+        sqlx::query(&query).fetch_all(pool).await
+    };
+    let rows = result.map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let raw_type: String = row.try_get("table_type").unwrap_or_default();
+            let table_type = match raw_type.to_lowercase().as_str() {
+                "base table" | "table" => "TABLE".to_string(),
+                "view" => "VIEW".to_string(),
+                _ => raw_type,
+            };
 
-    match sqlx::query(&query).fetch_all(&pool).await {
-        Ok(rows) => {
-            let tables: Vec<TableInfo> = rows
-                .into_iter()
-                .map(|row| {
-                    let raw_type: String = row.try_get("table_type").unwrap_or_default();
-                    let table_type = match raw_type.to_lowercase().as_str() {
-                        "base table" | "table" => "TABLE".to_string(),
-                        "view" => "VIEW".to_string(),
-                        _ => raw_type,
-                    };
+            let row_count_estimate: Option<i64> =
+                row.try_get("row_count_estimate").unwrap_or_default();
 
-                    let row_count_estimate: Option<i64> =
-                        row.try_get("row_count_estimate").unwrap_or_default();
+            TableInfo {
+                schema: schema.to_string(),
+                name: row.try_get("name").unwrap_or_default(),
+                table_type,
+                row_count_estimate,
+            }
+        })
+        .collect())
+}
 
-                    TableInfo {
-                        name: row.try_get("name").unwrap_or_default(),
-                        table_type,
-                        row_count_estimate,
-                    }
-                })
-                .collect();
+/// GET /api/schema/snapshot?schema=&format= - Every table and view of a schema with its columns,
+/// keys and indexes, as JSON or rendered as a Mermaid ER diagram, Graphviz DOT or a JSON graph
+async fn get_snapshot(
+    AuthSession(session): AuthSession,
+    Query(params): Query<SnapshotParams>,
+) -> Json<ApiResponse<Value>> {
+    let schema = match requested_schema(&session, params.schema) {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let snapshot = match load_snapshot(&session.pool, &session.db_type, &schema).await {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
 
-            Json(ApiResponse::success(json!({ "tables": tables })))
+    let format = params.format.unwrap_or_else(|| "json".to_string());
+    let content = match format.as_str() {
+        "json" => return Json(ApiResponse::success(json!({ "snapshot": snapshot }))),
+        "graph" => return Json(ApiResponse::success(json!({ "graph": graph(&snapshot) }))),
+        "mermaid" => mermaid(&snapshot),
+        "dot" => dot(&snapshot),
+        _ => {
+            return Json(ApiResponse::error(format!(
+                "Unsupported format: '{}'. Supported formats: json, mermaid, dot, graph",
+                format
+            )));
         }
-        Err(e) => Json(ApiResponse::error(e.to_string())),
+    };
+    Json(ApiResponse::success(json!({
+        "format": format,
+        "content": content
+    })))
+}
+
+/// POST /api/schema/diff - Compare the session's schema with another session's, returning how the
+/// target's tables differ and the SQL, in the target's dialect, that makes them match
+async fn diff_schema(
+    State(session_store): State<SessionStore>,
+    AuthSession(session): AuthSession,
+    Json(payload): Json<SchemaDiffRequest>,
+) -> Json<ApiResponse<Value>> {
+    let target = match session_store.read().await.get(&payload.target_token) {
+        Some(target) => target.clone(),
+        None => return Json(ApiResponse::error("Target session not found")),
+    };
+    // types, defaults and index definitions are compared and copied as the source spells them
+    if session.db_type != target.db_type {
+        return Json(ApiResponse::error(
+            "Schemas can only be compared between databases of the same type",
+        ));
     }
+    let schemas = (
+        requested_schema(&session, payload.schema),
+        requested_schema(&target, payload.target_schema),
+    );
+    let (source_schema, target_schema) = match schemas {
+        (Ok(source), Ok(target)) => (source, target),
+        (Err(e), _) | (_, Err(e)) => return Json(ApiResponse::error(e)),
+    };
+
+    let source = match SchemaModel::load(&session.pool, &session.db_type, &source_schema).await {
+        Ok(s) => s,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let target_pool = target.pool.clone();
+    let target = match SchemaModel::load(&target_pool, &target.db_type, &target_schema).await {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    let diff = diff_schemas(&source, &target);
+    let statements = match migration(&diff, &source, &target, &target_pool).await {
+        Ok(statements) => statements,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    Json(ApiResponse::success(json!({
+        "diff": diff,
+        "target_db_type": target.db_type,
+        "migration": statements.concat()
+    })))
 }
 
 /// GET /api/schema/table/{name} - Get table structure
@@ -96,147 +274,212 @@ async fn get_table(
     AuthSession(session): AuthSession,
     Path(name): Path<String>,
 ) -> Json<ApiResponse<Value>> {
-    let pool = session.pool;
-    let db_type = session.db_type;
-    let db_name = session.database;
-
-    // build query with proper escaping for each database type
-    let (query, use_bind) = match db_type {
-        DbType::Postgres => (
-            "SELECT c.column_name::text as column_name, c.data_type::text as data_type, 
-                c.is_nullable::text as is_nullable, c.column_default::text as column_default,
-                CASE WHEN pk.column_name IS NOT NULL THEN true ELSE false END as is_primary
-             FROM information_schema.columns c
-             LEFT JOIN (
-                 SELECT ku.column_name
-                 FROM information_schema.key_column_usage ku
-                 JOIN information_schema.table_constraints tc 
-                     ON ku.constraint_name = tc.constraint_name 
-                     AND ku.table_schema = tc.table_schema
-                 WHERE tc.constraint_type = 'PRIMARY KEY'
-                     AND tc.table_schema = 'public' 
-                     AND tc.table_name = $1
-             ) pk ON c.column_name = pk.column_name
-             WHERE c.table_schema = 'public' AND c.table_name = $1
-             ORDER BY c.ordinal_position"
-                .to_string(),
-            true,
-        ),
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    match fetch_columns(&session.pool, &session.db_type, &table).await {
+        Ok(columns) => Json(ApiResponse::success(json!({ "columns": columns }))),
+        Err(e) => Json(ApiResponse::error(e)),
+    }
+}
+
+/// Describes the columns of a table in declaration order
+pub async fn fetch_columns(
+    pool: &AnyPool,
+    db_type: &DbType,
+    table: &TableRef,
+) -> Result<Vec<ColumnInfo>, String> {
+    let columns = fetch_schema_columns(pool, db_type, &table.schema, Some(&table.name)).await?;
+    Ok(columns.into_iter().map(|(_, column)| column).collect())
+}
+
+/// Describes the columns of every table and view in a schema, or of a single one, paired with
+/// their table's name and ordered by table and declaration
+pub async fn fetch_schema_columns(
+    pool: &AnyPool,
+    db_type: &DbType,
+    schema: &str,
+    table: Option<&str>,
+) -> Result<Vec<(String, ColumnInfo)>, String> {
+    let result = match db_type {
+        DbType::Postgres => {
+            sqlx::query(
+                "SELECT c.table_name::text as table_name, c.column_name::text as column_name,
+                    c.data_type::text as data_type, c.is_nullable::text as is_nullable,
+                    c.column_default::text as column_default,
+                    CASE WHEN pk.column_name IS NOT NULL THEN true ELSE false END as is_primary,
+                    CASE WHEN c.column_default LIKE 'nextval(%' OR c.is_identity = 'YES' THEN true ELSE false END as is_auto_increment,
+                    CASE WHEN c.is_generated = 'ALWAYS'
+                        THEN 'GENERATED ALWAYS AS (' || c.generation_expression || ') STORED' END as generated,
+                    COALESCE(c.identity_generation = 'ALWAYS', false) as identity_always
+                 FROM information_schema.columns c
+                 LEFT JOIN (
+                     SELECT ku.table_name, ku.column_name
+                     FROM information_schema.key_column_usage ku
+                     JOIN information_schema.table_constraints tc 
+                         ON ku.constraint_name = tc.constraint_name 
+                         AND ku.table_schema = tc.table_schema
+                         AND ku.table_name = tc.table_name
+                     WHERE tc.constraint_type = 'PRIMARY KEY'
+                         AND tc.table_schema = $1
+                 ) pk ON c.table_name = pk.table_name AND c.column_name = pk.column_name
+                 WHERE c.table_schema = $1 AND ($2::text IS NULL OR c.table_name = $2)
+                 ORDER BY c.table_name, c.ordinal_position",
+            )
+            .bind(schema)
+            .bind(table)
+            .fetch_all(pool)
+            .await
+        }
         DbType::Mysql => {
-            // escape single quotes for MySQL
-            let safe_db = db_name.replace("'", "''");
-            let safe_name = name.replace("'", "''");
-            // note: MySQL information_schema returns some columns as BLOB, so we CAST them to VARCHAR
-            (
-                format!(
-                    "SELECT CAST(COLUMN_NAME AS CHAR) as `column_name`, 
+            let table_filter = table
+                .map(|t| format!("AND table_name = {}", escape_string_literal(t, db_type)))
+                .unwrap_or_default();
+            // note: MySQL information_schema returns some columns as BLOB, so we CAST them to VARCHAR.
+            // EXTRA also says DEFAULT_GENERATED for expression defaults, which have no
+            // generation expression.
+            let sql = format!(
+                "SELECT CAST(TABLE_NAME AS CHAR) as `table_name`,
+                    CAST(COLUMN_NAME AS CHAR) as `column_name`, 
                     CAST(DATA_TYPE AS CHAR) as `data_type`, 
                     CAST(IS_NULLABLE AS CHAR) as `is_nullable`, 
                     CAST(COLUMN_DEFAULT AS CHAR) as `column_default`,
-                    CASE WHEN COLUMN_KEY = 'PRI' THEN true ELSE false END as `is_primary`
+                    CASE WHEN COLUMN_KEY = 'PRI' THEN true ELSE false END as `is_primary`,
+                    CASE WHEN EXTRA LIKE '%auto_increment%' THEN true ELSE false END as `is_auto_increment`,
+                    CASE WHEN GENERATION_EXPRESSION <> '' THEN CAST(CONCAT(
+                        'GENERATED ALWAYS AS (', GENERATION_EXPRESSION, ') ',
+                        IF(EXTRA LIKE '%STORED GENERATED%', 'STORED', 'VIRTUAL')
+                    ) AS CHAR) END as `generated`
                  FROM information_schema.columns
-                 WHERE table_schema = '{}' AND table_name = '{}'
-                 ORDER BY ordinal_position",
-                    safe_db, safe_name
-                ),
-                false,
+                 WHERE table_schema = {} {}
+                 ORDER BY table_name, ordinal_position",
+                escape_string_literal(schema, db_type),
+                table_filter
+            );
+            sqlx::query(&sql).fetch_all(pool).await
+        }
+        DbType::Sqlite => {
+            sqlx::query(
+                "SELECT m.name as table_name, p.name as column_name, p.type as data_type, 
+                    CASE WHEN p.\"notnull\" = 0 THEN 'YES' ELSE 'NO' END as is_nullable, 
+                    p.dflt_value as column_default, 
+                    -- pk holds the column's position within the key
+                    CASE WHEN p.pk > 0 THEN 1 ELSE 0 END as is_primary,
+                    -- a lone INTEGER PRIMARY KEY is an alias for the rowid, which SQLite assigns itself
+                    CASE WHEN p.pk = 1 AND upper(p.type) = 'INTEGER'
+                        AND (SELECT COUNT(*) FROM pragma_table_info(m.name) WHERE pk > 0) = 1
+                        THEN 1 ELSE 0 END as is_auto_increment,
+                    -- hidden is 2 or 3 for virtual and stored generated columns, whose
+                    -- expression is only found in the table's SQL
+                    CASE WHEN p.hidden IN (2, 3) THEN m.sql END as table_sql
+                 FROM sqlite_schema m
+                 JOIN pragma_table_xinfo(m.name) p
+                 WHERE m.type IN ('table', 'view') AND m.name NOT LIKE 'sqlite_%' AND p.hidden <> 1
+                   AND (?1 IS NULL OR m.name = ?1 COLLATE NOCASE)
+                 ORDER BY m.name, p.cid",
             )
+            .bind(table)
+            .fetch_all(pool)
+            .await
         }
-        DbType::Sqlite => (
-            "SELECT name as column_name, type as data_type, 
-                CASE WHEN \"notnull\" = 0 THEN 'YES' ELSE 'NO' END as is_nullable, 
-                dflt_value as column_default, 
-                pk as is_primary
-             FROM pragma_table_info(?)"
-                .to_string(),
-            true,
-        ),
     };
 
-    // execute query with or without parameter binding
-    let result = if use_bind {
-        sqlx::query(&query).bind(&name).fetch_all(&pool).await
-    } else {
-        sqlx::query(&query).fetch_all(&pool).await
-    };
-
-    match result {
-        Ok(rows) => {
-            let columns: Vec<ColumnInfo> = rows
-                .into_iter()
-                .map(|row| {
-                    // normalize is_primary: try bool (PG/MySQL), fallback to int relative to 1 (SQLite uses 'pk')
-                    let is_primary_key: bool = row
-                        .try_get("is_primary")
-                        .or_else(|_| row.try_get::<i64, _>("pk").map(|pk| pk > 0)) // SQLite PRAGMA column
-                        .unwrap_or_else(|_| row.try_get::<i64, _>("is_primary").unwrap_or(0) == 1);
-
-                    // normalize is_nullable: try lowercase first, then uppercase for MySQL, then SQLite 'notnull'
-                    let nullable: bool = row
-                        .try_get::<String, _>("is_nullable")
-                        .or_else(|_| row.try_get::<String, _>("IS_NULLABLE"))
-                        .map(|s| s.eq_ignore_ascii_case("YES"))
-                        .unwrap_or_else(|_| {
-                            // SQLite PRAGMA uses 'notnull' where 0 = nullable, 1 = not null
-                            row.try_get::<i64, _>("notnull")
-                                .map(|nn| nn == 0)
-                                .unwrap_or(true)
-                        });
-
-                    // try lowercase first, then uppercase for MySQL, then SQLite 'name'
-                    let name: String = row
-                        .try_get("column_name")
-                        .or_else(|_| row.try_get("COLUMN_NAME"))
-                        .or_else(|_| row.try_get("name")) // SQLite PRAGMA column
-                        .unwrap_or_default();
-
-                    // for MySQL, data_type may come as BLOB, so try bytes first then string
-                    // For SQLite PRAGMA, column is 'type'
-                    let data_type: String = row
-                        .try_get::<String, _>("data_type")
-                        .or_else(|_| row.try_get::<String, _>("DATA_TYPE"))
-                        .or_else(|_| row.try_get::<String, _>("type")) // SQLite PRAGMA column
-                        .or_else(|_| {
-                            // try as bytes and convert to string
-                            row.try_get::<Vec<u8>, _>("data_type")
-                                .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
-                        })
-                        .unwrap_or_default();
-
-                    // Normalize SQLite types for consistency (SQLite returns exact type from CREATE TABLE)
-                    let data_type = if matches!(db_type, DbType::Sqlite) {
-                        match data_type.to_uppercase().as_str() {
-                            "DATETIME" | "TIMESTAMP" => "DATETIME".to_string(),
-                            "DATE" => "DATE".to_string(),
-                            "INT" | "INTEGER" => "INTEGER".to_string(),
-                            "REAL" | "DOUBLE" | "FLOAT" => "REAL".to_string(),
-                            "BOOL" | "BOOLEAN" => "BOOLEAN".to_string(),
-                            "TEXT" | "CHAR" | "VARCHAR" | "CLOB" => "TEXT".to_string(),
-                            "BLOB" => "BLOB".to_string(),
-                            _ => data_type, // Keep original if unrecognized
-                        }
-                    } else {
-                        data_type
-                    };
-
-                    ColumnInfo {
-                        name,
-                        data_type,
-                        nullable,
-                        is_primary_key,
-                        default_value: row
-                            .try_get("column_default")
-                            .or_else(|_| row.try_get("COLUMN_DEFAULT"))
-                            .or_else(|_| row.try_get("dflt_value")) // SQLite PRAGMA column
-                            .ok(),
-                    }
+    let rows = result.map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            // normalize is_primary: try bool (PG/MySQL), fallback to int relative to 1 (SQLite uses 'pk')
+            let is_primary_key: bool = row
+                .try_get("is_primary")
+                .or_else(|_| row.try_get::<i64, _>("pk").map(|pk| pk > 0)) // SQLite PRAGMA column
+                .unwrap_or_else(|_| row.try_get::<i64, _>("is_primary").unwrap_or(0) == 1);
+
+            // normalize is_nullable: try lowercase first, then uppercase for MySQL, then SQLite 'notnull'
+            let nullable: bool = row
+                .try_get::<String, _>("is_nullable")
+                .or_else(|_| row.try_get::<String, _>("IS_NULLABLE"))
+                .map(|s| s.eq_ignore_ascii_case("YES"))
+                .unwrap_or_else(|_| {
+                    // SQLite PRAGMA uses 'notnull' where 0 = nullable, 1 = not null
+                    row.try_get::<i64, _>("notnull")
+                        .map(|nn| nn == 0)
+                        .unwrap_or(true)
+                });
+
+            // try lowercase first, then uppercase for MySQL, then SQLite 'name'
+            let name: String = row
+                .try_get("column_name")
+                .or_else(|_| row.try_get("COLUMN_NAME"))
+                .or_else(|_| row.try_get("name")) // SQLite PRAGMA column
+                .unwrap_or_default();
+
+            // for MySQL, data_type may come as BLOB, so try bytes first then string
+            // For SQLite PRAGMA, column is 'type'
+            let data_type: String = row
+                .try_get::<String, _>("data_type")
+                .or_else(|_| row.try_get::<String, _>("DATA_TYPE"))
+                .or_else(|_| row.try_get::<String, _>("type")) // SQLite PRAGMA column
+                .or_else(|_| {
+                    // try as bytes and convert to string
+                    row.try_get::<Vec<u8>, _>("data_type")
+                        .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
                 })
-                .collect();
+                .unwrap_or_default();
+
+            // Normalize SQLite types for consistency (SQLite returns exact type from CREATE TABLE)
+            let data_type = if matches!(db_type, DbType::Sqlite) {
+                match data_type.to_uppercase().as_str() {
+                    "DATETIME" | "TIMESTAMP" => "DATETIME".to_string(),
+                    "DATE" => "DATE".to_string(),
+                    "INT" | "INTEGER" => "INTEGER".to_string(),
+                    "REAL" | "DOUBLE" | "FLOAT" => "REAL".to_string(),
+                    "BOOL" | "BOOLEAN" => "BOOLEAN".to_string(),
+                    "TEXT" | "CHAR" | "VARCHAR" | "CLOB" => "TEXT".to_string(),
+                    "BLOB" => "BLOB".to_string(),
+                    _ => data_type, // Keep original if unrecognized
+                }
+            } else {
+                data_type
+            };
 
-            Json(ApiResponse::success(json!({ "columns": columns })))
-        }
-        Err(e) => Json(ApiResponse::error(e.to_string())),
-    }
+            let auto_increment: bool = row
+                .try_get("is_auto_increment")
+                .unwrap_or_else(|_| row.try_get::<i64, _>("is_auto_increment").unwrap_or(0) == 1);
+
+            let generated: Option<String> = match db_type {
+                DbType::Sqlite => row
+                    .try_get::<Option<String>, _>("table_sql")
+                    .ok()
+                    .flatten()
+                    .and_then(|sql| generated_clause(&sql, &name)),
+                // MySQL escapes the quotes of string literals in generation expressions
+                DbType::Mysql => row
+                    .try_get::<Option<String>, _>("generated")
+                    .ok()
+                    .flatten()
+                    .map(|clause| clause.replace("\\'", "'")),
+                DbType::Postgres => row.try_get("generated").unwrap_or(None),
+            };
+
+            let column = ColumnInfo {
+                name,
+                data_type,
+                nullable,
+                is_primary_key,
+                auto_increment,
+                default_value: row
+                    .try_get("column_default")
+                    .or_else(|_| row.try_get("COLUMN_DEFAULT"))
+                    .or_else(|_| row.try_get("dflt_value")) // SQLite PRAGMA column
+                    .ok(),
+                generated,
+                identity_always: row.try_get("identity_always").unwrap_or(false),
+            };
+            (row.try_get("table_name").unwrap_or_default(), column)
+        })
+        .collect())
 }
 
 /// GET /api/schema/table/{name}/indexes - Get table indexes
@@ -244,143 +487,241 @@ async fn get_indexes(
     AuthSession(session): AuthSession,
     Path(name): Path<String>,
 ) -> Json<ApiResponse<Value>> {
-    let pool = session.pool;
-    let db_type = session.db_type;
-    let db_name = session.database;
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    match fetch_indexes(&session.pool, &session.db_type, &table).await {
+        Ok(indexes) => Json(ApiResponse::success(json!({ "indexes": indexes }))),
+        Err(e) => Json(ApiResponse::error(e)),
+    }
+}
+
+/// Lists the indexes of a table, including the primary key
+pub async fn fetch_indexes(
+    pool: &AnyPool,
+    db_type: &DbType,
+    table: &TableRef,
+) -> Result<Vec<IndexInfo>, String> {
+    let indexes = fetch_schema_indexes(pool, db_type, &table.schema, Some(&table.name)).await?;
+    Ok(indexes.into_iter().map(|(_, index)| index).collect())
+}
 
-    // use an async block to allow using the `?` operator for error handling
-    let result: Result<Vec<IndexInfo>, String> = async {
-        match db_type {
-            DbType::Postgres => {
-                let sql = "
+/// Lists the indexes of every table in a schema, or of a single one, paired with their table's name
+pub async fn fetch_schema_indexes(
+    pool: &AnyPool,
+    db_type: &DbType,
+    schema: &str,
+    table: Option<&str>,
+) -> Result<Vec<(String, IndexInfo)>, String> {
+    match db_type {
+        DbType::Postgres => {
+            // expressions are numbered 0 in indkey, so an index on nothing but expressions
+            // has no columns
+            let sql = "
                     SELECT
-                        i.relname as name,
+                        t.relname::text as table_name,
+                        i.relname::text as name,
                         ix.indisunique as is_unique,
                         ix.indisprimary as is_primary,
-                        array_to_json(array_agg(a.attname))::text as column_names
-                    FROM pg_class t, pg_class i, pg_index ix, pg_attribute a
-                    WHERE t.oid = ix.indrelid
-                      AND i.oid = ix.indexrelid
-                      AND a.attrelid = t.oid
-                      AND a.attnum = ANY(ix.indkey)
-                      AND t.relkind = 'r'
-                      AND t.relname = $1
-                    GROUP BY i.relname, ix.indisunique, ix.indisprimary
+                        COALESCE(array_to_json(array_agg(
+                            a.attname ORDER BY array_position(ix.indkey::int2[], a.attnum)
+                        ) FILTER (WHERE a.attname IS NOT NULL))::text, '[]') as column_names,
+                        pg_get_indexdef(i.oid) as definition
+                    FROM pg_index ix
+                    JOIN pg_class t ON t.oid = ix.indrelid
+                    JOIN pg_class i ON i.oid = ix.indexrelid
+                    JOIN pg_namespace n ON n.oid = t.relnamespace
+                    LEFT JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = ANY(ix.indkey)
+                    WHERE t.relkind = 'r'
+                      AND n.nspname = $1
+                      AND ($2::text IS NULL OR t.relname = $2)
+                    GROUP BY t.relname, i.relname, i.oid, ix.indisunique, ix.indisprimary
+                    ORDER BY t.relname, i.relname
                 ";
 
-                let rows = sqlx::query(sql)
-                    .bind(name)
-                    .fetch_all(&pool)
-                    .await
-                    .map_err(|e| e.to_string())?;
-
-                let indexes = rows
-                    .into_iter()
-                    .map(|row| {
-                        let columns_json_str: String = row
-                            .try_get("column_names")
-                            .unwrap_or_else(|_| "[]".to_string());
-                        let column_names: Vec<String> =
-                            serde_json::from_str(&columns_json_str).unwrap_or_default();
-
-                        IndexInfo {
-                            name: row.try_get("name").unwrap_or_default(),
-                            is_unique: row.try_get("is_unique").unwrap_or(false),
-                            is_primary: row.try_get("is_primary").unwrap_or(false),
-                            column_names,
-                        }
-                    })
-                    .collect();
+            let rows = sqlx::query(sql)
+                .bind(schema)
+                .bind(table)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| e.to_string())?;
 
-                Ok(indexes)
-            }
-            DbType::Mysql => {
-                // Escape single quotes for MySQL
-                let safe_db = db_name.replace("'", "''");
-                let safe_name = name.replace("'", "''");
-                let sql = format!(
-                    "
+            let indexes = rows
+                .into_iter()
+                .map(|row| {
+                    let columns_json_str: String = row
+                        .try_get("column_names")
+                        .unwrap_or_else(|_| "[]".to_string());
+                    let column_names: Vec<String> =
+                        serde_json::from_str(&columns_json_str).unwrap_or_default();
+
+                    let definition: Option<String> = row.try_get("definition").unwrap_or(None);
+                    let index = IndexInfo {
+                        name: row.try_get("name").unwrap_or_default(),
+                        is_unique: row.try_get("is_unique").unwrap_or(false),
+                        is_primary: row.try_get("is_primary").unwrap_or(false),
+                        column_names,
+                        definition: definition.as_deref().and_then(index_definition),
+                        kind: None,
+                    };
+                    (row.try_get("table_name").unwrap_or_default(), index)
+                })
+                .collect();
+
+            Ok(indexes)
+        }
+        DbType::Mysql => {
+            let table_filter = table
+                .map(|t| format!("AND TABLE_NAME = {}", escape_string_literal(t, db_type)))
+                .unwrap_or_default();
+            // one row per key part, in index order; functional key parts have an EXPRESSION
+            // instead of a COLUMN_NAME
+            let sql = format!(
+                "
                     SELECT 
-                        INDEX_NAME as name,
+                        CAST(TABLE_NAME AS CHAR) as table_name,
+                        CAST(INDEX_NAME AS CHAR) as name,
                         NON_UNIQUE = 0 as is_unique,
-                        INDEX_NAME = 'PRIMARY' as is_primary,
-                        GROUP_CONCAT(COLUMN_NAME ORDER BY SEQ_IN_INDEX) as column_names
+                        CAST(INDEX_TYPE AS CHAR) as index_type,
+                        CAST(COLUMN_NAME AS CHAR) as column_name,
+                        CAST(SUB_PART AS SIGNED) as sub_part,
+                        CAST(COLLATION AS CHAR) as collation,
+                        CAST(EXPRESSION AS CHAR) as expression
                     FROM INFORMATION_SCHEMA.STATISTICS
-                    WHERE TABLE_SCHEMA = '{}' AND TABLE_NAME = '{}'
-                    GROUP BY INDEX_NAME, NON_UNIQUE, INDEX_NAME
+                    WHERE TABLE_SCHEMA = {} {}
+                    ORDER BY TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX
                 ",
-                    safe_db, safe_name
-                );
-
-                let rows = sqlx::query(&sql)
-                    .fetch_all(&pool)
-                    .await
-                    .map_err(|e| e.to_string())?;
-
-                let indexes = rows
-                    .into_iter()
-                    .map(|row| {
-                        let columns_str: String = row.try_get("column_names").unwrap_or_default();
-                        let column_names: Vec<String> =
-                            columns_str.split(',').map(|s| s.to_string()).collect();
-
-                        let is_unique_int: i64 = row.try_get("is_unique").unwrap_or(0);
-                        let is_primary_int: i64 = row.try_get("is_primary").unwrap_or(0);
-
-                        IndexInfo {
-                            name: row.try_get("name").unwrap_or_default(),
-                            is_unique: is_unique_int == 1,
-                            is_primary: is_primary_int == 1,
-                            column_names,
-                        }
-                    })
-                    .collect();
+                escape_string_literal(schema, db_type),
+                table_filter
+            );
+
+            let rows = sqlx::query(&sql)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            // each index with its key parts and INDEX_TYPE
+            let mut indexes: Vec<(String, IndexInfo, Vec<String>, String)> = Vec::new();
+            for row in rows {
+                let table_name: String = row.try_get("table_name").unwrap_or_default();
+                let index_name: String = row.try_get("name").unwrap_or_default();
+                let column_name: Option<String> = row.try_get("column_name").unwrap_or(None);
+
+                // MySQL escapes the quotes of string literals in index expressions
+                let mut part = match &column_name {
+                    Some(column) => quote_identifier(column, db_type),
+                    None => {
+                        let expression: Option<String> = row.try_get("expression").unwrap_or(None);
+                        format!("({})", expression.unwrap_or_default().replace("\\'", "'"))
+                    }
+                };
+                if let Ok(Some(length)) = row.try_get::<Option<i64>, _>("sub_part") {
+                    part.push_str(&format!("({})", length));
+                }
+                let collation: Option<String> = row.try_get("collation").unwrap_or(None);
+                if collation.as_deref() == Some("D") {
+                    part.push_str(" DESC");
+                }
 
-                Ok(indexes)
+                match indexes.last_mut() {
+                    Some((t, index, parts, _)) if *t == table_name && index.name == index_name => {
+                        index.column_names.extend(column_name);
+                        parts.push(part);
+                    }
+                    _ => {
+                        let index = IndexInfo {
+                            is_unique: row.try_get::<i64, _>("is_unique").unwrap_or(0) == 1,
+                            is_primary: index_name == "PRIMARY",
+                            name: index_name,
+                            column_names: column_name.into_iter().collect(),
+                            definition: None,
+                            kind: None,
+                        };
+                        let index_type = row.try_get("index_type").unwrap_or_default();
+                        indexes.push((table_name, index, vec![part], index_type));
+                    }
+                }
             }
-            DbType::Sqlite => {
-                let index_list =
-                    sqlx::query("SELECT name, `unique`, origin FROM pragma_index_list(?)")
-                        .bind(&name)
-                        .fetch_all(&pool)
-                        .await
-                        .map_err(|e| e.to_string())?;
-
-                let mut indexes = Vec::new();
-                for row in index_list {
-                    let index_name: String = row.try_get("name").unwrap_or_default();
-                    let is_unique: bool = row.try_get::<i64, _>("unique").unwrap_or(0) == 1;
-                    let origin: String = row.try_get("origin").unwrap_or_default();
-                    let is_primary = origin == "pk";
-
-                    // Now get columns for this index - use parameterized query
-                    let cols_rows = sqlx::query("SELECT name FROM pragma_index_info(?)")
-                        .bind(&index_name)
-                        .fetch_all(&pool)
-                        .await
-                        .unwrap_or_default();
-                    let column_names: Vec<String> = cols_rows
-                        .into_iter()
-                        .map(|r| r.try_get("name").unwrap_or_default())
-                        .collect();
-
-                    indexes.push(IndexInfo {
-                        name: index_name,
-                        is_unique,
-                        is_primary,
-                        column_names,
+
+            Ok(indexes
+                .into_iter()
+                .map(|(table_name, mut index, parts, index_type)| {
+                    let parts = format!("({})", parts.join(", "));
+                    // BTREE is the default method
+                    index.definition = Some(match index_type.as_str() {
+                        "HASH" => format!("{} USING HASH", parts),
+                        _ => parts,
                     });
+                    if matches!(index_type.as_str(), "FULLTEXT" | "SPATIAL") {
+                        index.kind = Some(index_type);
+                    }
+                    (table_name, index)
+                })
+                .collect())
+        }
+        DbType::Sqlite => {
+            // one row per indexed column, in index order; expressions have no name
+            let rows = sqlx::query(
+                "SELECT m.name AS table_name, il.name, il.`unique`, il.origin,
+                        ii.name AS column_name, s.sql AS definition
+                 FROM sqlite_schema m
+                 JOIN pragma_index_list(m.name) il
+                 JOIN pragma_index_info(il.name) ii
+                 LEFT JOIN sqlite_schema s ON s.type = 'index' AND s.name = il.name
+                 WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'
+                   AND (?1 IS NULL OR m.name = ?1 COLLATE NOCASE)
+                 ORDER BY m.name, il.seq, ii.seqno",
+            )
+            .bind(table)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+            let mut indexes: Vec<(String, IndexInfo)> = Vec::new();
+            for row in rows {
+                let table_name: String = row.try_get("table_name").unwrap_or_default();
+                let index_name: String = row.try_get("name").unwrap_or_default();
+                let column_name: Option<String> = row.try_get("column_name").unwrap_or(None);
+                match indexes.last_mut() {
+                    Some((t, index)) if *t == table_name && index.name == index_name => {
+                        index.column_names.extend(column_name);
+                    }
+                    _ => {
+                        let origin: String = row.try_get("origin").unwrap_or_default();
+                        let definition: Option<String> = row.try_get("definition").unwrap_or(None);
+                        let index = IndexInfo {
+                            name: index_name,
+                            is_unique: row.try_get::<i64, _>("unique").unwrap_or(0) == 1,
+                            is_primary: origin == "pk",
+                            column_names: column_name.into_iter().collect(),
+                            definition: definition.as_deref().and_then(index_definition),
+                            kind: None,
+                        };
+                        indexes.push((table_name, index));
+                    }
                 }
-                Ok(indexes)
             }
+            Ok(indexes)
         }
     }
-    .await;
+}
 
-    match result {
-        Ok(indexes) => Json(ApiResponse::success(json!({ "indexes": indexes }))),
-        Err(e) => Json(ApiResponse::error(e)),
+/// The part of a CREATE INDEX statement after the table name: the method, key columns,
+/// options and predicate
+fn index_definition(create_index: &str) -> Option<String> {
+    let tokens = tokenize(create_index).ok()?;
+    let on = tokens.iter().position(|t| t.is_keyword("ON"))?;
+    let mut rest = tokens[on + 1..].iter().skip_while(|t| t.is_keyword("ONLY"));
+    // the table name, schema-qualified on PostgreSQL
+    rest.next()?;
+    let mut rest = rest.peekable();
+    if rest.peek().is_some_and(|t| t.text == ".") {
+        rest.next();
+        rest.next()?;
     }
+    let start = rest.next()?.start;
+    Some(create_index[start..].trim().to_string())
 }
 
 /// GET /api/schema/table/{name}/foreign-keys - Get foreign key relationships
@@ -388,129 +729,469 @@ async fn get_foreign_keys(
     AuthSession(session): AuthSession,
     Path(name): Path<String>,
 ) -> Json<ApiResponse<Value>> {
-    let pool = session.pool;
-    let db_type = session.db_type;
-    let db_name = session.database;
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    match fetch_foreign_keys(&session.pool, &session.db_type, &table).await {
+        Ok(foreign_keys) => Json(ApiResponse::success(
+            json!({ "foreign_keys": foreign_keys }),
+        )),
+        Err(e) => Json(ApiResponse::error(e)),
+    }
+}
 
-    let result: Result<Vec<ForeignKeyInfo>, String> = async {
-        match db_type {
-            DbType::Postgres => {
-                let sql = "
+/// Lists the foreign keys of a table, one entry per constraint
+pub async fn fetch_foreign_keys(
+    pool: &AnyPool,
+    db_type: &DbType,
+    table: &TableRef,
+) -> Result<Vec<ForeignKeyInfo>, String> {
+    let fks = fetch_schema_foreign_keys(pool, db_type, &table.schema, Some(&table.name)).await?;
+    Ok(fks.into_iter().map(|(_, fk)| fk).collect())
+}
+
+/// Lists the foreign keys of every table in a schema, or of a single one, paired with their
+/// table's name
+pub async fn fetch_schema_foreign_keys(
+    pool: &AnyPool,
+    db_type: &DbType,
+    schema: &str,
+    table: Option<&str>,
+) -> Result<Vec<(String, ForeignKeyInfo)>, String> {
+    // one row per column pair, in key order, merged into constraints below
+    let rows: Vec<(String, ForeignKeyInfo)> = match db_type {
+        DbType::Postgres => {
+            // pg_constraint pairs the columns by position, which information_schema cannot do
+            let sql = "
                     SELECT
-                        tc.constraint_name::TEXT as constraint_name,
-                        kcu.column_name::TEXT as column_name,
-                        ccu.table_name::TEXT AS foreign_table,
-                        ccu.column_name::TEXT AS foreign_column
-                    FROM information_schema.table_constraints AS tc
-                    JOIN information_schema.key_column_usage AS kcu
-                      ON tc.constraint_name = kcu.constraint_name
-                      AND tc.table_schema = kcu.table_schema
-                    JOIN information_schema.constraint_column_usage AS ccu
-                      ON ccu.constraint_name = tc.constraint_name
-                      AND ccu.table_schema = tc.table_schema
-                    WHERE tc.constraint_type = 'FOREIGN KEY'
-                      AND tc.table_name = $1
+                        t.relname::TEXT AS table_name,
+                        c.conname::TEXT AS constraint_name,
+                        a.attname::TEXT AS column_name,
+                        fns.nspname::TEXT AS foreign_schema,
+                        ft.relname::TEXT AS foreign_table,
+                        fa.attname::TEXT AS foreign_column,
+                        c.confupdtype::TEXT AS on_update,
+                        c.confdeltype::TEXT AS on_delete,
+                        c.condeferrable AS deferrable,
+                        c.condeferred AS initially_deferred
+                    FROM pg_constraint c
+                    JOIN pg_class t ON t.oid = c.conrelid
+                    JOIN pg_namespace ns ON ns.oid = t.relnamespace
+                    JOIN pg_class ft ON ft.oid = c.confrelid
+                    JOIN pg_namespace fns ON fns.oid = ft.relnamespace
+                    CROSS JOIN LATERAL unnest(c.conkey, c.confkey)
+                        WITH ORDINALITY AS k(attnum, foreign_attnum, position)
+                    JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum
+                    JOIN pg_attribute fa
+                      ON fa.attrelid = c.confrelid AND fa.attnum = k.foreign_attnum
+                    WHERE c.contype = 'f'
+                      AND ns.nspname = $1
+                      AND ($2::text IS NULL OR t.relname = $2)
+                    ORDER BY t.relname, c.conname, k.position
                 ";
-                let rows = sqlx::query(sql)
-                    .bind(name)
-                    .fetch_all(&pool)
-                    .await
-                    .map_err(|e| e.to_string())?;
-
-                let fks = rows
-                    .into_iter()
-                    .map(|row| crate::models::ForeignKeyInfo {
+            let rows = sqlx::query(sql)
+                .bind(schema)
+                .bind(table)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            rows.into_iter()
+                .map(|row| {
+                    let action = |column: &str| {
+                        let code: String = row.try_get(column).unwrap_or_default();
+                        match code.as_str() {
+                            "r" => "RESTRICT",
+                            "c" => "CASCADE",
+                            "n" => "SET NULL",
+                            "d" => "SET DEFAULT",
+                            _ => "NO ACTION",
+                        }
+                        .to_string()
+                    };
+                    let fk = ForeignKeyInfo {
                         constraint_name: row.try_get("constraint_name").unwrap_or_default(),
-                        column_name: row.try_get("column_name").unwrap_or_default(),
+                        columns: vec![row.try_get("column_name").unwrap_or_default()],
+                        foreign_schema: row.try_get("foreign_schema").unwrap_or_default(),
                         foreign_table: row.try_get("foreign_table").unwrap_or_default(),
-                        foreign_column: row.try_get("foreign_column").unwrap_or_default(),
-                    })
-                    .collect();
-                Ok(fks)
-            }
-            DbType::Mysql => {
-                // Escape single quotes for MySQL
-                let safe_db = db_name.replace("'", "''");
-                let safe_name = name.replace("'", "''");
-                let sql = format!(
-                    "
+                        foreign_columns: vec![row.try_get("foreign_column").unwrap_or_default()],
+                        on_update: action("on_update"),
+                        on_delete: action("on_delete"),
+                        deferrable: row.try_get("deferrable").unwrap_or(false),
+                        initially_deferred: row.try_get("initially_deferred").unwrap_or(false),
+                    };
+                    (row.try_get("table_name").unwrap_or_default(), fk)
+                })
+                .collect()
+        }
+        DbType::Mysql => {
+            // Escape single quotes for MySQL
+            let table_filter = table
+                .map(|t| format!("AND kcu.TABLE_NAME = '{}'", t.replace("'", "''")))
+                .unwrap_or_default();
+            let sql = format!(
+                "
                     SELECT 
-                        CONSTRAINT_NAME as constraint_name,
-                        COLUMN_NAME as column_name,
-                        REFERENCED_TABLE_NAME as foreign_table,
-                        REFERENCED_COLUMN_NAME as foreign_column
-                    FROM INFORMATION_SCHEMA.KEY_COLUMN_USAGE
-                    WHERE TABLE_SCHEMA = '{}' 
-                      AND TABLE_NAME = '{}' 
-                      AND REFERENCED_TABLE_NAME IS NOT NULL
+                        CAST(kcu.TABLE_NAME AS CHAR) as table_name,
+                        kcu.CONSTRAINT_NAME as constraint_name,
+                        kcu.COLUMN_NAME as column_name,
+                        kcu.REFERENCED_TABLE_SCHEMA as foreign_schema,
+                        kcu.REFERENCED_TABLE_NAME as foreign_table,
+                        kcu.REFERENCED_COLUMN_NAME as foreign_column,
+                        rc.UPDATE_RULE as on_update,
+                        rc.DELETE_RULE as on_delete
+                    FROM INFORMATION_SCHEMA.KEY_COLUMN_USAGE kcu
+                    JOIN INFORMATION_SCHEMA.REFERENTIAL_CONSTRAINTS rc
+                      ON rc.CONSTRAINT_SCHEMA = kcu.CONSTRAINT_SCHEMA
+                      AND rc.CONSTRAINT_NAME = kcu.CONSTRAINT_NAME
+                      AND rc.TABLE_NAME = kcu.TABLE_NAME
+                    WHERE kcu.TABLE_SCHEMA = '{}' {}
+                      AND kcu.REFERENCED_TABLE_NAME IS NOT NULL
+                    ORDER BY kcu.TABLE_NAME, kcu.CONSTRAINT_NAME, kcu.ORDINAL_POSITION
                 ",
-                    safe_db, safe_name
-                );
+                schema.replace("'", "''"),
+                table_filter
+            );
 
-                let rows = sqlx::query(&sql)
-                    .fetch_all(&pool)
-                    .await
-                    .map_err(|e| e.to_string())?;
+            let rows = sqlx::query(&sql)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| e.to_string())?;
 
-                let fks = rows
-                    .into_iter()
-                    .map(|row| crate::models::ForeignKeyInfo {
+            rows.into_iter()
+                .map(|row| {
+                    let fk = ForeignKeyInfo {
                         constraint_name: row.try_get("constraint_name").unwrap_or_default(),
-                        column_name: row.try_get("column_name").unwrap_or_default(),
+                        columns: vec![row.try_get("column_name").unwrap_or_default()],
+                        foreign_schema: row.try_get("foreign_schema").unwrap_or_default(),
                         foreign_table: row.try_get("foreign_table").unwrap_or_default(),
-                        foreign_column: row.try_get("foreign_column").unwrap_or_default(),
-                    })
-                    .collect();
-                Ok(fks)
+                        foreign_columns: vec![row.try_get("foreign_column").unwrap_or_default()],
+                        on_update: row.try_get("on_update").unwrap_or_default(),
+                        on_delete: row.try_get("on_delete").unwrap_or_default(),
+                        deferrable: false,
+                        initially_deferred: false,
+                    };
+                    (row.try_get("table_name").unwrap_or_default(), fk)
+                })
+                .collect()
+        }
+        DbType::Sqlite => {
+            let rows = sqlx::query(
+                "SELECT m.name AS table_name, f.id, f.`from`, f.`table`, f.`to`,
+                        f.on_update, f.on_delete
+                 FROM sqlite_schema m
+                 JOIN pragma_foreign_key_list(m.name) f
+                 WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'
+                   AND (?1 IS NULL OR m.name = ?1 COLLATE NOCASE)
+                 ORDER BY m.name, f.id, f.seq",
+            )
+            .bind(table)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+            rows.into_iter()
+                .map(|row| {
+                    let id: i64 = row.try_get("id").unwrap_or(0);
+                    // `to` is NULL when the key refers to the primary key implicitly
+                    let to: Option<String> = row.try_get("to").unwrap_or(None);
+                    let fk = ForeignKeyInfo {
+                        constraint_name: format!("fk_{}", id), // renamed below if declared with a name
+                        columns: vec![row.try_get("from").unwrap_or_default()],
+                        foreign_schema: schema.to_string(),
+                        foreign_table: row.try_get("table").unwrap_or_default(),
+                        foreign_columns: to.into_iter().collect(),
+                        on_update: row.try_get("on_update").unwrap_or_default(),
+                        on_delete: row.try_get("on_delete").unwrap_or_default(),
+                        deferrable: false,
+                        initially_deferred: false,
+                    };
+                    (row.try_get("table_name").unwrap_or_default(), fk)
+                })
+                .collect()
+        }
+    };
+
+    let mut fks: Vec<(String, ForeignKeyInfo)> = Vec::new();
+    for (table_name, row) in rows {
+        match fks.last_mut() {
+            Some((t, fk)) if *t == table_name && fk.constraint_name == row.constraint_name => {
+                fk.columns.extend(row.columns);
+                fk.foreign_columns.extend(row.foreign_columns);
             }
-            DbType::Sqlite => {
-                let rows =
-                    sqlx::query("SELECT id, `from`, `table`, `to` FROM pragma_foreign_key_list(?)")
-                        .bind(&name)
-                        .fetch_all(&pool)
-                        .await
-                        .map_err(|e| e.to_string())?;
-
-                let fks = rows
-                    .into_iter()
-                    .map(|row| {
-                        let id: i64 = row.try_get("id").unwrap_or(0);
-                        crate::models::ForeignKeyInfo {
-                            constraint_name: format!("fk_{}", id), // SQLite doesn't always name FKs explicitly in this list
-                            column_name: row.try_get("from").unwrap_or_default(),
-                            foreign_table: row.try_get("table").unwrap_or_default(),
-                            foreign_column: row.try_get("to").unwrap_or_default(),
-                        }
-                    })
-                    .collect();
-                Ok(fks)
+            _ => fks.push((table_name, row)),
+        }
+    }
+
+    // SQLite only keeps constraint names in the table's SQL
+    if matches!(db_type, DbType::Sqlite) && !fks.is_empty() {
+        let rows = sqlx::query(
+            "SELECT name, sql FROM sqlite_schema
+             WHERE type = 'table' AND (?1 IS NULL OR name = ?1 COLLATE NOCASE)",
+        )
+        .bind(table)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let table_sql: HashMap<String, String> = rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.try_get("name").unwrap_or_default(),
+                    row.try_get("sql").unwrap_or_default(),
+                )
+            })
+            .collect();
+        for (table_name, fk) in &mut fks {
+            let Some(sql) = table_sql.get(table_name) else {
+                continue;
+            };
+            if let Some(name) = foreign_key_name(sql, &fk.columns, &fk.foreign_table) {
+                fk.constraint_name = name;
             }
         }
     }
-    .await;
+    Ok(fks)
+}
 
-    match result {
-        Ok(fks) => Json(ApiResponse::success(json!({ "foreign_keys": fks }))),
+/// GET /api/schema/table/{name}/ddl - Get the CREATE statements of a table, view or index
+async fn get_table_ddl(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+) -> Json<ApiResponse<Value>> {
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    match fetch_ddl(&session, &table).await {
+        Ok((object_type, ddl)) => Json(ApiResponse::success(json!({
+            "table": name,
+            "object_type": object_type,
+            "ddl": ddl
+        }))),
         Err(e) => Json(ApiResponse::error(e)),
     }
 }
 
+/// The DDL of a table or view with its indexes, or of an index, as its object type and the
+/// statements. MySQL and SQLite report the statements they keep; PostgreSQL's are rebuilt from
+/// the catalog.
+pub async fn fetch_ddl(session: &Session, table: &TableRef) -> Result<(String, String), String> {
+    let (pool, db_type) = (&session.pool, &session.db_type);
+    // tables and views come first, as MySQL names indexes apart from them
+    if fetch_columns(pool, db_type, table).await?.is_empty()
+        && let Some(ddl) = fetch_index_ddl(session, table).await?
+    {
+        return Ok(("INDEX".to_string(), ddl));
+    }
+    match db_type {
+        DbType::Postgres => {
+            if let Some(view) = fetch_view(pool, db_type, table).await? {
+                let object_type = if view.materialized {
+                    "MATERIALIZED VIEW"
+                } else {
+                    "VIEW"
+                };
+                let mut ddl = format!(
+                    "CREATE {} {} AS\n{};\n",
+                    object_type,
+                    table.quoted(db_type),
+                    view.definition
+                );
+                for index in pg_index_definitions(pool, table).await? {
+                    ddl.push_str(&format!("{};\n", index));
+                }
+                return Ok((object_type.to_string(), ddl));
+            }
+
+            let schema = TableSchema::load(session, table).await?;
+            if schema.columns.is_empty() {
+                return Err(format!("Table '{}' not found", table));
+            }
+            let mut ddl = schema.create_table(db_type);
+            for index in pg_index_definitions(pool, table).await? {
+                ddl.push_str(&format!("{};\n", index));
+            }
+            // unique, check and exclusion constraints; the primary key is part of CREATE TABLE
+            let constraints = sqlx::query(
+                "SELECT c.conname::text AS name, pg_get_constraintdef(c.oid) AS definition
+                 FROM pg_constraint c
+                 JOIN pg_class t ON t.oid = c.conrelid
+                 JOIN pg_namespace n ON n.oid = t.relnamespace
+                 WHERE n.nspname = $1 AND t.relname = $2 AND c.contype IN ('u', 'c', 'x')
+                 ORDER BY c.conname",
+            )
+            .bind(&table.schema)
+            .bind(&table.name)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            for row in constraints {
+                let name: String = row.try_get("name").unwrap_or_default();
+                let definition: String = row.try_get("definition").unwrap_or_default();
+                ddl.push_str(&format!(
+                    "ALTER TABLE {} ADD CONSTRAINT {} {};\n",
+                    table.quoted(db_type),
+                    quote_identifier(&name, db_type),
+                    definition
+                ));
+            }
+            for fk in schema.add_foreign_keys(db_type) {
+                ddl.push_str(&fk);
+            }
+            Ok(("TABLE".to_string(), ddl))
+        }
+        DbType::Mysql => {
+            let sql = format!("SHOW CREATE TABLE {}", table.quoted(db_type));
+            let row = sqlx::query(&sql)
+                .fetch_one(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+            // the statement is in "Create Table", or "Create View" for a view
+            let object_type = if row.columns()[1].name() == "Create View" {
+                "VIEW"
+            } else {
+                "TABLE"
+            };
+            let ddl: String = row.try_get(1).map_err(|e| e.to_string())?;
+            Ok((object_type.to_string(), format!("{};\n", ddl)))
+        }
+        DbType::Sqlite => {
+            // the table or view first, then its indexes and triggers in creation order
+            let rows = sqlx::query(
+                "SELECT type, sql FROM sqlite_schema
+                 WHERE tbl_name = ? COLLATE NOCASE AND sql IS NOT NULL
+                 ORDER BY type NOT IN ('table', 'view'), rowid",
+            )
+            .bind(&table.name)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            let object_type: String = match rows.first() {
+                Some(row) => row.try_get("type").unwrap_or_default(),
+                None => return Err(format!("Table '{}' not found", table)),
+            };
+            let ddl: String = rows
+                .iter()
+                .map(|row| format!("{};\n", row.try_get::<String, _>("sql").unwrap_or_default()))
+                .collect();
+            Ok((object_type.to_uppercase(), ddl))
+        }
+    }
+}
+
+/// The CREATE INDEX statement of the index with this name, if there is one. MySQL only names
+/// indexes uniquely within a table; the first table with such an index is taken.
+async fn fetch_index_ddl(session: &Session, index: &TableRef) -> Result<Option<String>, String> {
+    let (pool, db_type) = (&session.pool, &session.db_type);
+    let ddl = match db_type {
+        DbType::Postgres => sqlx::query_scalar(
+            "SELECT pg_get_indexdef(i.oid)
+             FROM pg_class i
+             JOIN pg_namespace n ON n.oid = i.relnamespace
+             WHERE n.nspname = $1 AND i.relname = $2 AND i.relkind IN ('i', 'I')",
+        )
+        .bind(&index.schema)
+        .bind(&index.name)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?,
+        DbType::Mysql => {
+            let table: Option<String> = sqlx::query_scalar(
+                "SELECT CAST(TABLE_NAME AS CHAR) FROM information_schema.STATISTICS
+                 WHERE TABLE_SCHEMA = ? AND INDEX_NAME = ? AND INDEX_NAME <> 'PRIMARY'
+                 ORDER BY TABLE_NAME
+                 LIMIT 1",
+            )
+            .bind(&index.schema)
+            .bind(&index.name)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            let Some(table) = table else {
+                return Ok(None);
+            };
+            let schema =
+                TableSchema::load(session, &TableRef::new(&index.schema, &table, db_type)).await?;
+            schema
+                .indexes
+                .iter()
+                .find(|i| i.name == index.name)
+                .map(|i| schema.create_index(i, db_type).trim_end().to_string())
+        }
+        // the indexes behind constraints have no SQL and go with their table
+        DbType::Sqlite => sqlx::query_scalar(
+            "SELECT sql FROM sqlite_schema
+             WHERE type = 'index' AND name = ? COLLATE NOCASE AND sql IS NOT NULL",
+        )
+        .bind(&index.name)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?,
+    };
+    Ok(ddl.map(|ddl: String| format!("{};\n", ddl.trim_end_matches(';'))))
+}
+
+/// CREATE INDEX statements for the indexes of a PostgreSQL table or materialized view,
+/// leaving out those behind constraints
+async fn pg_index_definitions(pool: &AnyPool, table: &TableRef) -> Result<Vec<String>, String> {
+    sqlx::query_scalar(
+        "SELECT pg_get_indexdef(i.indexrelid)
+         FROM pg_index i
+         JOIN pg_class t ON t.oid = i.indrelid
+         JOIN pg_namespace n ON n.oid = t.relnamespace
+         WHERE n.nspname = $1 AND t.relname = $2
+           AND NOT EXISTS (SELECT 1 FROM pg_constraint c WHERE c.conindid = i.indexrelid)
+         ORDER BY i.indexrelid",
+    )
+    .bind(&table.schema)
+    .bind(&table.name)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Validates a requested ON DELETE / ON UPDATE action
+fn referential_action(action: &str) -> Option<&'static str> {
+    match action.to_uppercase().as_str() {
+        "RESTRICT" => Some("RESTRICT"),
+        "CASCADE" => Some("CASCADE"),
+        "SET NULL" => Some("SET NULL"),
+        "SET DEFAULT" => Some("SET DEFAULT"),
+        "NO ACTION" => Some("NO ACTION"),
+        _ => None,
+    }
+}
+
+/// Renders a requested column default as SQL; anything but a few keywords is a string literal
+fn default_sql(value: &str) -> String {
+    match value.to_uppercase().as_str() {
+        "NULL" => "NULL".to_string(),
+        "CURRENT_TIMESTAMP" | "NOW()" => "CURRENT_TIMESTAMP".to_string(),
+        "TRUE" | "FALSE" => value.to_uppercase(),
+        _ => format!("'{}'", value.replace("'", "''")),
+    }
+}
+
 /// POST /api/schema/table - Create new table
 async fn create_table(
     AuthSession(session): AuthSession,
+    Query(params): Query<SchemaChangeParams>,
     Json(payload): Json<CreateTableRequest>,
 ) -> Json<ApiResponse<Value>> {
-    if !is_valid_identifier(&payload.name) {
-        return Json(ApiResponse::error("Invalid table name"));
-    }
+    let table = match session.table(&payload.name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
 
-    let pool = session.pool;
-    let db_type = session.db_type;
+    let pool = &session.pool;
+    let db_type = session.db_type.clone();
 
     let mut column_defs = Vec::new();
     for col in &payload.columns {
         if !is_valid_identifier(&col.name) {
-            return Json(ApiResponse::error(&format!(
+            return Json(ApiResponse::error(format!(
                 "Invalid column name: {}",
                 col.name
             )));
@@ -588,21 +1269,10 @@ async fn create_table(
         }
 
         // DEFAULT value
-        if let Some(ref default_val) = col.default_value {
-            if !default_val.is_empty() {
-                // Handle common defaults
-                let default_clause = match default_val.to_uppercase().as_str() {
-                    "NULL" => "DEFAULT NULL".to_string(),
-                    "CURRENT_TIMESTAMP" | "NOW()" => match db_type {
-                        DbType::Postgres => "DEFAULT CURRENT_TIMESTAMP".to_string(),
-                        DbType::Mysql => "DEFAULT CURRENT_TIMESTAMP".to_string(),
-                        DbType::Sqlite => "DEFAULT CURRENT_TIMESTAMP".to_string(),
-                    },
-                    "TRUE" | "FALSE" => format!("DEFAULT {}", default_val.to_uppercase()),
-                    _ => format!("DEFAULT '{}'", default_val.replace("'", "''")),
-                };
-                constraints.push(default_clause);
-            }
+        if let Some(ref default_val) = col.default_value
+            && !default_val.is_empty()
+        {
+            constraints.push(format!("DEFAULT {}", default_sql(default_val)));
         }
 
         let constraints_str = constraints.join(" ");
@@ -616,29 +1286,27 @@ async fn create_table(
     // Build foreign key constraints (only if FKs exist)
     let mut fk_constraints = Vec::new();
     for fk in &payload.foreign_keys {
-        // Validate identifiers
-        if !is_valid_identifier(&fk.source_column)
-            || !is_valid_identifier(&fk.target_table)
-            || !is_valid_identifier(&fk.target_column)
-        {
-            return Json(ApiResponse::error(&format!(
-                "Invalid foreign key identifier: {} -> {}.{}",
-                fk.source_column, fk.target_table, fk.target_column
-            )));
-        }
+        // Validate identifiers; the target table may be schema-qualified
+        let target = match session.table(&fk.target_table) {
+            Ok(t)
+                if is_valid_identifier(&fk.source_column)
+                    && is_valid_identifier(&fk.target_column) =>
+            {
+                t
+            }
+            _ => {
+                return Json(ApiResponse::error(format!(
+                    "Invalid foreign key identifier: {} -> {}.{}",
+                    fk.source_column, fk.target_table, fk.target_column
+                )));
+            }
+        };
 
         // Validate ON DELETE action
-        let on_delete_action = match fk.on_delete.to_uppercase().as_str() {
-            "RESTRICT" => "RESTRICT",
-            "CASCADE" => "CASCADE",
-            "SET NULL" => "SET NULL",
-            "SET DEFAULT" => "SET DEFAULT",
-            "NO ACTION" => "NO ACTION",
-            _ => "RESTRICT", // Default fallback
-        };
+        let on_delete_action = referential_action(&fk.on_delete).unwrap_or("RESTRICT"); // Default fallback
 
         let source_col = quote_identifier(&fk.source_column, &db_type);
-        let target_table = quote_identifier(&fk.target_table, &db_type);
+        let target_table = target.quoted(&db_type);
         let target_col = quote_identifier(&fk.target_column, &db_type);
 
         // Generate FK constraint based on DB type
@@ -663,7 +1331,7 @@ async fn create_table(
     }
 
     // construct SQL with columns and foreign keys
-    let table_name_quoted = quote_identifier(&payload.name, &db_type);
+    let table_name_quoted = table.quoted(&db_type);
 
     // Combine column definitions and FK constraints
     let all_defs = if fk_constraints.is_empty() {
@@ -673,9 +1341,12 @@ async fn create_table(
     };
 
     let sql = format!("CREATE TABLE {} ({})", table_name_quoted, all_defs);
+    if params.preview {
+        return preview_response(vec![sql], None);
+    }
 
     // execute the query
-    match sqlx::query(&sql).execute(&pool).await {
+    match sqlx::query(&sql).execute(pool).await {
         Ok(_) => Json(ApiResponse::success(json!({
             "message": "Table created successfully",
             "table": payload.name
@@ -688,17 +1359,26 @@ async fn create_table(
 async fn alter_table(
     AuthSession(session): AuthSession,
     Path(name): Path<String>,
+    Query(params): Query<SchemaChangeParams>,
     Json(payload): Json<AlterTableRequest>,
 ) -> Json<ApiResponse<Value>> {
-    if !is_valid_identifier(&name) {
-        return Json(ApiResponse::error("Invalid table name"));
-    }
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
 
-    let pool = session.pool;
-    let db_type = session.db_type;
+    let pool = session.pool.clone();
+    let db_type = session.db_type.clone();
+
+    let dropped_column = match payload.alter_type {
+        AlterType::DropColumn => payload.column_name.clone(),
+        _ => None,
+    };
+    // a SQLite rebuild is planned first and runs its statements itself
+    let mut rebuild_plan: Option<RebuildPlan> = None;
 
-    let table_name_quoted = quote_identifier(&name, &db_type);
-    let sql = match payload.alter_type {
+    let table_name_quoted = table.quoted(&db_type);
+    let statements = match payload.alter_type {
         AlterType::RenameTable => {
             let new_name = match payload.new_name {
                 Some(n) => n,
@@ -708,10 +1388,10 @@ async fn alter_table(
                 return Json(ApiResponse::error("Invalid new table name"));
             }
             let new_name_quoted = quote_identifier(&new_name, &db_type);
-            format!(
+            vec![format!(
                 "ALTER TABLE {} RENAME TO {}",
                 table_name_quoted, new_name_quoted
-            )
+            )]
         }
         AlterType::AddColumn => {
             let col_def = match payload.column_definition {
@@ -772,31 +1452,21 @@ async fn alter_table(
             let nullable = if col_def.nullable { "" } else { "NOT NULL" };
 
             // Handle DEFAULT value (required for NOT NULL columns on populated tables)
-            let default_clause = if let Some(ref default_val) = col_def.default_value {
-                if !default_val.is_empty() {
-                    match default_val.to_uppercase().as_str() {
-                        "NULL" => "DEFAULT NULL".to_string(),
-                        "CURRENT_TIMESTAMP" | "NOW()" => match db_type {
-                            DbType::Postgres => "DEFAULT CURRENT_TIMESTAMP".to_string(),
-                            DbType::Mysql => "DEFAULT CURRENT_TIMESTAMP".to_string(),
-                            DbType::Sqlite => "DEFAULT CURRENT_TIMESTAMP".to_string(),
-                        },
-                        "TRUE" | "FALSE" => format!("DEFAULT {}", default_val.to_uppercase()),
-                        _ => format!("DEFAULT '{}'", default_val.replace("'", "''")),
-                    }
-                } else {
-                    String::new()
+            let default_clause = match col_def.default_value {
+                Some(ref default_val) if !default_val.is_empty() => {
+                    format!("DEFAULT {}", default_sql(default_val))
                 }
-            } else {
-                String::new()
+                _ => String::new(),
             };
 
-            format!(
-                "ALTER TABLE {} ADD COLUMN {} {} {} {}",
-                table_name_quoted, name_quoted, data_type, default_clause, nullable
-            )
-            .trim()
-            .to_string()
+            vec![
+                format!(
+                    "ALTER TABLE {} ADD COLUMN {} {} {} {}",
+                    table_name_quoted, name_quoted, data_type, default_clause, nullable
+                )
+                .trim()
+                .to_string(),
+            ]
         }
         AlterType::DropColumn => {
             let col_name = match payload.column_name {
@@ -806,11 +1476,31 @@ async fn alter_table(
             if !is_valid_identifier(&col_name) {
                 return Json(ApiResponse::error("Invalid column name"));
             }
-            let col_name_quoted = quote_identifier(&col_name, &db_type);
-            format!(
-                "ALTER TABLE {} DROP COLUMN {}",
-                table_name_quoted, col_name_quoted
-            )
+            // SQLite before 3.35 has no DROP COLUMN, so the table is rebuilt without it
+            let rebuild = match db_type {
+                DbType::Sqlite => match supports_drop_column(&pool).await {
+                    Ok(supported) => !supported,
+                    Err(e) => return Json(ApiResponse::error(e)),
+                },
+                _ => false,
+            };
+            if rebuild {
+                let change = TableChange::DropColumn(col_name);
+                match plan_rebuild(&pool, &table.name, change).await {
+                    Ok(plan) => {
+                        let statements = plan.statements();
+                        rebuild_plan = Some(plan);
+                        statements
+                    }
+                    Err(e) => return Json(ApiResponse::error(e)),
+                }
+            } else {
+                let col_name_quoted = quote_identifier(&col_name, &db_type);
+                vec![format!(
+                    "ALTER TABLE {} DROP COLUMN {}",
+                    table_name_quoted, col_name_quoted
+                )]
+            }
         }
         AlterType::ModifyColumn => {
             // Get old column name and new column definition
@@ -865,14 +1555,12 @@ async fn alter_table(
                     // We need to handle rename and type change separately
 
                     // Step 1: Rename column if name changed
+                    let mut statements = Vec::new();
                     if old_name != col_def.name {
-                        let rename_sql = format!(
+                        statements.push(format!(
                             "ALTER TABLE {} RENAME COLUMN {} TO {}",
                             table_name_quoted, old_name_quoted, new_name_quoted
-                        );
-                        if let Err(e) = sqlx::query(&rename_sql).execute(&pool).await {
-                            return Json(ApiResponse::error(e.to_string()));
-                        }
+                        ));
                     }
 
                     // Step 2: Change type (always execute to update the column type)
@@ -883,28 +1571,45 @@ async fn alter_table(
                         &old_name_quoted
                     };
 
-                    format!(
+                    statements.push(format!(
                         "ALTER TABLE {} ALTER COLUMN {} TYPE {} USING {}::{}",
                         table_name_quoted,
                         col_name_for_type,
                         data_type,
                         col_name_for_type,
                         data_type
-                    )
+                    ));
+                    statements
                 }
                 DbType::Mysql => {
                     let nullable = if col_def.nullable { "NULL" } else { "NOT NULL" };
-                    format!(
+                    vec![format!(
                         "ALTER TABLE {} CHANGE COLUMN {} {} {} {}",
                         table_name_quoted, old_name_quoted, new_name_quoted, data_type, nullable
-                    )
+                    )]
                 }
                 DbType::Sqlite => {
-                    // SQLite doesn't support modifying columns directly
-                    // For now, return an error
-                    return Json(ApiResponse::error(
-                        "SQLite does not support modifying columns directly. Drop and recreate the column instead.",
-                    ));
+                    // SQLite can't alter a column in place, so the table is rebuilt with the new definition
+                    let column = ColumnSpec {
+                        name: col_def.name,
+                        data_type: data_type.to_string(),
+                        nullable: col_def.nullable,
+                        primary_key: col_def.is_primary_key,
+                        unique: col_def.unique,
+                        default: col_def
+                            .default_value
+                            .filter(|d| !d.is_empty())
+                            .map(|d| default_sql(&d)),
+                    };
+                    let change = TableChange::ModifyColumn { old_name, column };
+                    match plan_rebuild(&pool, &table.name, change).await {
+                        Ok(plan) => {
+                            let statements = plan.statements();
+                            rebuild_plan = Some(plan);
+                            statements
+                        }
+                        Err(e) => return Json(ApiResponse::error(e)),
+                    }
                 }
             }
         }
@@ -932,7 +1637,7 @@ async fn alter_table(
             let old_name_quoted = quote_identifier(&old_name, &db_type);
             let new_name_quoted = quote_identifier(&new_name, &db_type);
 
-            match db_type {
+            vec![match db_type {
                 DbType::Postgres | DbType::Sqlite => {
                     format!(
                         "ALTER TABLE {} RENAME COLUMN {} TO {}",
@@ -947,35 +1652,245 @@ async fn alter_table(
                         table_name_quoted, old_name_quoted, new_name_quoted
                     )
                 }
+            }]
+        }
+        AlterType::AddForeignKey => {
+            let fk = match payload.foreign_key {
+                Some(fk) => fk,
+                None => {
+                    return Json(ApiResponse::error(
+                        "Foreign key definition required for Add Foreign Key",
+                    ));
+                }
+            };
+            if fk.columns.is_empty() || fk.columns.len() != fk.target_columns.len() {
+                return Json(ApiResponse::error(
+                    "A foreign key needs the same number of columns on both sides",
+                ));
+            }
+            let target = match session.table(&fk.target_table) {
+                Ok(t) => t,
+                Err(e) => return Json(ApiResponse::error(e)),
+            };
+            if let Some(col) = fk
+                .columns
+                .iter()
+                .chain(&fk.target_columns)
+                .find(|c| !is_valid_identifier(c))
+            {
+                return Json(ApiResponse::error(format!("Invalid column name: {}", col)));
+            }
+            let (on_delete, on_update) = match (
+                referential_action(&fk.on_delete),
+                referential_action(&fk.on_update),
+            ) {
+                (Some(on_delete), Some(on_update)) => (on_delete, on_update),
+                _ => {
+                    return Json(ApiResponse::error(
+                        "Referential actions must be RESTRICT, CASCADE, SET NULL, SET DEFAULT or NO ACTION",
+                    ));
+                }
+            };
+            if fk.initially_deferred && !fk.deferrable {
+                return Json(ApiResponse::error("INITIALLY DEFERRED requires DEFERRABLE"));
+            }
+            if fk.deferrable && !matches!(db_type, DbType::Postgres) {
+                return Json(ApiResponse::error(
+                    "DEFERRABLE is only supported on PostgreSQL",
+                ));
+            }
+
+            let constraint_name = fk
+                .name
+                .unwrap_or_else(|| format!("{}_{}_fkey", table.name, fk.columns.join("_")));
+            if !is_valid_identifier(&constraint_name) {
+                return Json(ApiResponse::error("Invalid constraint name"));
+            }
+            let quote_all = |cols: &[String]| {
+                cols.iter()
+                    .map(|c| quote_identifier(c, &db_type))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let mut constraint = format!(
+                "CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({}) ON DELETE {} ON UPDATE {}",
+                quote_identifier(&constraint_name, &db_type),
+                quote_all(&fk.columns),
+                target.quoted(&db_type),
+                quote_all(&fk.target_columns),
+                on_delete,
+                on_update
+            );
+            if fk.deferrable {
+                constraint.push_str(" DEFERRABLE");
+                if fk.initially_deferred {
+                    constraint.push_str(" INITIALLY DEFERRED");
+                }
+            }
+
+            // SQLite can't add a constraint to an existing table, so it is rebuilt with it
+            if matches!(db_type, DbType::Sqlite) {
+                // SQLite allows duplicate constraint names, but the name identifies the key to drop
+                match fetch_foreign_keys(&pool, &db_type, &table).await {
+                    Ok(fks) if fks.iter().any(|f| f.constraint_name == constraint_name) => {
+                        return Json(ApiResponse::error(format!(
+                            "Foreign key '{}' already exists",
+                            constraint_name
+                        )));
+                    }
+                    Ok(_) => {}
+                    Err(e) => return Json(ApiResponse::error(e)),
+                }
+                let change = TableChange::AddConstraint(constraint);
+                match plan_rebuild(&pool, &table.name, change).await {
+                    Ok(plan) => {
+                        let statements = plan.statements();
+                        rebuild_plan = Some(plan);
+                        statements
+                    }
+                    Err(e) => return Json(ApiResponse::error(e)),
+                }
+            } else {
+                vec![format!(
+                    "ALTER TABLE {} ADD {}",
+                    table_name_quoted, constraint
+                )]
+            }
+        }
+        AlterType::DropForeignKey => {
+            let constraint_name = match payload.constraint_name {
+                Some(n) => n,
+                None => {
+                    return Json(ApiResponse::error(
+                        "Constraint name required for Drop Foreign Key",
+                    ));
+                }
+            };
+            let fks = match fetch_foreign_keys(&pool, &db_type, &table).await {
+                Ok(fks) => fks,
+                Err(e) => return Json(ApiResponse::error(e)),
+            };
+            let fk = match fks
+                .into_iter()
+                .find(|f| f.constraint_name == constraint_name)
+            {
+                Some(fk) => fk,
+                None => {
+                    return Json(ApiResponse::error(format!(
+                        "Foreign key '{}' not found on table '{}'",
+                        constraint_name, table
+                    )));
+                }
+            };
+
+            let constraint_quoted = quote_identifier(&constraint_name, &db_type);
+            match db_type {
+                DbType::Postgres => vec![format!(
+                    "ALTER TABLE {} DROP CONSTRAINT {}",
+                    table_name_quoted, constraint_quoted
+                )],
+                DbType::Mysql => vec![format!(
+                    "ALTER TABLE {} DROP FOREIGN KEY {}",
+                    table_name_quoted, constraint_quoted
+                )],
+                DbType::Sqlite => {
+                    let change = TableChange::DropForeignKey {
+                        columns: fk.columns,
+                        foreign_table: fk.foreign_table,
+                    };
+                    match plan_rebuild(&pool, &table.name, change).await {
+                        Ok(plan) => {
+                            let statements = plan.statements();
+                            rebuild_plan = Some(plan);
+                            statements
+                        }
+                        Err(e) => return Json(ApiResponse::error(e)),
+                    }
+                }
             }
         }
     };
 
-    match sqlx::query(&sql).execute(&pool).await {
-        Ok(_) => Json(ApiResponse::success(json!({
-            "message": "Table altered successfully",
-            "table": name,
-            "action": format!("{:?}", payload.alter_type)
-        }))),
-        Err(e) => Json(ApiResponse::error(e.to_string())),
+    if params.preview {
+        let impact = match dropped_column {
+            Some(column) => {
+                let dependents = fetch_dependents(&pool, &db_type, &table, Some(&column)).await;
+                let rows = count_dropped_rows(&pool, &db_type, &table, Some(&column)).await;
+                match (dependents, rows) {
+                    (Ok(dependents), Ok(rows)) => Some((dependents, rows)),
+                    (Err(e), _) | (_, Err(e)) => return Json(ApiResponse::error(e)),
+                }
+            }
+            None => None,
+        };
+        return preview_response(statements, impact);
     }
+
+    match rebuild_plan {
+        Some(plan) => {
+            if let Err(e) = run_rebuild(&pool, &plan).await {
+                return Json(ApiResponse::error(e));
+            }
+        }
+        None => {
+            for sql in &statements {
+                if let Err(e) = sqlx::query(sql).execute(&pool).await {
+                    return Json(ApiResponse::error(e.to_string()));
+                }
+            }
+        }
+    }
+    Json(ApiResponse::success(json!({
+        "message": "Table altered successfully",
+        "table": name,
+        "action": format!("{:?}", payload.alter_type)
+    })))
+}
+
+/// The response to a previewed change: the statements it would run and, for a drop, the objects
+/// depending on what goes and the rows discarded with it
+fn preview_response(
+    statements: Vec<String>,
+    impact: Option<(Vec<DependentObject>, i64)>,
+) -> Json<ApiResponse<Value>> {
+    let (dependents, rows) = match impact {
+        Some((dependents, rows)) => (dependents, Some(rows)),
+        None => (Vec::new(), None),
+    };
+    Json(ApiResponse::success(json!({
+        "preview": true,
+        "statements": statements,
+        "dependents": dependents,
+        "rows": rows
+    })))
 }
 
 /// DELETE /api/schema/table/{name} - Drop table
 async fn drop_table(
     AuthSession(session): AuthSession,
     Path(name): Path<String>,
+    Query(params): Query<SchemaChangeParams>,
 ) -> Json<ApiResponse<Value>> {
-    if !is_valid_identifier(&name) {
-        return Json(ApiResponse::error("Invalid table name"));
-    }
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
 
     let pool = session.pool;
     let db_type = session.db_type;
 
-    let table_name_quoted = quote_identifier(&name, &db_type);
+    let table_name_quoted = table.quoted(&db_type);
     let sql = format!("DROP TABLE {}", table_name_quoted);
 
+    if params.preview {
+        let dependents = fetch_dependents(&pool, &db_type, &table, None).await;
+        let rows = count_dropped_rows(&pool, &db_type, &table, None).await;
+        return match (dependents, rows) {
+            (Ok(dependents), Ok(rows)) => preview_response(vec![sql], Some((dependents, rows))),
+            (Err(e), _) | (_, Err(e)) => Json(ApiResponse::error(e)),
+        };
+    }
+
     match sqlx::query(&sql).execute(&pool).await {
         Ok(_) => Json(ApiResponse::success(json!({
             "message": "Table dropped successfully",
@@ -984,3 +1899,171 @@ async fn drop_table(
         Err(e) => Json(ApiResponse::error(e.to_string())),
     }
 }
+
+/// POST /api/schema/table/{name}/indexes - Create an index
+async fn create_index(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Json(payload): Json<CreateIndexRequest>,
+) -> Json<ApiResponse<Value>> {
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let db_type = &session.db_type;
+
+    if payload.columns.is_empty() {
+        return Json(ApiResponse::error("An index needs at least one column"));
+    }
+    let mut columns = Vec::new();
+    for col in &payload.columns {
+        if !is_valid_identifier(&col.name) {
+            return Json(ApiResponse::error(format!(
+                "Invalid column name: {}",
+                col.name
+            )));
+        }
+        let order = match col.order.as_deref().map(str::to_uppercase).as_deref() {
+            None | Some("ASC") => "",
+            Some("DESC") => " DESC",
+            Some(other) => {
+                return Json(ApiResponse::error(format!(
+                    "Invalid sort order for column {}: {}",
+                    col.name, other
+                )));
+            }
+        };
+        columns.push(format!("{}{}", quote_identifier(&col.name, db_type), order));
+    }
+
+    // default to the Postgres naming convention, e.g. users_email_key
+    let index_name = payload.name.clone().unwrap_or_else(|| {
+        let column_names: Vec<&str> = payload.columns.iter().map(|c| c.name.as_str()).collect();
+        let suffix = if payload.unique { "key" } else { "idx" };
+        format!("{}_{}_{}", table.name, column_names.join("_"), suffix)
+    });
+    if !is_valid_identifier(&index_name) {
+        return Json(ApiResponse::error(format!(
+            "Invalid index name: {}",
+            index_name
+        )));
+    }
+
+    // Validate index method
+    let method = match (&payload.method, db_type) {
+        (None, _) => None,
+        (Some(m), DbType::Postgres) => match m.to_lowercase().as_str() {
+            "btree" => Some("btree"),
+            "hash" => Some("hash"),
+            "gin" => Some("gin"),
+            "gist" => Some("gist"),
+            "spgist" => Some("spgist"),
+            "brin" => Some("brin"),
+            _ => return Json(ApiResponse::error(format!("Unknown index method: {}", m))),
+        },
+        (Some(_), _) => {
+            return Json(ApiResponse::error(
+                "Index methods are only supported on PostgreSQL",
+            ));
+        }
+    };
+
+    // Validate partial index predicate, raw SQL that must hold a single expression
+    let predicate = match payload.where_clause.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(_) if matches!(db_type, DbType::Mysql) => {
+            return Json(ApiResponse::error("MySQL does not support partial indexes"));
+        }
+        Some(p) if !is_single_expression(p) => {
+            return Json(ApiResponse::error(
+                "The WHERE clause must be a single expression",
+            ));
+        }
+        Some(p) => Some(p),
+    };
+
+    let unique = if payload.unique { "UNIQUE " } else { "" };
+    let index_quoted = quote_identifier(&index_name, db_type);
+    let table_quoted = table.quoted(db_type);
+    let columns = columns.join(", ");
+
+    // Generate DDL based on DB type
+    let mut sql = match db_type {
+        DbType::Postgres => {
+            let using = method.map(|m| format!(" USING {}", m)).unwrap_or_default();
+            format!(
+                "CREATE {}INDEX {} ON {}{} ({})",
+                unique, index_quoted, table_quoted, using, columns
+            )
+        }
+        DbType::Mysql | DbType::Sqlite => format!(
+            "CREATE {}INDEX {} ON {} ({})",
+            unique, index_quoted, table_quoted, columns
+        ),
+    };
+    if let Some(predicate) = predicate {
+        sql.push_str(&format!(" WHERE {}", predicate));
+    }
+
+    match sqlx::query(&sql).execute(&session.pool).await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "Index created successfully",
+            "table": name,
+            "index": index_name
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// DELETE /api/schema/table/{name}/indexes/{index} - Drop an index
+async fn drop_index(
+    AuthSession(session): AuthSession,
+    Path((name, index)): Path<(String, String)>,
+) -> Json<ApiResponse<Value>> {
+    let table = match session.table(&name) {
+        Ok(t) => t,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let db_type = &session.db_type;
+
+    // the index has to belong to the table, and primary keys are changed with the table instead
+    let indexes = match fetch_indexes(&session.pool, db_type, &table).await {
+        Ok(indexes) => indexes,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    match indexes.iter().find(|idx| idx.name == index) {
+        None => {
+            return Json(ApiResponse::error(format!(
+                "Index '{}' not found on table '{}'",
+                index, table
+            )));
+        }
+        Some(idx) if idx.is_primary => {
+            return Json(ApiResponse::error(
+                "The primary key index cannot be dropped",
+            ));
+        }
+        Some(_) => {}
+    }
+
+    let index_quoted = quote_identifier(&index, db_type);
+    let sql = match db_type {
+        // indexes live in their table's schema
+        DbType::Postgres => format!(
+            "DROP INDEX {}.{}",
+            quote_identifier(&table.schema, db_type),
+            index_quoted
+        ),
+        DbType::Mysql => format!("DROP INDEX {} ON {}", index_quoted, table.quoted(db_type)),
+        DbType::Sqlite => format!("DROP INDEX {}", index_quoted),
+    };
+
+    match sqlx::query(&sql).execute(&session.pool).await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "Index dropped successfully",
+            "table": name,
+            "index": index
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
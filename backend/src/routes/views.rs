@@ -0,0 +1,379 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query},
+    routing::{get, post},
+};
+use serde_json::{Value, json};
+use sqlx::{AnyPool, Row};
+
+use crate::{
+    auth::AuthSession,
+    lexer::{Token, TokenKind, tokenize},
+    models::{
+        ApiResponse, CreateViewRequest, DbType, RefreshViewParams, ReplaceViewRequest, ViewInfo,
+    },
+    sql_utils::TableRef,
+    state::SessionStore,
+};
+
+pub fn routes(session_store: SessionStore) -> Router {
+    Router::new()
+        .route("/", post(create_view))
+        .route("/{name}", get(get_view).put(replace_view).delete(drop_view))
+        .route("/{name}/refresh", post(refresh_view))
+        .with_state(session_store)
+}
+
+/// GET /api/schema/views/{name} - Get a view's definition
+async fn get_view(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+) -> Json<ApiResponse<Value>> {
+    let view = match session.table(&name) {
+        Ok(v) => v,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    match fetch_view(&session.pool, &session.db_type, &view).await {
+        Ok(Some(info)) => Json(ApiResponse::success(json!({ "view": info }))),
+        Ok(None) => Json(ApiResponse::error(format!("View '{}' not found", view))),
+        Err(e) => Json(ApiResponse::error(e)),
+    }
+}
+
+/// Looks up a view and its query, None if there is no view of that name
+pub async fn fetch_view(
+    pool: &AnyPool,
+    db_type: &DbType,
+    view: &TableRef,
+) -> Result<Option<ViewInfo>, String> {
+    let (materialized, definition) = match db_type {
+        DbType::Postgres => {
+            let row = sqlx::query(
+                "SELECT c.relkind::text AS kind, pg_get_viewdef(c.oid, true) AS definition
+                 FROM pg_class c
+                 JOIN pg_namespace n ON n.oid = c.relnamespace
+                 WHERE n.nspname = $1 AND c.relname = $2 AND c.relkind IN ('v', 'm')",
+            )
+            .bind(&view.schema)
+            .bind(&view.name)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            let Some(row) = row else {
+                return Ok(None);
+            };
+            let kind: String = row.try_get("kind").unwrap_or_default();
+            let definition: String = row.try_get("definition").unwrap_or_default();
+            (kind == "m", definition)
+        }
+        DbType::Mysql => {
+            let sql = format!(
+                "SELECT CAST(VIEW_DEFINITION AS CHAR) as definition
+                 FROM information_schema.VIEWS
+                 WHERE TABLE_SCHEMA = '{}' AND TABLE_NAME = '{}'",
+                view.schema.replace("'", "''"),
+                view.name.replace("'", "''")
+            );
+            let definition: Option<String> = sqlx::query_scalar(&sql)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+            let Some(definition) = definition else {
+                return Ok(None);
+            };
+            (false, definition)
+        }
+        DbType::Sqlite => {
+            // SQLite keeps the CREATE VIEW statement as written
+            let sql: Option<String> = sqlx::query_scalar(
+                "SELECT sql FROM sqlite_schema WHERE type = 'view' AND name = ?",
+            )
+            .bind(&view.name)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            let Some(sql) = sql else {
+                return Ok(None);
+            };
+            (false, view_query(&sql)?.to_string())
+        }
+    };
+
+    Ok(Some(ViewInfo {
+        schema: view.schema.clone(),
+        name: view.name.clone(),
+        materialized,
+        definition: definition.trim().trim_end_matches(';').to_string(),
+    }))
+}
+
+/// The query of a CREATE VIEW statement, which follows its first top-level AS
+fn view_query(create_view: &str) -> Result<&str, String> {
+    let tokens = tokenize(create_view)?;
+    let as_keyword = tokens
+        .iter()
+        .find(|t| t.is_keyword("AS"))
+        .ok_or("View definition has no query")?;
+    Ok(create_view[as_keyword.end..].trim())
+}
+
+/// Trims a requested view query, which has to be a single statement: a `;` may end it, or
+/// appear in literals, quoted names and comments
+fn view_definition(raw: &str) -> Result<&str, String> {
+    let tokens = tokenize(raw)?;
+    let separator = |t: &Token| t.kind == TokenKind::Other && t.text == ";";
+    let tokens = match tokens.split_last() {
+        Some((last, rest)) if separator(last) => rest,
+        _ => &tokens[..],
+    };
+    let (Some(first), Some(last)) = (tokens.first(), tokens.last()) else {
+        return Err("View definition required".to_string());
+    };
+    if tokens.iter().any(separator) {
+        return Err("View definition must be a single statement".to_string());
+    }
+    Ok(&raw[first.start..last.end])
+}
+
+/// A query that fails if the view's definition does not compile. SQLite only resolves the
+/// tables and columns a view uses when it is queried, where other databases check on CREATE.
+fn validate_view(db_type: &DbType, quoted: &str) -> Option<String> {
+    matches!(db_type, DbType::Sqlite).then(|| format!("SELECT * FROM {} LIMIT 0", quoted))
+}
+
+/// POST /api/schema/views - Create a view
+async fn create_view(
+    AuthSession(session): AuthSession,
+    Json(payload): Json<CreateViewRequest>,
+) -> Json<ApiResponse<Value>> {
+    let view = match session.table(&payload.name) {
+        Ok(v) => v,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let definition = match view_definition(&payload.definition) {
+        Ok(d) => d,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    if payload.materialized && !matches!(session.db_type, DbType::Postgres) {
+        return Json(ApiResponse::error(
+            "Materialized views are only supported on PostgreSQL",
+        ));
+    }
+
+    let quoted = view.quoted(&session.db_type);
+    let mut statements = vec![format!(
+        "CREATE {}VIEW {} AS {}",
+        if payload.materialized {
+            "MATERIALIZED "
+        } else {
+            ""
+        },
+        quoted,
+        definition
+    )];
+    statements.extend(validate_view(&session.db_type, &quoted));
+
+    let mut tx = match session.pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => return Json(ApiResponse::error(e.to_string())),
+    };
+    for sql in &statements {
+        if let Err(e) = sqlx::query(sql).execute(&mut *tx).await {
+            return Json(ApiResponse::error(e.to_string()));
+        }
+    }
+    match tx.commit().await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "View created successfully",
+            "view": payload.name
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// PUT /api/schema/views/{name} - Replace a view's query.
+/// PostgreSQL and MySQL replace plain views in place, which on MySQL is a single statement
+/// since its DDL commits implicitly. A materialized view or a SQLite view is dropped and
+/// recreated in one transaction, bringing back its indexes or triggers.
+async fn replace_view(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Json(payload): Json<ReplaceViewRequest>,
+) -> Json<ApiResponse<Value>> {
+    let view = match session.table(&name) {
+        Ok(v) => v,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let definition = match view_definition(&payload.definition) {
+        Ok(d) => d,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let pool = &session.pool;
+    let db_type = &session.db_type;
+
+    let existing = match fetch_view(pool, db_type, &view).await {
+        Ok(Some(v)) => v,
+        Ok(None) => return Json(ApiResponse::error(format!("View '{}' not found", view))),
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let quoted = view.quoted(db_type);
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => return Json(ApiResponse::error(e.to_string())),
+    };
+    // PostgreSQL only lets CREATE OR REPLACE append columns, which a failing replace reports
+    let mut statements = match db_type {
+        DbType::Postgres if existing.materialized => vec![
+            format!("DROP MATERIALIZED VIEW {}", quoted),
+            format!("CREATE MATERIALIZED VIEW {} AS {}", quoted, definition),
+        ],
+        DbType::Postgres | DbType::Mysql => {
+            vec![format!(
+                "CREATE OR REPLACE VIEW {} AS {}",
+                quoted, definition
+            )]
+        }
+        DbType::Sqlite => vec![
+            format!("DROP VIEW {}", quoted),
+            format!("CREATE VIEW {} AS {}", quoted, definition),
+        ],
+    };
+    if statements.len() > 1 {
+        let dependents = match db_type {
+            DbType::Postgres => {
+                sqlx::query_scalar(
+                    "SELECT indexdef FROM pg_indexes WHERE schemaname = $1 AND tablename = $2",
+                )
+                .bind(&view.schema)
+                .bind(&view.name)
+                .fetch_all(&mut *tx)
+                .await
+            }
+            _ => {
+                sqlx::query_scalar(
+                    "SELECT sql FROM sqlite_schema
+                     WHERE type = 'trigger' AND tbl_name = ? AND sql IS NOT NULL
+                     ORDER BY rowid",
+                )
+                .bind(&view.name)
+                .fetch_all(&mut *tx)
+                .await
+            }
+        };
+        match dependents {
+            Ok(dependents) => statements.extend(dependents),
+            Err(e) => return Json(ApiResponse::error(e.to_string())),
+        }
+    }
+
+    statements.extend(validate_view(db_type, &quoted));
+
+    for sql in &statements {
+        if let Err(e) = sqlx::query(sql).execute(&mut *tx).await {
+            return Json(ApiResponse::error(e.to_string()));
+        }
+    }
+    match tx.commit().await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "View replaced successfully",
+            "view": name
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// DELETE /api/schema/views/{name} - Drop a view
+async fn drop_view(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+) -> Json<ApiResponse<Value>> {
+    let view = match session.table(&name) {
+        Ok(v) => v,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    let existing = match fetch_view(&session.pool, &session.db_type, &view).await {
+        Ok(Some(v)) => v,
+        Ok(None) => return Json(ApiResponse::error(format!("View '{}' not found", view))),
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+
+    let sql = format!(
+        "DROP {}VIEW {}",
+        if existing.materialized {
+            "MATERIALIZED "
+        } else {
+            ""
+        },
+        view.quoted(&session.db_type)
+    );
+
+    match sqlx::query(&sql).execute(&session.pool).await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "View dropped successfully",
+            "view": name
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+/// POST /api/schema/views/{name}/refresh?concurrently= - Refresh a PostgreSQL materialized view
+async fn refresh_view(
+    AuthSession(session): AuthSession,
+    Path(name): Path<String>,
+    Query(params): Query<RefreshViewParams>,
+) -> Json<ApiResponse<Value>> {
+    if !matches!(session.db_type, DbType::Postgres) {
+        return Json(ApiResponse::error(
+            "Materialized views are only supported on PostgreSQL",
+        ));
+    }
+    let view = match session.table(&name) {
+        Ok(v) => v,
+        Err(e) => return Json(ApiResponse::error(e)),
+    };
+    match fetch_view(&session.pool, &session.db_type, &view).await {
+        Ok(Some(v)) if v.materialized => {}
+        Ok(_) => {
+            return Json(ApiResponse::error(format!(
+                "Materialized view '{}' not found",
+                view
+            )));
+        }
+        Err(e) => return Json(ApiResponse::error(e)),
+    }
+
+    let sql = format!(
+        "REFRESH MATERIALIZED VIEW {}{}",
+        if params.concurrently {
+            "CONCURRENTLY "
+        } else {
+            ""
+        },
+        view.quoted(&session.db_type)
+    );
+
+    match sqlx::query(&sql).execute(&session.pool).await {
+        Ok(_) => Json(ApiResponse::success(json!({
+            "message": "View refreshed successfully",
+            "view": name
+        }))),
+        Err(e) => Json(ApiResponse::error(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_definition() {
+        assert_eq!(
+            view_definition(" SELECT 'a;b' AS \"c;d\" -- e;f\n;").unwrap(),
+            "SELECT 'a;b' AS \"c;d\""
+        );
+        assert_eq!(view_definition("SELECT 1 /* ; */").unwrap(), "SELECT 1");
+        assert!(view_definition("SELECT 1; DROP TABLE t").is_err());
+        assert!(view_definition(" ; ").is_err());
+    }
+}
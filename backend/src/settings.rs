@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+
+use sqlx::{AnyPool, Row};
+
+use crate::{
+    models::{ConnectRequest, DbType, SettingInfo},
+    sql_utils::escape_string_literal,
+};
+
+/// PRAGMAs listed on SQLite: name, whether a connection can change it, unit and description
+const SQLITE_PRAGMAS: &[(&str, bool, Option<&str>, &str)] = &[
+    (
+        "application_id",
+        false,
+        None,
+        "Application identifier stored in the file header",
+    ),
+    (
+        "auto_vacuum",
+        false,
+        None,
+        "Free page reclaiming: 0 none, 1 full, 2 incremental",
+    ),
+    (
+        "automatic_index",
+        true,
+        None,
+        "Build temporary indexes for queries that lack one",
+    ),
+    (
+        "busy_timeout",
+        true,
+        Some("ms"),
+        "Time to wait for a locked database",
+    ),
+    (
+        "cache_size",
+        true,
+        None,
+        "Page cache size, in pages when positive and KiB when negative",
+    ),
+    ("encoding", false, None, "Text encoding of the database"),
+    (
+        "foreign_keys",
+        true,
+        None,
+        "Enforce foreign key constraints",
+    ),
+    ("freelist_count", false, None, "Unused pages in the file"),
+    (
+        "journal_mode",
+        true,
+        None,
+        "Rollback journal mode; WAL persists in the file",
+    ),
+    (
+        "journal_size_limit",
+        true,
+        Some("bytes"),
+        "Size a journal is truncated to after a transaction",
+    ),
+    ("mmap_size", true, Some("bytes"), "Memory-mapped I/O limit"),
+    ("page_count", false, None, "Pages in the file"),
+    ("page_size", false, Some("bytes"), "Size of a database page"),
+    ("query_only", true, None, "Reject changes to the database"),
+    (
+        "recursive_triggers",
+        true,
+        None,
+        "Let triggers fire other triggers recursively",
+    ),
+    (
+        "secure_delete",
+        true,
+        None,
+        "Overwrite deleted content with zeros",
+    ),
+    (
+        "synchronous",
+        true,
+        None,
+        "Disk sync level: 0 off, 1 normal, 2 full, 3 extra",
+    ),
+    (
+        "temp_store",
+        true,
+        None,
+        "Temporary storage: 0 default, 1 file, 2 memory",
+    ),
+    (
+        "user_version",
+        false,
+        None,
+        "User version number stored in the file header",
+    ),
+    (
+        "wal_autocheckpoint",
+        true,
+        Some("pages"),
+        "WAL size that triggers a checkpoint",
+    ),
+];
+
+/// Setting names are plain words, dotted for PostgreSQL's custom settings
+pub fn is_valid_setting_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// A setting value as SQL. MySQL rejects quoted numbers for numeric variables, where
+/// PostgreSQL and SQLite parse any value from a string.
+fn value_sql(value: &str, db_type: &DbType) -> String {
+    match db_type {
+        DbType::Mysql if value.parse::<f64>().is_ok() => value.to_string(),
+        _ => escape_string_literal(value, db_type),
+    }
+}
+
+/// The statement changing a setting on one connection
+pub fn session_statement(db_type: &DbType, name: &str, value: &str) -> String {
+    let value = value_sql(value, db_type);
+    match db_type {
+        DbType::Postgres => format!("SET {} TO {}", name, value),
+        DbType::Mysql => format!("SET SESSION {} = {}", name, value),
+        DbType::Sqlite => format!("PRAGMA {} = {}", name, value),
+    }
+}
+
+/// The statements changing a setting server-wide. PostgreSQL writes it to
+/// postgresql.auto.conf and reloads; a MySQL change lasts until the server restarts.
+pub fn global_statements(db_type: &DbType, name: &str, value: &str) -> Result<Vec<String>, String> {
+    let value = value_sql(value, db_type);
+    match db_type {
+        DbType::Postgres => Ok(vec![
+            format!("ALTER SYSTEM SET {} TO {}", name, value),
+            "SELECT pg_reload_conf()".to_string(),
+        ]),
+        DbType::Mysql => Ok(vec![format!("SET GLOBAL {} = {}", name, value)]),
+        DbType::Sqlite => {
+            Err("Global settings are only supported on PostgreSQL and MySQL".to_string())
+        }
+    }
+}
+
+/// The statements a session's pools run on each new connection to apply its settings, empty
+/// when it has none
+pub fn session_init_sql(request: &ConnectRequest) -> Result<String, String> {
+    let mut statements = Vec::with_capacity(request.settings.len());
+    for (name, value) in &request.settings {
+        if !is_valid_setting_name(name) {
+            return Err(format!("Invalid setting name: '{}'", name));
+        }
+        statements.push(session_statement(&request.db_type, name, value));
+    }
+    Ok(statements.join(";\n"))
+}
+
+/// Lists the server's settings with their values on the pool's connections, sorted by name
+pub async fn fetch_settings(pool: &AnyPool, db_type: &DbType) -> Result<Vec<SettingInfo>, String> {
+    match db_type {
+        DbType::Postgres => {
+            let sql = "
+                SELECT
+                    name::text AS name,
+                    setting AS value,
+                    unit,
+                    category,
+                    short_desc AS description,
+                    context,
+                    pending_restart
+                FROM pg_settings
+                ORDER BY name
+            ";
+            let rows = sqlx::query(sql)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            // internal settings are fixed when the server is built or its cluster created,
+            // backend ones when a connection starts and postmaster ones when the server does
+            Ok(rows
+                .into_iter()
+                .map(|row| {
+                    let context: String = row.try_get("context").unwrap_or_default();
+                    SettingInfo {
+                        name: row.try_get("name").unwrap_or_default(),
+                        value: row.try_get("value").unwrap_or(None),
+                        unit: row.try_get("unit").unwrap_or(None),
+                        category: row.try_get("category").unwrap_or(None),
+                        description: row.try_get("description").unwrap_or(None),
+                        session_editable: matches!(context.as_str(), "user" | "superuser"),
+                        global_editable: context != "internal",
+                        requires_restart: Some(context == "postmaster"),
+                        pending_restart: row.try_get("pending_restart").unwrap_or(false),
+                    }
+                })
+                .collect())
+        }
+        DbType::Mysql => {
+            let rows = sqlx::query("SHOW VARIABLES")
+                .fetch_all(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            // scopes and descriptions are only reported from MySQL 8.0.32; before, both scopes
+            // are offered and the server rejects what it can't change
+            let metadata: HashMap<String, (String, Option<String>)> = sqlx::query(
+                "
+                    SELECT
+                        CAST(VARIABLE_NAME AS CHAR) AS name,
+                        CAST(VARIABLE_SCOPE AS CHAR) AS scope,
+                        CAST(DOCUMENTATION AS CHAR) AS description
+                    FROM performance_schema.variables_metadata
+                ",
+            )
+            .fetch_all(pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| {
+                        (
+                            row.try_get("name").unwrap_or_default(),
+                            (
+                                row.try_get("scope").unwrap_or_default(),
+                                row.try_get("description").unwrap_or(None),
+                            ),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+            let mut settings: Vec<SettingInfo> = rows
+                .into_iter()
+                .map(|row| {
+                    let name: String = row.try_get(0).unwrap_or_default();
+                    let (scope, description) = metadata.get(&name).cloned().unwrap_or_default();
+                    SettingInfo {
+                        value: row.try_get(1).unwrap_or(None),
+                        unit: None,
+                        category: None,
+                        description,
+                        session_editable: scope != "GLOBAL",
+                        global_editable: scope != "SESSION_ONLY",
+                        requires_restart: None,
+                        pending_restart: false,
+                        name,
+                    }
+                })
+                .collect();
+            settings.sort_by(|a, b| a.name.cmp(&b.name));
+            Ok(settings)
+        }
+        DbType::Sqlite => {
+            let mut settings = Vec::with_capacity(SQLITE_PRAGMAS.len());
+            for (name, editable, unit, description) in SQLITE_PRAGMAS {
+                let row = sqlx::query(&format!("PRAGMA {}", name))
+                    .fetch_optional(pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let value = row.map(|row| {
+                    row.try_get::<String, _>(0).unwrap_or_else(|_| {
+                        row.try_get::<i64, _>(0)
+                            .map(|v| v.to_string())
+                            .unwrap_or_default()
+                    })
+                });
+                settings.push(SettingInfo {
+                    name: name.to_string(),
+                    value,
+                    unit: unit.map(|u| u.to_string()),
+                    category: None,
+                    description: Some(description.to_string()),
+                    session_editable: *editable,
+                    global_editable: false,
+                    requires_restart: Some(false),
+                    pending_restart: false,
+                });
+            }
+            Ok(settings)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::any::AnyPoolOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_setting_statements() {
+        assert!(is_valid_setting_name("statement_timeout"));
+        assert!(is_valid_setting_name("myapp.tenant"));
+        assert!(!is_valid_setting_name("x; DROP TABLE users"));
+        assert!(!is_valid_setting_name("1x"));
+
+        assert_eq!(
+            session_statement(&DbType::Postgres, "statement_timeout", "5s"),
+            "SET statement_timeout TO '5s'"
+        );
+        assert_eq!(
+            session_statement(&DbType::Mysql, "sort_buffer_size", "262144"),
+            "SET SESSION sort_buffer_size = 262144"
+        );
+        assert_eq!(
+            session_statement(&DbType::Mysql, "sql_mode", "it's"),
+            "SET SESSION sql_mode = 'it''s'"
+        );
+        assert_eq!(
+            global_statements(&DbType::Postgres, "work_mem", "64MB").unwrap(),
+            vec![
+                "ALTER SYSTEM SET work_mem TO '64MB'",
+                "SELECT pg_reload_conf()"
+            ]
+        );
+        assert!(global_statements(&DbType::Sqlite, "cache_size", "10").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_settings() {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        for (name, value) in [("foreign_keys", "on"), ("cache_size", "-4000")] {
+            let sql = session_statement(&DbType::Sqlite, name, value);
+            sqlx::raw_sql(&sql).execute(&pool).await.unwrap();
+        }
+
+        let settings = fetch_settings(&pool, &DbType::Sqlite).await.unwrap();
+        let value = |name: &str| {
+            let setting = settings.iter().find(|s| s.name == name).unwrap();
+            setting.value.clone().unwrap()
+        };
+        assert_eq!(settings.len(), SQLITE_PRAGMAS.len());
+        assert_eq!(value("foreign_keys"), "1");
+        assert_eq!(value("cache_size"), "-4000");
+        assert_eq!(value("journal_mode"), "memory");
+        assert_eq!(value("encoding"), "UTF-8");
+        assert!(!settings[0].session_editable);
+    }
+}
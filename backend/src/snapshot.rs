@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use serde_json::{Value, json};
+use sqlx::AnyPool;
+
+use crate::{
+    models::{DbType, ForeignKeyInfo, SchemaSnapshot, TableSnapshot},
+    routes::schema::{
+        fetch_schema_columns, fetch_schema_foreign_keys, fetch_schema_indexes, fetch_tables,
+    },
+};
+
+/// Loads every table and view of a schema with one catalog query per kind of object, however
+/// many tables there are
+pub async fn load_snapshot(
+    pool: &AnyPool,
+    db_type: &DbType,
+    schema: &str,
+) -> Result<SchemaSnapshot, String> {
+    let tables = fetch_tables(pool, db_type, schema).await?;
+    let mut columns = by_table(fetch_schema_columns(pool, db_type, schema, None).await?);
+    let mut indexes = by_table(fetch_schema_indexes(pool, db_type, schema, None).await?);
+    let mut foreign_keys = by_table(fetch_schema_foreign_keys(pool, db_type, schema, None).await?);
+
+    let tables = tables
+        .into_iter()
+        .map(|table| {
+            let columns = columns.remove(&table.name).unwrap_or_default();
+            let primary_key = columns
+                .iter()
+                .filter(|c| c.is_primary_key)
+                .map(|c| c.name.clone())
+                .collect();
+            TableSnapshot {
+                indexes: indexes.remove(&table.name).unwrap_or_default(),
+                foreign_keys: foreign_keys.remove(&table.name).unwrap_or_default(),
+                name: table.name,
+                table_type: table.table_type,
+                columns,
+                primary_key,
+            }
+        })
+        .collect();
+
+    Ok(SchemaSnapshot {
+        schema: schema.to_string(),
+        tables,
+    })
+}
+
+fn by_table<T>(items: Vec<(String, T)>) -> HashMap<String, Vec<T>> {
+    let mut grouped: HashMap<String, Vec<T>> = HashMap::new();
+    for (table, item) in items {
+        grouped.entry(table).or_default().push(item);
+    }
+    grouped
+}
+
+/// How a diagram names the table a foreign key refers to: by name within the snapshot's schema,
+/// qualified with its schema otherwise
+fn target_name(snapshot: &SchemaSnapshot, fk: &ForeignKeyInfo) -> String {
+    if fk.foreign_schema == snapshot.schema {
+        fk.foreign_table.clone()
+    } else {
+        format!("{}.{}", fk.foreign_schema, fk.foreign_table)
+    }
+}
+
+/// Whether a table's foreign key columns may all be left empty, making the reference optional
+fn is_optional(table: &TableSnapshot, fk: &ForeignKeyInfo) -> bool {
+    fk.columns.iter().any(|name| {
+        table
+            .columns
+            .iter()
+            .find(|c| &c.name == name)
+            .is_none_or(|c| c.nullable)
+    })
+}
+
+/// A Mermaid erDiagram with an entity per table and a relationship per foreign key
+pub fn mermaid(snapshot: &SchemaSnapshot) -> String {
+    // Mermaid names and types are single words
+    let word = |s: &str| -> String {
+        s.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+
+    let mut out = String::from("erDiagram\n");
+    for table in &snapshot.tables {
+        out.push_str(&format!("    {} {{\n", word(&table.name)));
+        for column in &table.columns {
+            let is_foreign = table
+                .foreign_keys
+                .iter()
+                .any(|fk| fk.columns.contains(&column.name));
+            let keys = match (column.is_primary_key, is_foreign) {
+                (true, true) => " PK, FK",
+                (true, false) => " PK",
+                (false, true) => " FK",
+                (false, false) => "",
+            };
+            out.push_str(&format!(
+                "        {} {}{}\n",
+                word(&column.data_type),
+                word(&column.name),
+                keys
+            ));
+        }
+        out.push_str("    }\n");
+    }
+    for table in &snapshot.tables {
+        for fk in &table.foreign_keys {
+            out.push_str(&format!(
+                "    {} }}o--{} {} : \"{}\"\n",
+                word(&table.name),
+                if is_optional(table, fk) { "o|" } else { "||" },
+                word(&target_name(snapshot, fk)),
+                fk.constraint_name.replace('"', "'")
+            ));
+        }
+    }
+    out
+}
+
+/// A Graphviz digraph with a record-like node per table, listing its columns, and an edge per
+/// foreign key, drawn between the columns of single-column keys
+pub fn dot(snapshot: &SchemaSnapshot) -> String {
+    let id = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let html = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+
+    let mut out = format!(
+        "digraph {} {{\n    rankdir=LR;\n    node [shape=plaintext];\n",
+        id(&snapshot.schema)
+    );
+    for table in &snapshot.tables {
+        let mut rows = vec![format!(
+            "<tr><td bgcolor=\"lightgrey\"><b>{}</b></td></tr>",
+            html(&table.name)
+        )];
+        for column in &table.columns {
+            rows.push(format!(
+                "<tr><td port=\"{}\" align=\"left\">{}: {}{}</td></tr>",
+                html(&column.name),
+                html(&column.name),
+                html(&column.data_type),
+                if column.is_primary_key { " (PK)" } else { "" }
+            ));
+        }
+        out.push_str(&format!(
+            "    {} [label=<<table border=\"0\" cellborder=\"1\" cellspacing=\"0\">{}</table>>];\n",
+            id(&table.name),
+            rows.concat()
+        ));
+    }
+    for table in &snapshot.tables {
+        for fk in &table.foreign_keys {
+            let target = target_name(snapshot, fk);
+            let (from, to) = match (fk.columns.as_slice(), fk.foreign_columns.as_slice()) {
+                ([column], [foreign_column]) => (
+                    format!("{}:{}", id(&table.name), id(column)),
+                    format!("{}:{}", id(&target), id(foreign_column)),
+                ),
+                _ => (id(&table.name), id(&target)),
+            };
+            out.push_str(&format!(
+                "    {} -> {} [label={}];\n",
+                from,
+                to,
+                id(&fk.constraint_name)
+            ));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// A JSON graph with a node per table, identified as schema.name, and an edge per foreign key
+pub fn graph(snapshot: &SchemaSnapshot) -> Value {
+    let node_id = |schema: &str, name: &str| format!("{}.{}", schema, name);
+
+    let nodes: Vec<Value> = snapshot
+        .tables
+        .iter()
+        .map(|table| {
+            json!({
+                "id": node_id(&snapshot.schema, &table.name),
+                "schema": snapshot.schema,
+                "name": table.name,
+                "table_type": table.table_type,
+                "columns": table.columns,
+                "primary_key": table.primary_key,
+            })
+        })
+        .collect();
+    let edges: Vec<Value> = snapshot
+        .tables
+        .iter()
+        .flat_map(|table| {
+            table.foreign_keys.iter().map(|fk| {
+                json!({
+                    "source": node_id(&snapshot.schema, &table.name),
+                    "target": node_id(&fk.foreign_schema, &fk.foreign_table),
+                    "constraint_name": fk.constraint_name,
+                    "columns": fk.columns,
+                    "foreign_columns": fk.foreign_columns,
+                    "optional": is_optional(table, fk),
+                })
+            })
+        })
+        .collect();
+
+    json!({ "nodes": nodes, "edges": edges })
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::any::AnyPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_snapshot_renderings() {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let setup = "
+            CREATE TABLE teams (org INTEGER, id INTEGER, name TEXT UNIQUE, PRIMARY KEY (org, id));
+            CREATE TABLE users (id INTEGER PRIMARY KEY, \"e-mail\" VARCHAR(255));
+            CREATE TABLE members (
+                user_id INTEGER NOT NULL CONSTRAINT fk_user REFERENCES users (id),
+                org INTEGER,
+                team INTEGER,
+                FOREIGN KEY (org, team) REFERENCES teams (org, id)
+            );
+            CREATE INDEX members_team ON members (org, team);
+            CREATE INDEX users_email ON users (lower(\"e-mail\") DESC) WHERE id > 0;
+            CREATE VIEW member_names AS SELECT user_id FROM members;
+        ";
+        sqlx::raw_sql(setup).execute(&pool).await.unwrap();
+
+        let snapshot = load_snapshot(&pool, &DbType::Sqlite, "main").await.unwrap();
+        let names: Vec<&str> = snapshot.tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["member_names", "members", "teams", "users"]);
+
+        let members = &snapshot.tables[1];
+        assert_eq!(members.columns.len(), 3);
+        assert_eq!(members.foreign_keys.len(), 2);
+        assert_eq!(members.foreign_keys[0].columns, vec!["org", "team"]);
+        assert_eq!(members.foreign_keys[1].constraint_name, "fk_user");
+        assert_eq!(members.indexes[0].column_names, vec!["org", "team"]);
+        assert_eq!(snapshot.tables[2].primary_key, vec!["org", "id"]);
+        assert_eq!(snapshot.tables[2].indexes.len(), 2);
+        assert_eq!(snapshot.tables[0].columns[0].name, "user_id");
+        assert_eq!(
+            members.indexes[0].definition.as_deref(),
+            Some("(org, team)")
+        );
+        assert_eq!(snapshot.tables[2].indexes[0].definition, None);
+        // expression indexes are kept whole, with no columns
+        let users_email = &snapshot.tables[3].indexes[0];
+        assert!(users_email.column_names.is_empty());
+        assert_eq!(
+            users_email.definition.as_deref(),
+            Some("(lower(\"e-mail\") DESC) WHERE id > 0")
+        );
+
+        let diagram = mermaid(&snapshot);
+        assert!(diagram.starts_with("erDiagram\n    member_names {\n"));
+        assert!(
+            diagram.contains(
+                "    users {\n        INTEGER id PK\n        VARCHAR_255_ e-mail\n    }\n"
+            )
+        );
+        assert!(diagram.contains("        INTEGER user_id FK\n"));
+        assert!(diagram.contains("    members }o--|| users : \"fk_user\"\n"));
+        assert!(diagram.contains("    members }o--o| teams : \"fk_0\"\n"));
+
+        let digraph = dot(&snapshot);
+        assert!(digraph.starts_with("digraph \"main\" {\n"));
+        assert!(digraph.contains("<td port=\"id\" align=\"left\">id: INTEGER (PK)</td>"));
+        assert!(
+            digraph
+                .contains("    \"members\":\"user_id\" -> \"users\":\"id\" [label=\"fk_user\"];\n")
+        );
+        assert!(digraph.contains("    \"members\" -> \"teams\" [label=\"fk_0\"];\n"));
+
+        let graph = graph(&snapshot);
+        assert_eq!(graph["nodes"].as_array().unwrap().len(), 4);
+        assert_eq!(graph["edges"][1]["source"], "main.members");
+        assert_eq!(graph["edges"][1]["target"], "main.users");
+        assert_eq!(graph["edges"][1]["optional"], false);
+    }
+}
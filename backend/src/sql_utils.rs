@@ -1,3 +1,5 @@
+use std::fmt;
+
 use crate::models::DbType;
 
 /// Validates that an identifier (table, column, or database name) contains only safe characters.
@@ -25,17 +27,83 @@ pub fn quote_identifier(name: &str, db_type: &DbType) -> String {
     }
 }
 
+/// A table named in a request. On Postgres it may be schema-qualified as "schema.table";
+/// unqualified names resolve to the session's default schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRef {
+    /// Namespace the catalogs file the table under: a Postgres schema, the MySQL database, or "main" on SQLite
+    pub schema: String,
+    pub name: String,
+    /// Whether SQL names the table with its schema, which only Postgres needs
+    qualify: bool,
+}
+
+impl TableRef {
+    /// A table as the catalogs name it, which needs no validation
+    pub fn new(schema: &str, name: &str, db_type: &DbType) -> Self {
+        Self {
+            schema: schema.to_string(),
+            name: name.to_string(),
+            qualify: matches!(db_type, DbType::Postgres),
+        }
+    }
+
+    pub fn parse(raw: &str, db_type: &DbType, default_schema: &str) -> Result<Self, String> {
+        let (schema, name) = match raw.split_once('.') {
+            Some((schema, name)) if matches!(db_type, DbType::Postgres) => {
+                if !is_valid_identifier(schema) {
+                    return Err("Invalid schema name".to_string());
+                }
+                (schema, name)
+            }
+            Some(_) => {
+                return Err(
+                    "Schema-qualified table names are only supported on PostgreSQL".to_string(),
+                );
+            }
+            None => (default_schema, raw),
+        };
+        if !is_valid_identifier(name) {
+            return Err("Invalid table name".to_string());
+        }
+
+        Ok(Self::new(schema, name, db_type))
+    }
+
+    /// The table's quoted name for use in SQL, schema-qualified on Postgres
+    pub fn quoted(&self, db_type: &DbType) -> String {
+        if self.qualify {
+            format!(
+                "{}.{}",
+                quote_identifier(&self.schema, db_type),
+                quote_identifier(&self.name, db_type)
+            )
+        } else {
+            quote_identifier(&self.name, db_type)
+        }
+    }
+}
+
+impl fmt::Display for TableRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.qualify {
+            write!(f, "{}.{}", self.schema, self.name)
+        } else {
+            write!(f, "{}", self.name)
+        }
+    }
+}
+
 /// Escapes a string value for use in a SQL string literal.
-#[allow(dead_code)]
 pub fn escape_string_literal(value: &str, db_type: &DbType) -> String {
     match db_type {
-        DbType::Postgres => {
+        DbType::Postgres | DbType::Sqlite => {
             let escaped = value.replace('\'', "''");
             format!("'{}'", escaped)
         }
-        DbType::Mysql | DbType::Sqlite => {
-            // MySQL and SQLite use doubled single quotes
-            let escaped = value.replace('\'', "''");
+        DbType::Mysql => {
+            // MySQL also treats backslash as an escape character by default
+            let escaped = value.replace('\\', "\\\\").replace('\'', "''");
             format!("'{}'", escaped)
         }
     }
@@ -73,6 +141,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_table_ref() {
+        let pg = DbType::Postgres;
+        let t = TableRef::parse("billing.invoices", &pg, "public").unwrap();
+        assert_eq!(t.schema, "billing");
+        assert_eq!(t.quoted(&pg), "\"billing\".\"invoices\"");
+        assert_eq!(t.to_string(), "billing.invoices");
+
+        let t = TableRef::parse("users", &pg, "auth").unwrap();
+        assert_eq!(t.quoted(&pg), "\"auth\".\"users\"");
+
+        let mysql = DbType::Mysql;
+        let t = TableRef::parse("users", &mysql, "app").unwrap();
+        assert_eq!(
+            (t.schema.as_str(), t.quoted(&mysql)),
+            ("app", "`users`".to_string())
+        );
+        assert!(TableRef::parse("other.users", &mysql, "app").is_err());
+
+        assert!(TableRef::parse("a.b.c", &pg, "public").is_err());
+        assert!(TableRef::parse("bad schema.t", &pg, "public").is_err());
+    }
+
     #[test]
     fn test_escape_string_literal() {
         let value = "O'Reilly";
@@ -81,5 +172,9 @@ mod tests {
             "'O''Reilly'"
         );
         assert_eq!(escape_string_literal(value, &DbType::Mysql), "'O''Reilly'");
+        assert_eq!(
+            escape_string_literal("C:\\dir", &DbType::Mysql),
+            "'C:\\\\dir'"
+        );
     }
 }
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use crate::{
+    binding::{
+        BindValue, ColumnKind, ColumnType, bind_value, fetch_column_types, get_primary_key_columns,
+        placeholder, to_bind_value,
+    },
+    models::{DbType, Session},
+    sql_utils::{TableRef, is_valid_identifier, quote_identifier},
+};
+
+/// A single write statement and the values bound to its placeholders
+pub struct Statement {
+    pub sql: String,
+    pub params: Vec<BindValue>,
+}
+
+impl Statement {
+    /// Executes the statement, returning the number of affected rows
+    pub async fn execute<'e, E>(&self, executor: E) -> Result<u64, sqlx::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Any>,
+    {
+        let mut query = sqlx::query(&self.sql);
+        for value in &self.params {
+            query = bind_value(query, value.clone());
+        }
+        Ok(query.execute(executor).await?.rows_affected())
+    }
+}
+
+/// Table metadata needed to build INSERT, UPDATE and DELETE statements for it
+pub struct TableWriter {
+    table: TableRef,
+    db_type: DbType,
+    columns: HashMap<String, ColumnType>,
+    primary_key: Vec<String>,
+}
+
+impl TableWriter {
+    /// `primary_key` lists the key columns in key order, empty if the table has none
+    pub fn new(
+        table: TableRef,
+        db_type: DbType,
+        columns: HashMap<String, ColumnType>,
+        primary_key: Vec<String>,
+    ) -> Self {
+        Self {
+            table,
+            db_type,
+            columns,
+            primary_key,
+        }
+    }
+
+    /// The table's columns and their declared types
+    pub fn columns(&self) -> &HashMap<String, ColumnType> {
+        &self.columns
+    }
+
+    pub fn insert(&self, values: &Map<String, Value>) -> Result<Statement, String> {
+        let values = self.collect_values(values)?;
+
+        let columns: Vec<String> = values
+            .iter()
+            .map(|(col, _)| quote_identifier(col, &self.db_type))
+            .collect();
+        let placeholders: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, (col, _))| placeholder(&self.db_type, i + 1, &self.columns[col]))
+            .collect();
+
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            self.table.quoted(&self.db_type),
+            columns.join(", "),
+            placeholders.join(", ")
+        );
+        Ok(Statement {
+            sql,
+            params: values.into_iter().map(|(_, value)| value).collect(),
+        })
+    }
+
+    pub fn update(
+        &self,
+        key: &Map<String, Value>,
+        values: &Map<String, Value>,
+        match_full_row: bool,
+    ) -> Result<Statement, String> {
+        let values = self.collect_values(values)?;
+        let (key_condition, key_params) =
+            self.key_condition(key, match_full_row, values.len() + 1)?;
+
+        let set_clauses: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, (col, _))| {
+                format!(
+                    "{} = {}",
+                    quote_identifier(col, &self.db_type),
+                    placeholder(&self.db_type, i + 1, &self.columns[col])
+                )
+            })
+            .collect();
+
+        let sql = format!(
+            "UPDATE {} SET {} WHERE {}",
+            self.table.quoted(&self.db_type),
+            set_clauses.join(", "),
+            key_condition
+        );
+        Ok(Statement {
+            sql,
+            params: values
+                .into_iter()
+                .map(|(_, value)| value)
+                .chain(key_params)
+                .collect(),
+        })
+    }
+
+    pub fn delete(
+        &self,
+        key: &Map<String, Value>,
+        match_full_row: bool,
+    ) -> Result<Statement, String> {
+        let (key_condition, key_params) = self.key_condition(key, match_full_row, 1)?;
+        let sql = format!(
+            "DELETE FROM {} WHERE {}",
+            self.table.quoted(&self.db_type),
+            key_condition
+        );
+        Ok(Statement {
+            sql,
+            params: key_params,
+        })
+    }
+
+    /// Converts a JSON object into (column, value) pairs typed against the table's columns
+    fn collect_values(&self, obj: &Map<String, Value>) -> Result<Vec<(String, BindValue)>, String> {
+        if obj.is_empty() {
+            return Err("No data provided".to_string());
+        }
+
+        let mut values = Vec::new();
+        for (col, val) in obj.iter() {
+            if !is_valid_identifier(col) {
+                return Err(format!("Invalid column name: {}", col));
+            }
+            let column = self
+                .columns
+                .get(col)
+                .ok_or_else(|| format!("Unknown column: {}", col))?;
+            let value = to_bind_value(val, column.kind, &self.db_type)
+                .map_err(|e| format!("Invalid value for column '{}': {}", col, e))?;
+            values.push((col.clone(), value));
+        }
+        Ok(values)
+    }
+
+    /// Builds the WHERE expression identifying one row from a key map, numbering placeholders
+    /// from `first_index`. With a primary key the map must hold exactly its columns; tables
+    /// without one are refused unless `match_full_row` is set and the map holds every column.
+    /// A full-row match leaves out json columns, which PostgreSQL can't compare, and
+    /// floating-point ones, which rarely compare equal after a round trip; their values are
+    /// ignored.
+    fn key_condition(
+        &self,
+        key: &Map<String, Value>,
+        match_full_row: bool,
+        first_index: usize,
+    ) -> Result<(String, Vec<BindValue>), String> {
+        let key_cols: Vec<&String> = if self.primary_key.is_empty() {
+            if !match_full_row {
+                return Err(format!(
+                    "Table '{}' has no primary key; set match_full_row and pass every column value to identify a row",
+                    self.table
+                ));
+            }
+            let mut cols: Vec<&String> = self
+                .columns
+                .iter()
+                .filter(|(_, column)| !matches!(column.kind, ColumnKind::Json | ColumnKind::Float))
+                .map(|(col, _)| col)
+                .collect();
+            if cols.is_empty() {
+                return Err(format!(
+                    "Table '{}' has no primary key and only json or floating-point columns, which can't identify a row",
+                    self.table
+                ));
+            }
+            cols.sort();
+            cols
+        } else {
+            self.primary_key.iter().collect()
+        };
+
+        let full_row = self.primary_key.is_empty();
+        if let Some(extra) = key
+            .keys()
+            .find(|col| !(key_cols.contains(col) || full_row && self.columns.contains_key(*col)))
+        {
+            return Err(format!("Column '{}' is not part of the row key", extra));
+        }
+
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        for col in key_cols {
+            let value = key
+                .get(col)
+                .ok_or_else(|| format!("Missing key column '{}'", col))?;
+            let quoted = quote_identifier(col, &self.db_type);
+            if value.is_null() {
+                conditions.push(format!("{} IS NULL", quoted));
+                continue;
+            }
+            let column = &self.columns[col];
+            let bound = to_bind_value(value, column.kind, &self.db_type)
+                .map_err(|e| format!("Invalid key value for column '{}': {}", col, e))?;
+            params.push(bound);
+            conditions.push(format!(
+                "{} = {}",
+                quoted,
+                placeholder(&self.db_type, first_index + params.len() - 1, column)
+            ));
+        }
+
+        Ok((conditions.join(" AND "), params))
+    }
+}
+
+/// Loads the column types and primary key needed to write to a table
+pub async fn load_writer(session: &Session, table: &TableRef) -> Result<TableWriter, String> {
+    let column_types = fetch_column_types(&session.pool, &session.db_type, table).await?;
+    let primary_key = get_primary_key_columns(&session.pool, &session.db_type, table)
+        .await
+        .map_err(|e| format!("Failed to determine primary key: {}", e))?;
+
+    Ok(TableWriter::new(
+        table.clone(),
+        session.db_type.clone(),
+        column_types,
+        primary_key,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::binding::classify;
+
+    fn writer(db_type: DbType, defs: &[(&str, &str)], primary_key: &[&str]) -> TableWriter {
+        let columns = defs
+            .iter()
+            .map(|(name, data_type)| {
+                (
+                    name.to_string(),
+                    ColumnType {
+                        data_type: data_type.to_string(),
+                        kind: classify(data_type, &db_type),
+                        nullable: true,
+                    },
+                )
+            })
+            .collect();
+        let primary_key = primary_key.iter().map(|c| c.to_string()).collect();
+        let table = TableRef::new("public", "t", &db_type);
+        TableWriter::new(table, db_type, columns, primary_key)
+    }
+
+    fn map(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_update_composite_key() {
+        let w = writer(
+            DbType::Postgres,
+            &[("a", "integer"), ("b", "text"), ("v", "integer")],
+            &["a", "b"],
+        );
+        let stmt = w
+            .update(
+                &map(json!({"a": 1, "b": "x"})),
+                &map(json!({"v": 2})),
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            stmt.sql,
+            "UPDATE \"public\".\"t\" SET \"v\" = CAST($1 AS integer) \
+             WHERE \"a\" = CAST($2 AS integer) AND \"b\" = CAST($3 AS text)"
+        );
+        assert_eq!(stmt.params.len(), 3);
+
+        // every key column is required, and nothing else
+        assert!(w.delete(&map(json!({"a": 1})), false).is_err());
+        assert!(
+            w.delete(&map(json!({"a": 1, "b": "x", "v": 2})), false)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_full_row_match() {
+        let w = writer(DbType::Sqlite, &[("n", "INTEGER"), ("t", "TEXT")], &[]);
+        let key = map(json!({"n": 1, "t": null}));
+        assert!(w.delete(&key, false).is_err());
+
+        let stmt = w.delete(&key, true).unwrap();
+        assert_eq!(
+            stmt.sql,
+            "DELETE FROM \"t\" WHERE \"n\" = ? AND \"t\" IS NULL"
+        );
+        assert_eq!(stmt.params, vec![BindValue::Int(1)]);
+
+        assert!(w.delete(&map(json!({"n": 1})), true).is_err());
+    }
+
+    #[test]
+    fn test_full_row_match_skips_json_and_float() {
+        let w = writer(
+            DbType::Postgres,
+            &[
+                ("id", "integer"),
+                ("doc", "json"),
+                ("score", "double precision"),
+            ],
+            &[],
+        );
+        let key = map(json!({"id": 1, "doc": {"a": 1}, "score": 0.1}));
+        let stmt = w.delete(&key, true).unwrap();
+        assert_eq!(
+            stmt.sql,
+            "DELETE FROM \"public\".\"t\" WHERE \"id\" = CAST($1 AS integer)"
+        );
+        assert_eq!(stmt.params, vec![BindValue::Int(1)]);
+
+        let w = writer(
+            DbType::Postgres,
+            &[("doc", "jsonb"), ("score", "real")],
+            &[],
+        );
+        assert!(
+            w.delete(&map(json!({"doc": null, "score": 1})), true)
+                .is_err()
+        );
+    }
+}
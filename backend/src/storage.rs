@@ -0,0 +1,187 @@
+use sqlx::{AnyPool, Row};
+
+use crate::models::{DatabaseStats, DbType, TableStorage};
+
+/// Size, encoding and collation of the session's database, with the server's uptime and
+/// connection counts
+pub async fn fetch_database_stats(
+    pool: &AnyPool,
+    db_type: &DbType,
+    database: &str,
+) -> Result<DatabaseStats, String> {
+    let sql = match db_type {
+        DbType::Postgres => "
+            SELECT
+                pg_database_size(d.oid) AS size_bytes,
+                pg_encoding_to_char(d.encoding)::text AS encoding,
+                d.datcollate::text AS collation,
+                EXTRACT(EPOCH FROM now() - pg_postmaster_start_time())::bigint AS uptime_seconds,
+                (SELECT count(*) FROM pg_stat_activity WHERE backend_type = 'client backend')
+                    AS connections,
+                current_setting('max_connections')::bigint AS max_connections
+            FROM pg_database d
+            WHERE d.datname = current_database()
+        "
+        .to_string(),
+        DbType::Mysql => "
+            SELECT
+                (SELECT CAST(COALESCE(SUM(DATA_LENGTH + INDEX_LENGTH), 0) AS SIGNED)
+                 FROM information_schema.TABLES
+                 WHERE TABLE_SCHEMA = ?) AS size_bytes,
+                CAST(s.DEFAULT_CHARACTER_SET_NAME AS CHAR) AS encoding,
+                CAST(s.DEFAULT_COLLATION_NAME AS CHAR) AS `collation`,
+                (SELECT CAST(VARIABLE_VALUE AS SIGNED)
+                 FROM performance_schema.global_status
+                 WHERE VARIABLE_NAME = 'Uptime') AS uptime_seconds,
+                (SELECT CAST(VARIABLE_VALUE AS SIGNED)
+                 FROM performance_schema.global_status
+                 WHERE VARIABLE_NAME = 'Threads_connected') AS connections,
+                CAST(@@max_connections AS SIGNED) AS max_connections
+            FROM information_schema.SCHEMATA s
+            WHERE s.SCHEMA_NAME = ?
+        "
+        .to_string(),
+        // text compares bytewise unless a column says otherwise; there is no server
+        DbType::Sqlite => "
+            SELECT
+                (SELECT page_count FROM pragma_page_count())
+                    * (SELECT page_size FROM pragma_page_size()) AS size_bytes,
+                (SELECT encoding FROM pragma_encoding()) AS encoding,
+                'BINARY' AS collation,
+                NULL AS uptime_seconds,
+                NULL AS connections,
+                NULL AS max_connections
+        "
+        .to_string(),
+    };
+
+    let mut query = sqlx::query(&sql);
+    if matches!(db_type, DbType::Mysql) {
+        query = query.bind(database).bind(database);
+    }
+    let row = query.fetch_one(pool).await.map_err(|e| e.to_string())?;
+    Ok(DatabaseStats {
+        size_bytes: row.try_get("size_bytes").unwrap_or(None),
+        encoding: row.try_get("encoding").unwrap_or(None),
+        collation: row.try_get("collation").unwrap_or(None),
+        uptime_seconds: row.try_get("uptime_seconds").unwrap_or(None),
+        connections: row.try_get("connections").unwrap_or(None),
+        max_connections: row.try_get("max_connections").unwrap_or(None),
+    })
+}
+
+/// Data and index sizes of a schema's tables, largest first. Materialized views count as
+/// tables on PostgreSQL; SQLite sizes are read from the dbstat virtual table.
+pub async fn fetch_table_storage(
+    pool: &AnyPool,
+    db_type: &DbType,
+    schema: &str,
+) -> Result<Vec<TableStorage>, String> {
+    let sql = match db_type {
+        DbType::Postgres => "
+            SELECT
+                c.relname::text AS name,
+                pg_table_size(c.oid) AS data_bytes,
+                pg_indexes_size(c.oid) AS index_bytes,
+                pg_total_relation_size(c.oid) AS total_bytes,
+                CASE WHEN c.reltuples < 0 THEN NULL ELSE c.reltuples::bigint END AS row_estimate
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = $1 AND c.relkind IN ('r', 'p', 'm')
+            ORDER BY total_bytes DESC, name
+        "
+        .to_string(),
+        DbType::Mysql => "
+            SELECT
+                CAST(TABLE_NAME AS CHAR) AS name,
+                CAST(COALESCE(DATA_LENGTH, 0) AS SIGNED) AS data_bytes,
+                CAST(COALESCE(INDEX_LENGTH, 0) AS SIGNED) AS index_bytes,
+                CAST(COALESCE(DATA_LENGTH, 0) + COALESCE(INDEX_LENGTH, 0) AS SIGNED)
+                    AS total_bytes,
+                CAST(TABLE_ROWS AS SIGNED) AS row_estimate
+            FROM information_schema.TABLES
+            WHERE TABLE_SCHEMA = ? AND TABLE_TYPE = 'BASE TABLE'
+            ORDER BY total_bytes DESC, name
+        "
+        .to_string(),
+        // a table's pages are filed under its name, those of its indexes under theirs
+        DbType::Sqlite => "
+            SELECT
+                t.name AS name,
+                COALESCE(SUM(CASE WHEN s.name = t.name THEN s.pgsize END), 0) AS data_bytes,
+                COALESCE(SUM(CASE WHEN s.name <> t.name THEN s.pgsize END), 0) AS index_bytes,
+                COALESCE(SUM(s.pgsize), 0) AS total_bytes,
+                NULL AS row_estimate
+            FROM sqlite_schema t
+            JOIN sqlite_schema o ON o.tbl_name = t.name AND o.type IN ('table', 'index')
+            LEFT JOIN dbstat s ON s.name = o.name
+            WHERE t.type = 'table' AND t.name NOT LIKE 'sqlite_%'
+            GROUP BY t.name
+            ORDER BY total_bytes DESC, t.name
+        "
+        .to_string(),
+    };
+
+    let result = if matches!(db_type, DbType::Sqlite) {
+        sqlx::query(&sql).fetch_all(pool).await
+    } else {
+        sqlx::query(&sql).bind(schema).fetch_all(pool).await
+    };
+    let rows = result.map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| TableStorage {
+            name: row.try_get("name").unwrap_or_default(),
+            data_bytes: row.try_get("data_bytes").unwrap_or(0),
+            index_bytes: row.try_get("index_bytes").unwrap_or(0),
+            total_bytes: row.try_get("total_bytes").unwrap_or(0),
+            row_estimate: row.try_get("row_estimate").unwrap_or(None),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::any::AnyPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sqlite_storage() {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let setup = "
+            CREATE TABLE small (id INTEGER PRIMARY KEY);
+            CREATE TABLE big (id INTEGER PRIMARY KEY, body TEXT UNIQUE);
+            CREATE INDEX big_body ON big (body);
+            WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2000)
+            INSERT INTO big (body) SELECT printf('%.100c-%d', 'x', i) FROM n;
+        ";
+        sqlx::raw_sql(setup).execute(&pool).await.unwrap();
+
+        let stats = fetch_database_stats(&pool, &DbType::Sqlite, "main")
+            .await
+            .unwrap();
+        assert_eq!(stats.encoding.as_deref(), Some("UTF-8"));
+        assert_eq!(stats.uptime_seconds, None);
+        let size = stats.size_bytes.unwrap();
+
+        let tables = fetch_table_storage(&pool, &DbType::Sqlite, "main")
+            .await
+            .unwrap();
+        let names: Vec<&str> = tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["big", "small"]);
+        let big = &tables[0];
+        assert!(big.data_bytes > 200_000);
+        // the unique constraint's autoindex and big_body
+        assert!(big.index_bytes > 2 * 200_000);
+        assert_eq!(big.total_bytes, big.data_bytes + big.index_bytes);
+        assert!(big.total_bytes <= size);
+        assert_eq!(big.row_estimate, None);
+    }
+}
@@ -20,9 +20,9 @@ import type { ColumnInfo, TableRow as RowData } from '@/models';
 
 // FK info for column indicators
 interface ForeignKeyInfo {
-	column_name: string;
+	columns: string[];
 	foreign_table: string;
-	foreign_column: string;
+	foreign_columns: string[];
 }
 
 interface DataGridProps {
@@ -50,13 +50,17 @@ export function DataGrid({
 	const someSelected =
 		selectedRows.size > 0 && selectedRows.size < rows.length;
 
-	// Create a map of column name to FK info for quick lookup
+	// Create a map of column name to its FK target for quick lookup
 	const fkMap = foreignKeys.reduce(
 		(acc, fk) => {
-			acc[fk.column_name] = fk;
+			fk.columns.forEach((column, i) => {
+				acc[column] = fk.foreign_columns[i]
+					? `${fk.foreign_table}.${fk.foreign_columns[i]}`
+					: fk.foreign_table;
+			});
 			return acc;
 		},
-		{} as Record<string, ForeignKeyInfo>,
+		{} as Record<string, string>,
 	);
 
 	// Use primary key as unique identifier, fallback to index
@@ -105,7 +109,7 @@ export function DataGrid({
 										{column.name}
 										{fkMap[column.name] && (
 											<span
-												title={`→ ${fkMap[column.name].foreign_table}.${fkMap[column.name].foreign_column}`}
+												title={`→ ${fkMap[column.name]}`}
 												className='text-duck-primary-400 cursor-help'
 											>
 												<Link2 className='w-3 h-3' />
@@ -2,9 +2,9 @@ import { useState } from 'react';
 import { ChevronDown, ChevronRight, Link2 } from 'lucide-react';
 
 interface ForeignKeyInfo {
-	column_name: string;
+	columns: string[];
 	foreign_table: string;
-	foreign_column: string;
+	foreign_columns: string[];
 }
 
 interface RelationshipsPanelProps {
@@ -43,7 +43,7 @@ export function RelationshipsPanel({ foreignKeys }: RelationshipsPanelProps) {
 							className='flex items-center gap-2 text-duck-sm bg-duck-dark-500/50 p-2 rounded border border-duck-dark-400/30'
 						>
 							<span className='text-duck-primary-400 font-mono'>
-								{fk.column_name}
+								{fk.columns.join(', ')}
 							</span>
 							<span className='text-duck-white-700'>
 								refers to
@@ -52,10 +52,16 @@ export function RelationshipsPanel({ foreignKeys }: RelationshipsPanelProps) {
 								<span className='text-duck-white-200 font-medium'>
 									{fk.foreign_table}
 								</span>
-								<span className='text-duck-white-500'>.</span>
-								<span className='text-duck-white-200 font-medium'>
-									{fk.foreign_column}
-								</span>
+								{fk.foreign_columns.length > 0 && (
+									<>
+										<span className='text-duck-white-500'>
+											.
+										</span>
+										<span className='text-duck-white-200 font-medium'>
+											{fk.foreign_columns.join(', ')}
+										</span>
+									</>
+								)}
 							</div>
 						</div>
 					))}
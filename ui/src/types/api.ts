@@ -60,9 +60,25 @@ export interface IndexInfo {
 
 export interface ForeignKeyInfo {
     constraint_name: string;
-    column_name: string;
+    columns: string[];
+    foreign_schema: string;
     foreign_table: string;
-    foreign_column: string;
+    foreign_columns: string[];
+    on_update: string;
+    on_delete: string;
+    deferrable: boolean;
+    initially_deferred: boolean;
+}
+
+export interface ForeignKeyConstraintDefinition {
+    name?: string;
+    columns: string[];
+    target_table: string;
+    target_columns: string[];
+    on_delete?: string;
+    on_update?: string;
+    deferrable?: boolean;
+    initially_deferred?: boolean;
 }
 
 // schema write types
@@ -84,7 +100,9 @@ export type AlterTableRequest =
     | { alter_type: 'AddColumn'; column_definition: ColumnDefinition }
     | { alter_type: 'DropColumn'; column_name: string }
     | { alter_type: 'ModifyColumn'; old_column_name: string; column_definition: ColumnDefinition }
-    | { alter_type: 'RenameColumn'; old_column_name: string; column_name: string };
+    | { alter_type: 'RenameColumn'; old_column_name: string; column_name: string }
+    | { alter_type: 'AddForeignKey'; foreign_key: ForeignKeyConstraintDefinition }
+    | { alter_type: 'DropForeignKey'; constraint_name: string };
 
 // data types (CRUD)
 export interface PaginatedRows {